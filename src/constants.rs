@@ -89,6 +89,35 @@ crate::attr_group! {
     // END USED BY process/capabilities.rs
 
 
+    // BEGIN USED BY prctl.rs
+    pub const SUID_DUMP_DISABLE: Int = 0;
+    pub const SUID_DUMP_USER: Int = 1;
+    pub const SUID_DUMP_ROOT: Int = 2;
+
+    pub const PR_TSC_ENABLE: Int = 1;
+    pub const PR_TSC_SIGSEGV: Int = 2;
+
+    pub const PR_GET_SPECULATION_CTRL: Int = 52;
+    pub const PR_SET_SPECULATION_CTRL: Int = 53;
+
+    pub const PR_SPEC_STORE_BYPASS: Ulong = 0;
+    pub const PR_SPEC_INDIRECT_BRANCH: Ulong = 1;
+    pub const PR_SPEC_L1D_FLUSH: Ulong = 2;
+
+    pub const PR_SPEC_PRCTL: Ulong = 1 << 0;
+    pub const PR_SPEC_ENABLE: Ulong = 1 << 1;
+    pub const PR_SPEC_DISABLE: Ulong = 1 << 2;
+    pub const PR_SPEC_FORCE_DISABLE: Ulong = 1 << 3;
+    pub const PR_SPEC_DISABLE_NOEXEC: Ulong = 1 << 4;
+
+    pub const PR_SET_IO_FLUSHER: Int = 57;
+    pub const PR_GET_IO_FLUSHER: Int = 58;
+
+    pub const PR_SET_VMA: Int = 0x5356_4d41;
+    pub const PR_SET_VMA_ANON_NAME: Ulong = 0;
+    // END USED BY prctl.rs
+
+
     // BEGIN USED by inotify.rs
     pub const IN_EXCL_UNLINK: u32 = 0x0400_0000;
     pub const IN_MASK_ADD: u32 = 0x2000_0000;
@@ -118,6 +147,51 @@ crate::attr_group! {
     pub const CLD_STOPPED: Int = 5;
     pub const CLD_CONTINUED: Int = 6;
     // END USED BY wait.rs
+
+    // BEGIN USED BY siginfo.rs
+    pub const SEGV_MAPERR: Int = 1;
+    pub const SEGV_ACCERR: Int = 2;
+
+    pub const POLL_IN: Int = 1;
+    pub const POLL_OUT: Int = 2;
+    pub const POLL_MSG: Int = 3;
+    pub const POLL_ERR: Int = 4;
+    pub const POLL_PRI: Int = 5;
+    pub const POLL_HUP: Int = 6;
+
+    pub const SYS_SECCOMP: Int = 1;
+    // END USED BY siginfo.rs
+
+    // BEGIN USED BY io_uring.rs
+    pub const IORING_OFF_SQ_RING: i64 = 0;
+    pub const IORING_OFF_CQ_RING: i64 = 0x0800_0000;
+    pub const IORING_OFF_SQES: i64 = 0x1000_0000;
+
+    pub const IORING_ENTER_GETEVENTS: u32 = 1 << 0;
+    pub const IORING_ENTER_EXT_ARG: u32 = 1 << 3;
+
+    pub const IORING_FEAT_SINGLE_MMAP: u32 = 1 << 0;
+    pub const IORING_FEAT_NODROP: u32 = 1 << 1;
+    pub const IORING_FEAT_SUBMIT_STABLE: u32 = 1 << 2;
+    pub const IORING_FEAT_RW_CUR_POS: u32 = 1 << 3;
+    pub const IORING_FEAT_CUR_PERSONALITY: u32 = 1 << 4;
+    pub const IORING_FEAT_FAST_POLL: u32 = 1 << 5;
+    pub const IORING_FEAT_POLL_32BITS: u32 = 1 << 6;
+    pub const IORING_FEAT_SQPOLL_NONFIXED: u32 = 1 << 7;
+    pub const IORING_FEAT_EXT_ARG: u32 = 1 << 8;
+    pub const IORING_FEAT_NATIVE_WORKERS: u32 = 1 << 9;
+
+    pub const IORING_OP_POLL_ADD: u8 = 6;
+    pub const IORING_OP_POLL_REMOVE: u8 = 7;
+    pub const IORING_OP_ACCEPT: u8 = 13;
+    pub const IORING_OP_CLOSE: u8 = 19;
+    pub const IORING_OP_READ: u8 = 22;
+    pub const IORING_OP_WRITE: u8 = 23;
+
+    pub const IORING_POLL_ADD_MULTI: u32 = 1 << 0;
+
+    pub const IORING_CQE_F_MORE: u32 = 1 << 1;
+    // END USED BY io_uring.rs
 }
 
 crate::attr_group! {
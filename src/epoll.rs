@@ -2,9 +2,12 @@ use std::convert::TryInto;
 use std::io;
 use std::time;
 use std::os::unix::prelude::*;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 use bitflags::bitflags;
 
+use crate::pollers::Token;
+use crate::types::kernel_timespec;
 use crate::Int;
 
 #[derive(Debug, Copy, Clone)]
@@ -19,13 +22,25 @@ bitflags! {
     #[repr(transparent)]
     pub struct Events: u32 {
         const IN = libc::EPOLLIN as u32;
+        const PRI = libc::EPOLLPRI as u32;
         const OUT = libc::EPOLLOUT as u32;
         const ERR = libc::EPOLLERR as u32;
         const ET = libc::EPOLLET as u32;
         const HUP = libc::EPOLLHUP as u32;
         const RDHUP = libc::EPOLLRDHUP as u32;
         const ONESHOT = libc::EPOLLONESHOT as u32;
+        /// Prevent the system from suspending while events are pending for this file descriptor.
+        ///
+        /// This is silently ignored if the process does not have the `CAP_BLOCK_SUSPEND`
+        /// capability.
         const WAKEUP = libc::EPOLLWAKEUP as u32;
+        /// Set an exclusive wakeup mode for this file descriptor. If the same target file is
+        /// registered with several `Epoll`s using this flag, only one (or more) of them will be
+        /// woken up when an event occurs, which avoids the "thundering herd" problem.
+        ///
+        /// This can only be used with `add()` (not `modify()`), and only in combination with
+        /// `IN`, `OUT`, `ERR`, `HUP`, `WAKEUP`, and `ET`; otherwise the call fails with
+        /// `EINVAL`.
         const EXCLUSIVE = libc::EPOLLEXCLUSIVE as u32;
     }
 }
@@ -101,6 +116,15 @@ pub struct Event {
     pub data: u64,
 }
 
+impl Event {
+    /// Interpret the `data` field as a token that was passed to `Epoll::add_token()` or
+    /// `Epoll::modify_token()`.
+    #[inline]
+    pub fn token<T: Token>(&self) -> T {
+        T::from_u64(self.data)
+    }
+}
+
 impl Default for Event {
     #[inline]
     fn default() -> Self {
@@ -116,6 +140,12 @@ impl Default for Event {
 #[cfg(not(target_arch = "x86_64"))]
 pub type RawEvent = Event;
 
+// Set if epoll_pwait2() fails with ENOSYS, so we can skip straight to epoll_pwait()
+static PWAIT2_UNSUPPORTED: AtomicBool = AtomicBool::new(false);
+
+// The size of the kernel's sigset_t (_NSIG / 8)
+const KERNEL_SIGSET_SIZE: usize = 8;
+
 #[derive(Debug)]
 pub struct Epoll {
     fd: Int,
@@ -174,6 +204,18 @@ impl Epoll {
         self.ctl(CtlOp::Mod, fd, events, data)
     }
 
+    /// Like `add3()`, but stores a typed token (which can be retrieved with `Event::token()`).
+    #[inline]
+    pub fn add_token<T: Token>(&mut self, fd: Int, events: Events, token: T) -> io::Result<()> {
+        self.add3(fd, events, token.into_u64())
+    }
+
+    /// Like `modify3()`, but stores a typed token (which can be retrieved with `Event::token()`).
+    #[inline]
+    pub fn modify_token<T: Token>(&mut self, fd: Int, events: Events, token: T) -> io::Result<()> {
+        self.modify3(fd, events, token.into_u64())
+    }
+
     #[cfg(target_arch = "x86_64")]
     pub fn pwait(
         &self,
@@ -218,6 +260,15 @@ impl Epoll {
             None => -1,
         };
 
+        self.pwait_millis(events, raw_timeout, sigmask)
+    }
+
+    fn pwait_millis(
+        &self,
+        events: &mut [RawEvent],
+        raw_timeout: Int,
+        sigmask: Option<crate::signal::Sigset>,
+    ) -> io::Result<usize> {
         let raw_sigmask = crate::internal::ptr_from_opt_ref(sigmask.as_ref().map(crate::signal::Sigset::as_ref));
 
         let n = crate::error::convert_neg_ret(unsafe {
@@ -233,6 +284,102 @@ impl Epoll {
         Ok(n as usize)
     }
 
+    /// Like `pwait()`, but uses `epoll_pwait2()` (see `pwait2_raw()` for details).
+    #[cfg(target_arch = "x86_64")]
+    pub fn pwait2(
+        &self,
+        events: &mut [Event],
+        timeout: Option<time::Duration>,
+        sigmask: Option<crate::signal::Sigset>,
+    ) -> io::Result<usize> {
+        let mut ep_events = Vec::new();
+        ep_events.resize(
+            events.len(),
+            RawEvent {
+                events: Events::empty(),
+                data: 0,
+            },
+        );
+
+        let res = self.pwait2_raw(&mut ep_events, timeout, sigmask)?;
+
+        for i in 0..res {
+            events[i] = Event {
+                events: ep_events[i].events,
+                data: ep_events[i].data,
+            };
+        }
+
+        Ok(res)
+    }
+
+    /// Like `pwait()`, but uses `epoll_pwait2()` (see `pwait2_raw()` for details).
+    #[cfg(not(target_arch = "x86_64"))]
+    #[inline]
+    pub fn pwait2(
+        &self,
+        events: &mut [Event],
+        timeout: Option<time::Duration>,
+        sigmask: Option<crate::signal::Sigset>,
+    ) -> io::Result<usize> {
+        self.pwait2_raw(events, timeout, sigmask)
+    }
+
+    /// Like `pwait_raw()`, but uses `epoll_pwait2()`, which accepts a timeout with nanosecond
+    /// precision (instead of truncating it to milliseconds).
+    ///
+    /// `epoll_pwait2()` was added in Linux 5.11. On older kernels, this falls back to
+    /// `epoll_pwait()`, rounding the timeout *up* to the nearest millisecond (so that it never
+    /// returns before the timeout has expired).
+    pub fn pwait2_raw(
+        &self,
+        events: &mut [RawEvent],
+        timeout: Option<time::Duration>,
+        sigmask: Option<crate::signal::Sigset>,
+    ) -> io::Result<usize> {
+        if !PWAIT2_UNSUPPORTED.load(Ordering::Relaxed) {
+            // epoll_pwait2() takes a struct __kernel_timespec (with a 64-bit tv_sec), even on
+            // 32-bit platforms where libc::timespec has a 32-bit time_t
+            let raw_timeout = timeout.map(|t| kernel_timespec {
+                tv_sec: t.as_secs().try_into().unwrap_or(i64::MAX),
+                tv_nsec: t.subsec_nanos() as i64,
+            });
+
+            let raw_sigmask = crate::internal::ptr_from_opt_ref(
+                sigmask.as_ref().map(crate::signal::Sigset::as_ref),
+            );
+
+            match crate::error::convert_neg_ret(unsafe {
+                libc::syscall(
+                    libc::SYS_epoll_pwait2,
+                    self.fd,
+                    events.as_mut_ptr() as *mut libc::epoll_event,
+                    events.len() as Int,
+                    crate::internal::ptr_from_opt_ref(raw_timeout.as_ref()),
+                    raw_sigmask,
+                    KERNEL_SIGSET_SIZE,
+                )
+            }) {
+                Ok(n) => return Ok(n as usize),
+                Err(e) if crate::error::is_raw(&e, libc::ENOSYS) => {
+                    PWAIT2_UNSUPPORTED.store(true, Ordering::Relaxed);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        let raw_timeout: Int = match timeout {
+            Some(t) => t
+                .as_nanos()
+                .div_ceil(1_000_000)
+                .try_into()
+                .unwrap_or(Int::MAX),
+            None => -1,
+        };
+
+        self.pwait_millis(events, raw_timeout, sigmask)
+    }
+
     #[inline]
     pub fn wait(&self, events: &mut [Event], timeout: Option<time::Duration>) -> io::Result<usize> {
         self.pwait(events, timeout, None)
@@ -264,7 +411,7 @@ impl Drop for Epoll {
 mod tests {
     use super::*;
 
-    use std::io::Write;
+    use std::io::{Read, Write};
 
     #[test]
     fn test_default() {
@@ -349,4 +496,128 @@ mod tests {
         assert_eq!({raw_events[0].data}, w2.as_raw_fd() as u64);
         assert_eq!({raw_events[0].events}, Events::IN);
     }
+
+    #[test]
+    fn test_epoll_pwait2() {
+        let mut poller = Epoll::new().unwrap();
+        let mut events = [Event::default(); 2];
+        let timeout = time::Duration::from_micros(1500);
+
+        let (r1, mut w1) = crate::pipe().unwrap();
+        poller.add(r1.as_raw_fd(), Events::IN).unwrap();
+
+        let orig_unsupported = PWAIT2_UNSUPPORTED.load(Ordering::Relaxed);
+
+        // Test both epoll_pwait2() (if it's supported) and the fallback
+        for &unsupported in [false, true].iter() {
+            PWAIT2_UNSUPPORTED.store(unsupported || orig_unsupported, Ordering::Relaxed);
+
+            let start = time::Instant::now();
+            assert_eq!(poller.pwait2(&mut events, Some(timeout), None).unwrap(), 0);
+            assert!(start.elapsed() >= timeout);
+
+            w1.write_all(b"a").unwrap();
+            assert_eq!(poller.pwait2(&mut events, None, None).unwrap(), 1);
+            assert_eq!(events[0].data, r1.as_raw_fd() as u64);
+            assert_eq!(events[0].events, Events::IN);
+
+            // Huge timeouts are clamped rather than wrapping around
+            assert_eq!(
+                poller
+                    .pwait2(&mut events, Some(time::Duration::MAX), None)
+                    .unwrap(),
+                1
+            );
+
+            let mut raw_events = [RawEvent::default(); 2];
+            assert_eq!(
+                poller
+                    .pwait2_raw(&mut raw_events, Some(time::Duration::from_secs(0)), None)
+                    .unwrap(),
+                1
+            );
+            assert_eq!({ raw_events[0].data }, r1.as_raw_fd() as u64);
+
+            let mut buf = [0; 1];
+            (&r1).read_exact(&mut buf).unwrap();
+        }
+
+        PWAIT2_UNSUPPORTED.store(orig_unsupported, Ordering::Relaxed);
+    }
+
+    #[test]
+    fn test_epoll_exclusive() {
+        let mut poller = Epoll::new().unwrap();
+        let mut events = [Event::default(); 2];
+
+        let (r1, mut w1) = crate::pipe().unwrap();
+
+        // Not allowed with ONESHOT
+        assert!(crate::error::is_raw(
+            &poller
+                .add(
+                    r1.as_raw_fd(),
+                    Events::IN | Events::EXCLUSIVE | Events::ONESHOT
+                )
+                .unwrap_err(),
+            libc::EINVAL
+        ));
+
+        poller
+            .add(
+                r1.as_raw_fd(),
+                Events::IN | Events::EXCLUSIVE | Events::WAKEUP,
+            )
+            .unwrap();
+
+        // Can't be modified
+        assert!(crate::error::is_raw(
+            &poller.modify(r1.as_raw_fd(), Events::IN).unwrap_err(),
+            libc::EINVAL
+        ));
+
+        w1.write_all(b"a").unwrap();
+        assert_eq!(
+            poller
+                .wait(&mut events, Some(time::Duration::from_secs(0)))
+                .unwrap(),
+            1
+        );
+        assert!(events[0].events.contains(Events::IN));
+    }
+
+    #[test]
+    fn test_epoll_token() {
+        #[derive(Copy, Clone, Debug, PartialEq)]
+        struct Key(u32);
+
+        impl Token for Key {
+            fn into_u64(self) -> u64 {
+                self.0 as u64
+            }
+
+            fn from_u64(data: u64) -> Self {
+                Key(data as u32)
+            }
+        }
+
+        let mut poller = Epoll::new().unwrap();
+        let mut events = [Event::default(); 2];
+        let timeout_0 = Some(time::Duration::from_secs(0));
+
+        let (r1, mut w1) = crate::pipe().unwrap();
+
+        poller
+            .add_token(r1.as_raw_fd(), Events::IN, Key(7))
+            .unwrap();
+        w1.write_all(b"a").unwrap();
+        assert_eq!(poller.wait(&mut events, timeout_0).unwrap(), 1);
+        assert_eq!(events[0].token::<Key>(), Key(7));
+
+        poller
+            .modify_token(r1.as_raw_fd(), Events::IN, Key(8))
+            .unwrap();
+        assert_eq!(poller.wait(&mut events, timeout_0).unwrap(), 1);
+        assert_eq!(events[0].token::<Key>(), Key(8));
+    }
 }
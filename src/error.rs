@@ -18,6 +18,19 @@ pub fn set_errno_success() {
     }
 }
 
+// Used to preserve errno in signal handlers
+#[inline]
+pub(crate) fn get_errno() -> i32 {
+    unsafe { *errno_mut_ptr() }
+}
+
+#[inline]
+pub(crate) fn set_errno(errno: i32) {
+    unsafe {
+        *errno_mut_ptr() = errno;
+    }
+}
+
 #[inline]
 pub fn convert_ret<T>(ret: T) -> io::Result<T>
 where
@@ -0,0 +1,132 @@
+use std::io;
+use std::os::unix::prelude::*;
+
+use crate::error;
+use crate::Int;
+
+#[derive(Debug)]
+pub struct EventFd {
+    fd: Int,
+}
+
+impl EventFd {
+    /// Create a new `EventFd` with its counter set to `initval`.
+    ///
+    /// If `semaphore` is true, each `read()` decrements the counter by 1 and returns 1.
+    /// Otherwise, each `read()` returns the value of the counter and resets it to 0.
+    pub fn new(initval: u32, semaphore: bool, nonblock: bool) -> io::Result<Self> {
+        let mut flags = libc::EFD_CLOEXEC;
+        if semaphore {
+            flags |= libc::EFD_SEMAPHORE;
+        }
+        if nonblock {
+            flags |= libc::EFD_NONBLOCK;
+        }
+
+        let fd = error::convert_neg_ret(unsafe { libc::eventfd(initval, flags) })?;
+
+        Ok(Self { fd })
+    }
+
+    /// Read from the counter (see `new()` for details).
+    ///
+    /// If the counter is 0, this blocks until it is nonzero (or fails with `EAGAIN` if the
+    /// `EventFd` is nonblocking).
+    pub fn read(&self) -> io::Result<u64> {
+        let mut value: u64 = 0;
+
+        error::convert_neg_ret(unsafe {
+            libc::read(
+                self.fd,
+                (&mut value as *mut u64) as *mut libc::c_void,
+                std::mem::size_of::<u64>(),
+            )
+        })?;
+
+        Ok(value)
+    }
+
+    /// Add `value` to the counter.
+    ///
+    /// The counter can hold at most `u64::MAX - 1`. If adding `value` would exceed that, this
+    /// blocks until the counter is read (or fails with `EAGAIN` if the `EventFd` is
+    /// nonblocking). Writing `u64::MAX` fails with `EINVAL`.
+    pub fn write(&self, value: u64) -> io::Result<()> {
+        error::convert_neg_ret(unsafe {
+            libc::write(
+                self.fd,
+                (&value as *const u64) as *const libc::c_void,
+                std::mem::size_of::<u64>(),
+            )
+        })?;
+
+        Ok(())
+    }
+
+    /// Begin monitoring this `EventFd` with the given `Poller` (for readability, which indicates
+    /// that the counter is nonzero).
+    #[inline]
+    pub fn register_with<P: crate::pollers::Poller>(&self, poller: &mut P) -> io::Result<()> {
+        poller.register(self.fd, crate::pollers::Events::READ)
+    }
+
+    /// Stop monitoring this `EventFd` with the given `Poller`.
+    #[inline]
+    pub fn unregister_from<P: crate::pollers::Poller>(&self, poller: &mut P) -> io::Result<()> {
+        poller.unregister(self.fd)
+    }
+}
+
+impl AsRawFd for EventFd {
+    #[inline]
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd
+    }
+}
+
+impl Drop for EventFd {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe {
+            libc::close(self.fd);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_eventfd_counter() {
+        let efd = EventFd::new(2, false, true).unwrap();
+
+        assert_eq!(efd.read().unwrap(), 2);
+        assert!(error::is_eagain(&efd.read().unwrap_err()));
+
+        efd.write(3).unwrap();
+        efd.write(4).unwrap();
+        assert_eq!(efd.read().unwrap(), 7);
+
+        // Overflow
+        efd.write(u64::MAX - 1).unwrap();
+        assert!(error::is_eagain(&efd.write(1).unwrap_err()));
+        assert!(error::is_raw(
+            &efd.write(u64::MAX).unwrap_err(),
+            libc::EINVAL
+        ));
+        assert_eq!(efd.read().unwrap(), u64::MAX - 1);
+    }
+
+    #[test]
+    fn test_eventfd_semaphore() {
+        let efd = EventFd::new(0, true, true).unwrap();
+
+        assert!(error::is_eagain(&efd.read().unwrap_err()));
+
+        efd.write(2).unwrap();
+        assert_eq!(efd.read().unwrap(), 1);
+        assert_eq!(efd.read().unwrap(), 1);
+        assert!(error::is_eagain(&efd.read().unwrap_err()));
+    }
+}
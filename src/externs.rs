@@ -92,4 +92,16 @@ extern "C" {
     pub fn __libc_current_sigrtmax() -> libc::c_int;
 
     pub fn getauxval(t: libc::c_ulong) -> libc::c_ulong;
+
+    pub fn sigorset(
+        dest: *mut libc::sigset_t,
+        left: *const libc::sigset_t,
+        right: *const libc::sigset_t,
+    ) -> libc::c_int;
+    pub fn sigandset(
+        dest: *mut libc::sigset_t,
+        left: *const libc::sigset_t,
+        right: *const libc::sigset_t,
+    ) -> libc::c_int;
+    pub fn sigisemptyset(set: *const libc::sigset_t) -> libc::c_int;
 }
@@ -53,6 +53,38 @@ pub fn set_inheritable(fd: Int, inheritable: bool) -> io::Result<()> {
     setflags(fd, flags)
 }
 
+#[inline]
+pub fn getfl(fd: Int) -> io::Result<Int> {
+    unsafe { fcntl_raw!(fd, libc::F_GETFL) }
+}
+
+#[inline]
+pub fn setfl(fd: Int, flags: Int) -> io::Result<()> {
+    unsafe { fcntl_raw!(fd, libc::F_SETFL, flags)? };
+    Ok(())
+}
+
+#[inline]
+pub fn is_nonblocking(fd: Int) -> io::Result<bool> {
+    Ok(getfl(fd)? & libc::O_NONBLOCK != 0)
+}
+
+pub fn set_nonblocking(fd: Int, nonblocking: bool) -> io::Result<()> {
+    let mut flags = getfl(fd)?;
+
+    if nonblocking == (flags & libc::O_NONBLOCK != 0) {
+        return Ok(());
+    }
+
+    if nonblocking {
+        flags |= libc::O_NONBLOCK;
+    } else {
+        flags &= !libc::O_NONBLOCK;
+    }
+
+    setfl(fd, flags)
+}
+
 #[inline]
 pub fn set_lock(fd: Int, lock: &libc::flock) -> io::Result<()> {
     unsafe { fcntl_raw!(fd, libc::F_SETLK, lock)? };
@@ -97,6 +129,21 @@ mod tests {
         assert!(!is_inheritable(f.as_raw_fd()).unwrap());
     }
 
+    #[test]
+    fn test_nonblocking() {
+        let f = std::fs::File::open("/dev/null").unwrap();
+
+        set_nonblocking(f.as_raw_fd(), true).unwrap();
+        assert!(is_nonblocking(f.as_raw_fd()).unwrap());
+        set_nonblocking(f.as_raw_fd(), true).unwrap();
+        assert!(is_nonblocking(f.as_raw_fd()).unwrap());
+
+        set_nonblocking(f.as_raw_fd(), false).unwrap();
+        assert!(!is_nonblocking(f.as_raw_fd()).unwrap());
+        set_nonblocking(f.as_raw_fd(), false).unwrap();
+        assert!(!is_nonblocking(f.as_raw_fd()).unwrap());
+    }
+
     #[test]
     fn test_dupfd() {
         let f = std::fs::File::open("/dev/null").unwrap();
@@ -0,0 +1,682 @@
+use std::convert::TryInto;
+use std::io;
+use std::os::unix::prelude::*;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+
+use bitflags::bitflags;
+
+use crate::constants;
+use crate::poll::Events as PollEvents;
+use crate::signal::Sigset;
+use crate::types::{
+    io_uring_cqe, io_uring_getevents_arg, io_uring_params, io_uring_sqe, kernel_timespec,
+};
+use crate::Int;
+
+bitflags! {
+    /// Features supported by the kernel's io_uring implementation.
+    pub struct Features: u32 {
+        const SINGLE_MMAP = constants::IORING_FEAT_SINGLE_MMAP;
+        const NODROP = constants::IORING_FEAT_NODROP;
+        const SUBMIT_STABLE = constants::IORING_FEAT_SUBMIT_STABLE;
+        const RW_CUR_POS = constants::IORING_FEAT_RW_CUR_POS;
+        const CUR_PERSONALITY = constants::IORING_FEAT_CUR_PERSONALITY;
+        const FAST_POLL = constants::IORING_FEAT_FAST_POLL;
+        const POLL_32BITS = constants::IORING_FEAT_POLL_32BITS;
+        const SQPOLL_NONFIXED = constants::IORING_FEAT_SQPOLL_NONFIXED;
+        const EXT_ARG = constants::IORING_FEAT_EXT_ARG;
+        const NATIVE_WORKERS = constants::IORING_FEAT_NATIVE_WORKERS;
+    }
+}
+
+/// A completed operation.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Completion {
+    /// The `user_data` that was passed when the operation was submitted.
+    pub user_data: u64,
+    /// The raw result (a negative errno value on failure).
+    pub res: i32,
+    pub flags: u32,
+}
+
+impl Completion {
+    /// Get the result of the operation as an `io::Result`.
+    #[inline]
+    pub fn result(&self) -> io::Result<u32> {
+        if self.res < 0 {
+            Err(io::Error::from_raw_os_error(-self.res))
+        } else {
+            Ok(self.res as u32)
+        }
+    }
+
+    /// Check whether more completions will be posted for this operation (for multishot
+    /// operations).
+    #[inline]
+    pub fn has_more(&self) -> bool {
+        self.flags & constants::IORING_CQE_F_MORE != 0
+    }
+}
+
+#[derive(Debug)]
+struct Mmap {
+    ptr: *mut libc::c_void,
+    len: usize,
+}
+
+impl Mmap {
+    fn new(fd: Int, len: usize, offset: i64) -> io::Result<Self> {
+        let ptr = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED | libc::MAP_POPULATE,
+                fd,
+                offset as libc::off_t,
+            )
+        };
+
+        if ptr == libc::MAP_FAILED {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(Self { ptr, len })
+        }
+    }
+
+    #[inline]
+    unsafe fn at<T>(&self, offset: u32) -> *mut T {
+        (self.ptr as *mut u8).add(offset as usize) as *mut T
+    }
+}
+
+impl Drop for Mmap {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe {
+            libc::munmap(self.ptr, self.len);
+        }
+    }
+}
+
+/// A raw io_uring instance, which can be used to submit operations and reap their completions.
+///
+/// Operations are added to the submission queue with the `push_*()` methods (which fail with
+/// `EBUSY` if the submission queue is full), and passed to the kernel with `submit()` or
+/// `submit_and_wait()`. Completions can then be retrieved with `peek_completion()`/
+/// `pop_completion()`.
+#[derive(Debug)]
+pub struct IoUring {
+    fd: Int,
+    features: Features,
+    sq_entries: u32,
+    cq_entries: u32,
+
+    sq_head: *const AtomicU32,
+    sq_tail: *const AtomicU32,
+    sq_mask: u32,
+    sq_array: *mut u32,
+    sqes: *mut io_uring_sqe,
+    // The number of entries that have been pushed but not yet submitted
+    pending: u32,
+
+    cq_head: *const AtomicU32,
+    cq_tail: *const AtomicU32,
+    cq_mask: u32,
+    cqes: *const io_uring_cqe,
+
+    // The mappings that the pointers above point into
+    _sq_ring: Mmap,
+    _cq_ring: Option<Mmap>,
+    _sqes_map: Mmap,
+}
+
+// All of the pointers point into memory that is owned by the IoUring.
+unsafe impl Send for IoUring {}
+
+impl IoUring {
+    /// Create a new io_uring instance with (at least) `entries` submission queue entries.
+    ///
+    /// This fails with `ENOSYS` if the kernel does not support io_uring. (It may also fail with
+    /// `EPERM` if io_uring has been disabled, for example with the `kernel.io_uring_disabled`
+    /// sysctl.)
+    pub fn new(entries: u32) -> io::Result<Self> {
+        let mut params = io_uring_params::default();
+
+        let fd = unsafe {
+            libc::syscall(
+                libc::SYS_io_uring_setup,
+                entries,
+                &mut params as *mut io_uring_params,
+            )
+        };
+        let fd = crate::error::convert_neg_ret(fd)? as Int;
+
+        match Self::map(fd, &params) {
+            Ok(ring) => Ok(ring),
+            Err(e) => {
+                unsafe {
+                    libc::close(fd);
+                }
+                Err(e)
+            }
+        }
+    }
+
+    fn map(fd: Int, params: &io_uring_params) -> io::Result<Self> {
+        let features = Features::from_bits_truncate(params.features);
+
+        let sq_len =
+            params.sq_off.array as usize + params.sq_entries as usize * std::mem::size_of::<u32>();
+        let cq_len = params.cq_off.cqes as usize
+            + params.cq_entries as usize * std::mem::size_of::<io_uring_cqe>();
+
+        let (sq_ring, cq_ring) = if features.contains(Features::SINGLE_MMAP) {
+            (
+                Mmap::new(fd, sq_len.max(cq_len), constants::IORING_OFF_SQ_RING)?,
+                None,
+            )
+        } else {
+            (
+                Mmap::new(fd, sq_len, constants::IORING_OFF_SQ_RING)?,
+                Some(Mmap::new(fd, cq_len, constants::IORING_OFF_CQ_RING)?),
+            )
+        };
+
+        let sqes_map = Mmap::new(
+            fd,
+            params.sq_entries as usize * std::mem::size_of::<io_uring_sqe>(),
+            constants::IORING_OFF_SQES,
+        )?;
+
+        let (cq_head, cq_tail, cq_mask, cqes) = unsafe {
+            let cq = cq_ring.as_ref().unwrap_or(&sq_ring);
+            (
+                cq.at(params.cq_off.head),
+                cq.at(params.cq_off.tail),
+                *cq.at::<u32>(params.cq_off.ring_mask),
+                cq.at(params.cq_off.cqes),
+            )
+        };
+
+        unsafe {
+            Ok(Self {
+                fd,
+                features,
+                sq_entries: params.sq_entries,
+                cq_entries: params.cq_entries,
+
+                sq_head: sq_ring.at(params.sq_off.head),
+                sq_tail: sq_ring.at(params.sq_off.tail),
+                sq_mask: *sq_ring.at::<u32>(params.sq_off.ring_mask),
+                sq_array: sq_ring.at(params.sq_off.array),
+                sqes: sqes_map.ptr as *mut io_uring_sqe,
+                pending: 0,
+
+                cq_head,
+                cq_tail,
+                cq_mask,
+                cqes,
+
+                _sq_ring: sq_ring,
+                _cq_ring: cq_ring,
+                _sqes_map: sqes_map,
+            })
+        }
+    }
+
+    #[inline]
+    pub fn features(&self) -> Features {
+        self.features
+    }
+
+    #[inline]
+    pub fn sq_entries(&self) -> u32 {
+        self.sq_entries
+    }
+
+    #[inline]
+    pub fn cq_entries(&self) -> u32 {
+        self.cq_entries
+    }
+
+    // Add an entry to the submission queue. Any pointers in `sqe` must remain valid until the
+    // operation completes.
+    unsafe fn push(&mut self, sqe: io_uring_sqe) -> io::Result<()> {
+        let head = (*self.sq_head).load(Ordering::Acquire);
+        let tail = (*self.sq_tail).load(Ordering::Relaxed);
+
+        if tail.wrapping_sub(head) >= self.sq_entries {
+            return Err(io::Error::from_raw_os_error(libc::EBUSY));
+        }
+
+        let index = tail & self.sq_mask;
+        *self.sqes.add(index as usize) = sqe;
+        *self.sq_array.add(index as usize) = index;
+        (*self.sq_tail).store(tail.wrapping_add(1), Ordering::Release);
+        self.pending += 1;
+
+        Ok(())
+    }
+
+    /// Submit a read of `len` bytes into `buf` from the given offset in `fd` (or from the current
+    /// file position if `offset` is `None`).
+    ///
+    /// # Safety
+    ///
+    /// `buf` must remain valid (and must not be accessed) until the operation completes.
+    #[inline]
+    pub unsafe fn push_read(
+        &mut self,
+        fd: Int,
+        buf: *mut u8,
+        len: u32,
+        offset: Option<u64>,
+        user_data: u64,
+    ) -> io::Result<()> {
+        self.push(io_uring_sqe {
+            opcode: constants::IORING_OP_READ,
+            fd,
+            off: offset.unwrap_or(u64::MAX),
+            addr: buf as u64,
+            len,
+            user_data,
+            ..Default::default()
+        })
+    }
+
+    /// Submit a write of `len` bytes from `buf` to the given offset in `fd` (or to the current
+    /// file position if `offset` is `None`).
+    ///
+    /// # Safety
+    ///
+    /// `buf` must remain valid (and must not be modified) until the operation completes.
+    #[inline]
+    pub unsafe fn push_write(
+        &mut self,
+        fd: Int,
+        buf: *const u8,
+        len: u32,
+        offset: Option<u64>,
+        user_data: u64,
+    ) -> io::Result<()> {
+        self.push(io_uring_sqe {
+            opcode: constants::IORING_OP_WRITE,
+            fd,
+            off: offset.unwrap_or(u64::MAX),
+            addr: buf as u64,
+            len,
+            user_data,
+            ..Default::default()
+        })
+    }
+
+    /// Submit an `accept4()` on the socket `fd`. The result of the completion is the new file
+    /// descriptor.
+    ///
+    /// # Safety
+    ///
+    /// `addr` and `addrlen` must either both be null, or both remain valid until the operation
+    /// completes.
+    #[inline]
+    pub unsafe fn push_accept(
+        &mut self,
+        fd: Int,
+        addr: *mut libc::sockaddr,
+        addrlen: *mut libc::socklen_t,
+        flags: Int,
+        user_data: u64,
+    ) -> io::Result<()> {
+        self.push(io_uring_sqe {
+            opcode: constants::IORING_OP_ACCEPT,
+            fd,
+            off: addrlen as u64,
+            addr: addr as u64,
+            op_flags: flags as u32,
+            user_data,
+            ..Default::default()
+        })
+    }
+
+    /// Submit a `close()` of `fd`.
+    #[inline]
+    pub fn push_close(&mut self, fd: Int, user_data: u64) -> io::Result<()> {
+        unsafe {
+            self.push(io_uring_sqe {
+                opcode: constants::IORING_OP_CLOSE,
+                fd,
+                user_data,
+                ..Default::default()
+            })
+        }
+    }
+
+    /// Submit a request to wait for the given events on `fd`. The result of the completion is
+    /// the mask of events that occurred.
+    ///
+    /// If `multishot` is true, a completion is posted every time the events occur, until the
+    /// request is removed with `push_poll_remove()` (or until a completion without the "more"
+    /// flag is posted; see `Completion::has_more()`). This requires Linux 5.13+; otherwise, the
+    /// completion fails with `EINVAL`.
+    #[inline]
+    pub fn push_poll_add(
+        &mut self,
+        fd: Int,
+        events: PollEvents,
+        multishot: bool,
+        user_data: u64,
+    ) -> io::Result<()> {
+        unsafe {
+            self.push(io_uring_sqe {
+                opcode: constants::IORING_OP_POLL_ADD,
+                fd,
+                len: if multishot {
+                    constants::IORING_POLL_ADD_MULTI
+                } else {
+                    0
+                },
+                op_flags: events.bits() as u32,
+                user_data,
+                ..Default::default()
+            })
+        }
+    }
+
+    /// Submit a request to remove the poll request that was submitted with `target_user_data`.
+    ///
+    /// If it is removed successfully, the poll request completes with `ECANCELED`.
+    #[inline]
+    pub fn push_poll_remove(&mut self, target_user_data: u64, user_data: u64) -> io::Result<()> {
+        unsafe {
+            self.push(io_uring_sqe {
+                opcode: constants::IORING_OP_POLL_REMOVE,
+                fd: -1,
+                addr: target_user_data,
+                user_data,
+                ..Default::default()
+            })
+        }
+    }
+
+    /// Get the number of entries that have been pushed but not yet submitted.
+    #[inline]
+    pub fn pending(&self) -> u32 {
+        self.pending
+    }
+
+    /// Submit all pending entries to the kernel, returning the number that were submitted.
+    #[inline]
+    pub fn submit(&mut self) -> io::Result<usize> {
+        self.enter(0, 0, None, None)
+    }
+
+    /// Submit all pending entries to the kernel, then wait until at least `min_complete`
+    /// completions are available.
+    ///
+    /// If `timeout` is given, this waits for at most that long (returning successfully if it
+    /// expires). If `sigmask` is given, the signal mask is replaced with it while waiting (like
+    /// `ppoll()`). Timeouts require the `EXT_ARG` feature (Linux 5.11+); otherwise this fails
+    /// with `ENOSYS`.
+    ///
+    /// The number of entries that were submitted is returned.
+    pub fn submit_and_wait(
+        &mut self,
+        min_complete: u32,
+        timeout: Option<Duration>,
+        sigmask: Option<Sigset>,
+    ) -> io::Result<usize> {
+        match self.enter(
+            min_complete,
+            constants::IORING_ENTER_GETEVENTS,
+            timeout,
+            sigmask.as_ref(),
+        ) {
+            Err(e) if crate::error::is_raw(&e, libc::ETIME) => Ok(0),
+            res => res,
+        }
+    }
+
+    fn enter(
+        &mut self,
+        min_complete: u32,
+        mut flags: u32,
+        timeout: Option<Duration>,
+        sigmask: Option<&Sigset>,
+    ) -> io::Result<usize> {
+        // _NSIG / 8
+        const SIGMASK_SIZE: usize = 8;
+
+        let raw_sigmask = match sigmask {
+            Some(set) => set.as_ref() as *const libc::sigset_t,
+            None => std::ptr::null(),
+        };
+
+        let ts;
+        let ext_arg;
+
+        let (arg, argsz) = if let Some(timeout) = timeout {
+            if !self.features.contains(Features::EXT_ARG) {
+                return Err(io::Error::from_raw_os_error(libc::ENOSYS));
+            }
+
+            ts = kernel_timespec {
+                tv_sec: timeout.as_secs().try_into().unwrap_or(i64::MAX),
+                tv_nsec: timeout.subsec_nanos() as i64,
+            };
+            ext_arg = io_uring_getevents_arg {
+                sigmask: raw_sigmask as u64,
+                sigmask_sz: SIGMASK_SIZE as u32,
+                pad: 0,
+                ts: &ts as *const kernel_timespec as u64,
+            };
+            flags |= constants::IORING_ENTER_EXT_ARG;
+
+            (
+                &ext_arg as *const io_uring_getevents_arg as *const libc::c_void,
+                std::mem::size_of::<io_uring_getevents_arg>(),
+            )
+        } else {
+            (raw_sigmask as *const libc::c_void, SIGMASK_SIZE)
+        };
+
+        let n = crate::error::convert_neg_ret(unsafe {
+            libc::syscall(
+                libc::SYS_io_uring_enter,
+                self.fd,
+                self.pending,
+                min_complete,
+                flags,
+                arg,
+                argsz,
+            )
+        })? as u32;
+
+        self.pending -= n.min(self.pending);
+
+        Ok(n as usize)
+    }
+
+    /// Get the next completion (if one is available) without removing it from the completion
+    /// queue.
+    pub fn peek_completion(&self) -> Option<Completion> {
+        unsafe {
+            let head = (*self.cq_head).load(Ordering::Relaxed);
+            let tail = (*self.cq_tail).load(Ordering::Acquire);
+
+            if head == tail {
+                None
+            } else {
+                let cqe = *self.cqes.add((head & self.cq_mask) as usize);
+
+                Some(Completion {
+                    user_data: cqe.user_data,
+                    res: cqe.res,
+                    flags: cqe.flags,
+                })
+            }
+        }
+    }
+
+    /// Remove the next completion (if one is available) from the completion queue and return it.
+    pub fn pop_completion(&mut self) -> Option<Completion> {
+        let completion = self.peek_completion()?;
+
+        unsafe {
+            let head = (*self.cq_head).load(Ordering::Relaxed);
+            (*self.cq_head).store(head.wrapping_add(1), Ordering::Release);
+        }
+
+        Some(completion)
+    }
+}
+
+impl AsRawFd for IoUring {
+    #[inline]
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd
+    }
+}
+
+impl Drop for IoUring {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe {
+            libc::close(self.fd);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::Write;
+
+    #[test]
+    fn test_io_uring() {
+        let mut ring = match IoUring::new(8) {
+            Ok(ring) => ring,
+            // Not supported, or disabled
+            Err(e) if crate::error::is_raw(&e, libc::ENOSYS) => return,
+            Err(e) if crate::error::is_raw(&e, libc::EPERM) => return,
+            Err(e) => panic!("{}", e),
+        };
+        assert!(ring.sq_entries() >= 8);
+        assert!(ring.cq_entries() >= ring.sq_entries());
+
+        let (r, mut w) = crate::pipe().unwrap();
+        let mut buf = [0u8; 16];
+
+        assert_eq!(ring.peek_completion(), None);
+
+        // Write then read
+        let data = b"abc";
+        unsafe {
+            ring.push_write(w.as_raw_fd(), data.as_ptr(), data.len() as u32, None, 1)
+                .unwrap();
+        }
+        assert_eq!(ring.pending(), 1);
+        assert_eq!(ring.submit_and_wait(1, None, None).unwrap(), 1);
+        assert_eq!(ring.pending(), 0);
+
+        let completion = ring.pop_completion().unwrap();
+        assert_eq!(completion.user_data, 1);
+        assert_eq!(completion.result().unwrap(), 3);
+        assert_eq!(ring.pop_completion(), None);
+
+        unsafe {
+            ring.push_read(r.as_raw_fd(), buf.as_mut_ptr(), buf.len() as u32, None, 2)
+                .unwrap();
+        }
+        ring.submit_and_wait(1, None, None).unwrap();
+        let completion = ring.pop_completion().unwrap();
+        assert_eq!(completion.user_data, 2);
+        assert_eq!(completion.result().unwrap(), 3);
+        assert_eq!(&buf[..3], b"abc");
+
+        // Poll
+        ring.push_poll_add(r.as_raw_fd(), PollEvents::IN, false, 3)
+            .unwrap();
+        ring.submit_and_wait(1, Some(Duration::from_millis(10)), None)
+            .unwrap();
+        assert_eq!(ring.pop_completion(), None);
+        w.write_all(b"a").unwrap();
+        ring.submit_and_wait(1, None, None).unwrap();
+        let completion = ring.pop_completion().unwrap();
+        assert_eq!(completion.user_data, 3);
+        assert!(PollEvents::from_bits_truncate(completion.res as _).contains(PollEvents::IN));
+
+        // Cancelling a poll
+        ring.push_poll_add(w.as_raw_fd(), PollEvents::IN, false, 4)
+            .unwrap();
+        ring.push_poll_remove(4, 5).unwrap();
+        ring.submit_and_wait(2, None, None).unwrap();
+        let mut completions = [
+            ring.pop_completion().unwrap(),
+            ring.pop_completion().unwrap(),
+        ];
+        completions.sort_by_key(|c| c.user_data);
+        assert!(crate::error::is_raw(
+            &completions[0].result().unwrap_err(),
+            libc::ECANCELED
+        ));
+        assert_eq!(completions[1].result().unwrap(), 0);
+
+        // Close
+        let fd = crate::dup(r.as_raw_fd()).unwrap();
+        ring.push_close(fd, 6).unwrap();
+        ring.submit_and_wait(1, None, None).unwrap();
+        assert_eq!(ring.pop_completion().unwrap().result().unwrap(), 0);
+        assert!(crate::error::is_raw(
+            &crate::fcntl::getflags(fd).unwrap_err(),
+            libc::EBADF
+        ));
+
+        // The submission queue fills up
+        for i in 0..ring.sq_entries() {
+            ring.push_poll_add(r.as_raw_fd(), PollEvents::IN, false, 100 + i as u64)
+                .unwrap();
+        }
+        assert!(crate::error::is_raw(
+            &ring
+                .push_poll_add(r.as_raw_fd(), PollEvents::IN, false, 0)
+                .unwrap_err(),
+            libc::EBUSY
+        ));
+    }
+
+    #[test]
+    fn test_io_uring_accept() {
+        use std::net::{TcpListener, TcpStream};
+
+        let mut ring = match IoUring::new(4) {
+            Ok(ring) => ring,
+            // Not supported, or disabled
+            Err(e) if crate::error::is_raw(&e, libc::ENOSYS) => return,
+            Err(e) if crate::error::is_raw(&e, libc::EPERM) => return,
+            Err(e) => panic!("{}", e),
+        };
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let _client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+
+        unsafe {
+            ring.push_accept(
+                listener.as_raw_fd(),
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+                libc::SOCK_CLOEXEC,
+                1,
+            )
+            .unwrap();
+        }
+        ring.submit_and_wait(1, None, None).unwrap();
+
+        let fd = ring.pop_completion().unwrap().result().unwrap() as Int;
+        assert!(!crate::fcntl::is_inheritable(fd).unwrap());
+        unsafe {
+            libc::close(fd);
+        }
+    }
+}
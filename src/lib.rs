@@ -28,6 +28,7 @@ pub mod resource;
 pub mod rusage;
 pub mod select;
 pub mod sigaction;
+pub mod sigaltstack;
 pub mod sigmask;
 pub mod signal;
 pub mod time;
@@ -60,13 +61,18 @@ attr_group! {
     #![cfg(target_os = "linux")]
 
     pub mod epoll;
+    pub mod eventfd;
     pub mod inotify;
+    pub mod io_uring;
     pub mod ioprio;
     pub mod namespace;
     pub mod openat2;
     pub mod prctl;
     pub mod sched;
+    pub mod siginfo;
     pub mod signalfd;
+    pub mod terminate;
+    pub mod timerfd;
 }
 
 pub type Short = libc::c_short;
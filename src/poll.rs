@@ -29,6 +29,8 @@ bitflags! {
         const ERR = libc::POLLERR;
         const HUP = libc::POLLHUP;
         const NVAL = libc::POLLNVAL;
+        #[cfg(target_os = "linux")]
+        const RDHUP = libc::POLLRDHUP;
     }
 }
 
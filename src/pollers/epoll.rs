@@ -2,27 +2,50 @@ use std::io;
 use std::os::unix::prelude::*;
 use std::time::Duration;
 
-use super::{Events, Poller, Ppoller};
+use super::{slice_sink, vec_sink, Events, Poller, Ppoller, Token, Waker};
 use crate::epoll::{Epoll, Events as EpollEvents, RawEvent as RawEpollEvent};
 use crate::signal::Sigset;
 
 #[derive(Debug)]
 pub struct EpollPoller {
     epoll: Epoll,
+    // Storage for the events returned by epoll_pwait(), reused across calls
+    events: Vec<RawEpollEvent>,
+    // The number of registered file descriptors (which is the most events we can receive at once)
+    nregistered: usize,
 }
 
 impl EpollPoller {
+    // The mapping between our events and epoll's events
+    const EVENT_MAP: [(Events, EpollEvents); 6] = [
+        (Events::READ, EpollEvents::IN),
+        (Events::WRITE, EpollEvents::OUT),
+        (Events::ERROR, EpollEvents::ERR),
+        (Events::HUP, EpollEvents::HUP),
+        (Events::RDHUP, EpollEvents::RDHUP),
+        (Events::PRIORITY, EpollEvents::PRI),
+    ];
+
     fn translate_events(events: Events) -> EpollEvents {
         let mut ev = EpollEvents::empty();
 
-        if events.contains(Events::READ) {
-            ev.insert(EpollEvents::IN);
+        for &(event, epoll_event) in Self::EVENT_MAP.iter() {
+            if events.contains(event) {
+                ev.insert(epoll_event);
+            }
+        }
+
+        if events.contains(Events::EDGE) {
+            ev.insert(EpollEvents::ET);
+        }
+        if events.contains(Events::ONESHOT) {
+            ev.insert(EpollEvents::ONESHOT);
         }
-        if events.contains(Events::WRITE) {
-            ev.insert(EpollEvents::OUT);
+        if events.contains(Events::EXCLUSIVE) {
+            ev.insert(EpollEvents::EXCLUSIVE);
         }
-        if events.contains(Events::ERROR) {
-            ev.insert(EpollEvents::ERR);
+        if events.contains(Events::WAKEUP) {
+            ev.insert(EpollEvents::WAKEUP);
         }
 
         ev
@@ -31,14 +54,10 @@ impl EpollPoller {
     fn translate_events_rev(events: EpollEvents) -> Option<Events> {
         let mut ev = Events::empty();
 
-        if events.contains(EpollEvents::IN) {
-            ev.insert(Events::READ);
-        }
-        if events.contains(EpollEvents::OUT) {
-            ev.insert(Events::WRITE);
-        }
-        if events.contains(EpollEvents::ERR) {
-            ev.insert(Events::ERROR);
+        for &(event, epoll_event) in Self::EVENT_MAP.iter() {
+            if events.contains(epoll_event) {
+                ev.insert(event);
+            }
         }
 
         if ev.is_empty() {
@@ -48,55 +67,125 @@ impl EpollPoller {
         }
     }
 
-    fn translate_epoll_event(e: &RawEpollEvent) -> Option<(RawFd, Events)> {
+    fn translate_epoll_event<T: Token>(e: &RawEpollEvent) -> Option<(T, Events)> {
         match Self::translate_events_rev(e.events) {
-            Some(ev) => Some((e.data as RawFd, ev)),
+            Some(ev) => Some((T::from_u64(e.data), ev)),
             None => None,
         }
     }
+
+    // Wait for at most `maxevents` events, and pass them to `push` (stopping if it returns
+    // false)
+    fn wait<T: Token, F: FnMut((T, Events)) -> bool>(
+        &mut self,
+        timeout: Option<Duration>,
+        sigmask: Option<Sigset>,
+        maxevents: usize,
+        mut push: F,
+    ) -> io::Result<()> {
+        if maxevents == 0 {
+            return Err(io::Error::from_raw_os_error(libc::EINVAL));
+        }
+
+        let maxevents = maxevents.min(self.nregistered.max(1));
+        if self.events.len() < maxevents {
+            self.events.resize(maxevents, RawEpollEvent::default());
+        }
+
+        let n = self
+            .epoll
+            .pwait2_raw(&mut self.events[..maxevents], timeout, sigmask)?;
+
+        for e in self.events[..n].iter() {
+            if let Some(ev) = Self::translate_epoll_event(e) {
+                if !push(ev) {
+                    break;
+                }
+            }
+        }
+
+        Ok(())
+    }
 }
 
 impl Poller for EpollPoller {
     fn new() -> io::Result<Self> {
         Ok(Self {
             epoll: Epoll::new()?,
+            events: Vec::new(),
+            nregistered: 0,
         })
     }
 
-    fn register(&mut self, fd: RawFd, events: Events) -> io::Result<()> {
-        self.epoll.add(fd, Self::translate_events(events))
+    fn register_token<T: Token>(&mut self, fd: RawFd, events: Events, token: T) -> io::Result<()> {
+        self.epoll
+            .add3(fd, Self::translate_events(events), token.into_u64())?;
+        self.nregistered += 1;
+        Ok(())
     }
 
     fn unregister(&mut self, fd: RawFd) -> io::Result<()> {
-        self.epoll.del(fd)
+        self.epoll.del(fd)?;
+        self.nregistered -= 1;
+        Ok(())
+    }
+
+    fn modify_token<T: Token>(&mut self, fd: RawFd, events: Events, token: T) -> io::Result<()> {
+        self.epoll
+            .modify3(fd, Self::translate_events(events), token.into_u64())
+    }
+
+    #[inline]
+    fn poll_into<T: Token>(
+        &mut self,
+        timeout: Option<Duration>,
+        events: &mut Vec<(T, Events)>,
+    ) -> io::Result<usize> {
+        self.ppoll_into(timeout, None, events)
     }
 
-    fn modify(&mut self, fd: RawFd, events: Events) -> io::Result<()> {
-        self.epoll.modify(fd, Self::translate_events(events))
+    #[inline]
+    fn poll_into_slice<T: Token>(
+        &mut self,
+        timeout: Option<Duration>,
+        events: &mut [(T, Events)],
+    ) -> io::Result<usize> {
+        self.ppoll_into_slice(timeout, None, events)
     }
 
-    fn poll(&mut self, timeout: Option<Duration>) -> io::Result<Vec<(RawFd, Events)>> {
-        self.ppoll(timeout, None)
+    /// Create a `Waker` backed by an `EventFd` (see `Waker::new_eventfd()`).
+    #[inline]
+    fn waker<T: Token>(&mut self, token: T) -> io::Result<Waker> {
+        Waker::new_eventfd(self, token)
+    }
+
+    #[inline]
+    fn native_events() -> Events {
+        Events::all()
     }
 }
 
 impl Ppoller for EpollPoller {
-    fn ppoll(
+    fn ppoll_into<T: Token>(
+        &mut self,
+        timeout: Option<Duration>,
+        sigmask: Option<Sigset>,
+        events: &mut Vec<(T, Events)>,
+    ) -> io::Result<usize> {
+        self.wait(timeout, sigmask, usize::MAX, vec_sink(events))?;
+        Ok(events.len())
+    }
+
+    fn ppoll_into_slice<T: Token>(
         &mut self,
         timeout: Option<Duration>,
         sigmask: Option<Sigset>,
-    ) -> io::Result<Vec<(RawFd, Events)>> {
-        let mut events = [RawEpollEvent {
-            events: EpollEvents::empty(),
-            data: 0,
-        }; 10];
-
-        let n = self.epoll.pwait_raw(&mut events, timeout, sigmask)?;
-        Ok(events
-            .iter()
-            .filter_map(Self::translate_epoll_event)
-            .take(n)
-            .collect())
+        events: &mut [(T, Events)],
+    ) -> io::Result<usize> {
+        let mut count = 0;
+        let maxevents = events.len();
+        self.wait(timeout, sigmask, maxevents, slice_sink(events, &mut count))?;
+        Ok(count)
     }
 }
 
@@ -106,6 +195,12 @@ mod tests {
 
     use std::io::Write;
 
+    #[test]
+    fn test_epoll_poller_common() {
+        assert_eq!(EpollPoller::native_events(), Events::all());
+        crate::pollers::tests::check_poller::<EpollPoller>(EpollPoller::native_events());
+    }
+
     #[test]
     fn test_epoll_poller() {
         let timeout_0 = Some(Duration::from_secs(0));
@@ -205,4 +300,46 @@ mod tests {
             vec![(r2.as_raw_fd(), Events::READ)],
         );
     }
+
+    #[test]
+    fn test_epoll_poller_exclusive() {
+        let (r1, mut w1) = crate::pipe().unwrap();
+
+        let mut poller1 = EpollPoller::new().unwrap();
+        let mut poller2 = EpollPoller::new().unwrap();
+        poller1
+            .register(
+                r1.as_raw_fd(),
+                Events::READ | Events::EXCLUSIVE | Events::WAKEUP,
+            )
+            .unwrap();
+        poller2
+            .register(r1.as_raw_fd(), Events::READ | Events::EXCLUSIVE)
+            .unwrap();
+
+        assert_eq!(
+            poller1
+                .modify(r1.as_raw_fd(), Events::READ)
+                .unwrap_err()
+                .raw_os_error(),
+            Some(libc::EINVAL),
+        );
+
+        w1.write_all(b"a").unwrap();
+        assert_eq!(
+            poller1.poll(Some(Duration::from_secs(0))).unwrap(),
+            vec![(r1.as_raw_fd(), Events::READ)],
+        );
+    }
+
+    #[test]
+    fn test_epoll_poller_timeout() {
+        let mut poller = EpollPoller::new().unwrap();
+
+        // Sub-millisecond precision isn't lost
+        let timeout = Duration::from_micros(1500);
+        let start = std::time::Instant::now();
+        assert_eq!(poller.poll(Some(timeout)).unwrap(), vec![]);
+        assert!(start.elapsed() >= timeout);
+    }
 }
@@ -0,0 +1,369 @@
+use std::collections::HashMap;
+use std::io;
+use std::io::Write;
+use std::os::unix::prelude::*;
+use std::time::{Duration, Instant};
+
+use super::{slice_sink, vec_sink, Events, Poller, Ppoller, Token};
+use crate::error;
+use crate::io_uring::{Features, IoUring};
+use crate::poll::Events as PollEvents;
+use crate::signal::Sigset;
+
+// The user_data of internal requests (like POLL_REMOVE), whose completions are ignored
+const INTERNAL_USER_DATA: u64 = u64::MAX;
+
+const RING_ENTRIES: u32 = 256;
+
+#[derive(Copy, Clone, Debug)]
+struct Registration {
+    fd: RawFd,
+    events: Events,
+    token: u64,
+    // Whether this uses a multishot poll request (for edge-triggered mode)
+    multishot: bool,
+    // Whether there is an active poll request for this registration
+    armed: bool,
+}
+
+/// A poller that uses io_uring's `IORING_OP_POLL_ADD`.
+///
+/// This requires Linux 5.11+. On older kernels (or if io_uring has been disabled), `new()` fails
+/// with `ENOSYS`, and another backend (such as `EpollPoller`, which is the `DefaultPoller` on
+/// Linux) should be used instead:
+///
+/// ```
+/// use simple_libc::pollers::{EpollPoller, IoUringPoller, Poller};
+///
+/// match IoUringPoller::new() {
+///     Ok(poller) => { /* ... */ }
+///     Err(e) if e.raw_os_error() == Some(libc::ENOSYS) => {
+///         let poller = EpollPoller::new().unwrap();
+///         // ...
+///     }
+///     Err(e) => panic!("{}", e),
+/// }
+/// ```
+///
+/// Level-triggered and one-shot registrations use single-shot poll requests (which are re-armed
+/// automatically after each event in level-triggered mode). Edge-triggered registrations use
+/// multishot poll requests on Linux 5.13+; on older kernels, they fall back to level-triggered
+/// mode.
+#[derive(Debug)]
+pub struct IoUringPoller {
+    ring: IoUring,
+    // The registrations, by the user_data of their poll requests
+    regs: HashMap<u64, Registration>,
+    // The user_data of the poll request for each registered file descriptor
+    fds: HashMap<RawFd, u64>,
+    next_id: u64,
+    multishot_supported: bool,
+}
+
+impl IoUringPoller {
+    // The mapping between our events and poll()'s events
+    const EVENT_MAP: [(Events, PollEvents); 6] = [
+        (Events::READ, PollEvents::IN),
+        (Events::WRITE, PollEvents::OUT),
+        (Events::ERROR, PollEvents::ERR),
+        (Events::HUP, PollEvents::HUP),
+        (Events::RDHUP, PollEvents::RDHUP),
+        (Events::PRIORITY, PollEvents::PRI),
+    ];
+
+    fn translate_events(events: Events) -> PollEvents {
+        let mut ev = PollEvents::empty();
+
+        for &(event, poll_event) in Self::EVENT_MAP.iter() {
+            if events.contains(event) {
+                ev.insert(poll_event);
+            }
+        }
+
+        ev
+    }
+
+    fn translate_events_rev(events: PollEvents) -> Events {
+        let mut ev = Events::empty();
+
+        for &(event, poll_event) in Self::EVENT_MAP.iter() {
+            if events.contains(poll_event) {
+                ev.insert(event);
+            }
+        }
+
+        ev
+    }
+
+    /// Check whether the kernel supports multishot poll requests (which are used for
+    /// edge-triggered registrations).
+    #[inline]
+    pub fn supports_multishot(&self) -> bool {
+        self.multishot_supported
+    }
+
+    // Check whether the kernel supports multishot poll requests by trying one on a pipe that is
+    // ready for reading
+    fn probe_multishot(ring: &mut IoUring) -> io::Result<bool> {
+        let (r, mut w) = crate::pipe()?;
+        w.write_all(b"a")?;
+
+        ring.push_poll_add(r.as_raw_fd(), PollEvents::IN, true, 0)?;
+        ring.submit_and_wait(1, None, None)?;
+
+        let completion = match ring.pop_completion() {
+            Some(c) => c,
+            None => return Err(io::Error::from_raw_os_error(libc::EIO)),
+        };
+
+        if completion.has_more() {
+            // Remove it, and wait for both the removal and the cancellation to complete
+            ring.push_poll_remove(0, INTERNAL_USER_DATA)?;
+            ring.submit_and_wait(2, None, None)?;
+            while ring.pop_completion().is_some() {}
+        }
+
+        Ok(completion.res >= 0)
+    }
+
+    // Push a poll request for the given registration, submitting the pending requests first if
+    // the submission queue is full
+    fn arm(&mut self, id: u64) -> io::Result<()> {
+        let reg = self.regs.get_mut(&id).unwrap();
+        let fd = reg.fd;
+        let events = Self::translate_events(reg.events);
+        let multishot = reg.multishot;
+        reg.armed = true;
+
+        match self.ring.push_poll_add(fd, events, multishot, id) {
+            Err(e) if error::is_raw(&e, libc::EBUSY) => {
+                self.ring.submit()?;
+                self.ring.push_poll_add(fd, events, multishot, id)
+            }
+            res => res,
+        }
+    }
+
+    fn disarm(&mut self, id: u64) -> io::Result<()> {
+        match self.ring.push_poll_remove(id, INTERNAL_USER_DATA) {
+            Err(e) if error::is_raw(&e, libc::EBUSY) => {
+                self.ring.submit()?;
+                self.ring.push_poll_remove(id, INTERNAL_USER_DATA)
+            }
+            res => res,
+        }
+    }
+
+    // Wait for events and pass them to `push` (stopping if it returns false)
+    fn wait<T: Token, F: FnMut((T, Events)) -> bool>(
+        &mut self,
+        timeout: Option<Duration>,
+        sigmask: Option<Sigset>,
+        mut push: F,
+    ) -> io::Result<()> {
+        // A timeout too large to represent as an Instant is treated as no timeout
+        let deadline = timeout.and_then(|t| Instant::now().checked_add(t));
+
+        loop {
+            if self.ring.peek_completion().is_none() || self.ring.pending() > 0 {
+                let remaining = deadline.map(|d| d.saturating_duration_since(Instant::now()));
+                self.ring.submit_and_wait(1, remaining, sigmask)?;
+            }
+
+            let mut nevents = 0;
+
+            while let Some(completion) = self.ring.peek_completion() {
+                let id = completion.user_data;
+
+                // Completions of internal requests, and of requests for registrations that have
+                // since been removed, are ignored
+                let reg = match self.regs.get(&id) {
+                    Some(reg) => *reg,
+                    None => {
+                        self.ring.pop_completion();
+                        continue;
+                    }
+                };
+
+                let events = if completion.res < 0 {
+                    Events::ERROR
+                } else {
+                    let ev = Self::translate_events_rev(PollEvents::from_bits_truncate(
+                        completion.res as _,
+                    ));
+                    if ev.is_empty() {
+                        Events::ERROR
+                    } else {
+                        ev
+                    }
+                };
+
+                if !push((T::from_u64(reg.token), events)) {
+                    return Ok(());
+                }
+                self.ring.pop_completion();
+                nevents += 1;
+
+                if completion.res < 0 || reg.events.contains(Events::ONESHOT) {
+                    self.regs.get_mut(&id).unwrap().armed = false;
+                } else if !reg.multishot || !completion.has_more() {
+                    self.arm(id)?;
+                }
+            }
+
+            if nevents > 0 {
+                return Ok(());
+            }
+
+            if let Some(deadline) = deadline {
+                if Instant::now() >= deadline {
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+impl Poller for IoUringPoller {
+    fn new() -> io::Result<Self> {
+        let mut ring = match IoUring::new(RING_ENTRIES) {
+            Ok(ring) => ring,
+            // io_uring has been disabled (for example, with the kernel.io_uring_disabled sysctl),
+            // which callers should handle the same way as it being unsupported
+            Err(e) if error::is_raw(&e, libc::EPERM) => {
+                return Err(io::Error::from_raw_os_error(libc::ENOSYS))
+            }
+            Err(e) => return Err(e),
+        };
+
+        // Needed for timeouts
+        if !ring.features().contains(Features::EXT_ARG) {
+            return Err(io::Error::from_raw_os_error(libc::ENOSYS));
+        }
+
+        let multishot_supported = Self::probe_multishot(&mut ring)?;
+
+        Ok(Self {
+            ring,
+            regs: HashMap::new(),
+            fds: HashMap::new(),
+            next_id: 0,
+            multishot_supported,
+        })
+    }
+
+    fn register_token<T: Token>(&mut self, fd: RawFd, events: Events, token: T) -> io::Result<()> {
+        if self.fds.contains_key(&fd) {
+            return Err(io::Error::from_raw_os_error(libc::EEXIST));
+        }
+
+        // Make sure the file descriptor is valid now (instead of reporting an error later)
+        crate::fcntl::getflags(fd)?;
+
+        let id = self.next_id;
+        self.next_id += 1;
+
+        self.regs.insert(
+            id,
+            Registration {
+                fd,
+                events,
+                token: token.into_u64(),
+                multishot: events.contains(Events::EDGE)
+                    && !events.contains(Events::ONESHOT)
+                    && self.multishot_supported,
+                armed: false,
+            },
+        );
+        self.fds.insert(fd, id);
+
+        if let Err(e) = self.arm(id) {
+            self.regs.remove(&id);
+            self.fds.remove(&fd);
+            return Err(e);
+        }
+
+        Ok(())
+    }
+
+    fn unregister(&mut self, fd: RawFd) -> io::Result<()> {
+        let id = match self.fds.remove(&fd) {
+            Some(id) => id,
+            None => return Err(io::Error::from_raw_os_error(libc::ENOENT)),
+        };
+
+        if self.regs.remove(&id).unwrap().armed {
+            self.disarm(id)?;
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    fn poll_into<T: Token>(
+        &mut self,
+        timeout: Option<Duration>,
+        events: &mut Vec<(T, Events)>,
+    ) -> io::Result<usize> {
+        self.ppoll_into(timeout, None, events)
+    }
+
+    #[inline]
+    fn poll_into_slice<T: Token>(
+        &mut self,
+        timeout: Option<Duration>,
+        events: &mut [(T, Events)],
+    ) -> io::Result<usize> {
+        self.ppoll_into_slice(timeout, None, events)
+    }
+
+    /// Edge-triggered mode is only supported natively on Linux 5.13+ (see
+    /// `supports_multishot()`).
+    #[inline]
+    fn native_events() -> Events {
+        Events::all() - (Events::EXCLUSIVE | Events::WAKEUP)
+    }
+}
+
+impl Ppoller for IoUringPoller {
+    fn ppoll_into<T: Token>(
+        &mut self,
+        timeout: Option<Duration>,
+        sigmask: Option<Sigset>,
+        events: &mut Vec<(T, Events)>,
+    ) -> io::Result<usize> {
+        self.wait(timeout, sigmask, vec_sink(events))?;
+        Ok(events.len())
+    }
+
+    fn ppoll_into_slice<T: Token>(
+        &mut self,
+        timeout: Option<Duration>,
+        sigmask: Option<Sigset>,
+        events: &mut [(T, Events)],
+    ) -> io::Result<usize> {
+        let mut count = 0;
+        self.wait(timeout, sigmask, slice_sink(events, &mut count))?;
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_io_uring_poller_common() {
+        if !crate::pollers::tests::io_uring_supported() {
+            return;
+        }
+
+        // Edge-triggered mode is emulated if multishot poll requests aren't supported
+        let mut native = IoUringPoller::native_events();
+        if !IoUringPoller::new().unwrap().supports_multishot() {
+            native.remove(Events::EDGE);
+        }
+
+        crate::pollers::tests::check_poller::<IoUringPoller>(native);
+    }
+}
@@ -1,3 +1,11 @@
+//! Portable interfaces to the platform's I/O polling mechanisms.
+//!
+//! `DefaultPoller` and `DefaultPpoller` are compile-time aliases for the preferred backend on
+//! each platform (`EpollPoller` on Linux). They do not check anything at runtime, so they never
+//! select `IoUringPoller`; to use io_uring when the kernel supports it, create an
+//! `IoUringPoller` and fall back to another backend if that fails with `ENOSYS` (see the
+//! `IoUringPoller` documentation for an example).
+
 use std::io;
 use std::os::unix::prelude::*;
 use std::time::Duration;
@@ -6,33 +14,123 @@ use bitflags::bitflags;
 
 #[cfg(target_os = "linux")]
 mod epoll;
+#[cfg(target_os = "linux")]
+mod io_uring;
 mod poll;
 mod select;
+mod waker;
 
 #[cfg(target_os = "linux")]
 pub use epoll::EpollPoller;
+#[cfg(target_os = "linux")]
+pub use io_uring::IoUringPoller;
 pub use poll::PollPoller;
 pub use select::SelectPoller;
+pub use waker::Waker;
 
 use crate::signal::Sigset;
 
 bitflags! {
     pub struct Events: u32 {
-        const READ  = 0b001;
-        const WRITE = 0b010;
+        const READ  = 0b0000_0001;
+        const WRITE = 0b0000_0010;
         /// Note: `ERROR` may be returned by `poll()` even if the file descriptor
         /// was not registered with the `ERROR` event.
-        const ERROR = 0b100;
+        const ERROR = 0b0000_0100;
+        /// The other end of the file descriptor was closed.
+        ///
+        /// Note: Like `ERROR`, `HUP` may be returned by `poll()` even if the file
+        /// descriptor was not registered with the `HUP` event.
+        const HUP = 0b0000_1000;
+        /// The peer closed its end of a stream socket (or shut down the writing half of
+        /// the connection).
+        const RDHUP = 0b0001_0000;
+        /// Priority data (for example, TCP out-of-band data) is available to read.
+        const PRIORITY = 0b0010_0000;
+
+        /// Register the file descriptor in edge-triggered mode: an event is only reported
+        /// when the file descriptor becomes ready, not for as long as it remains ready.
+        ///
+        /// This is only valid when registering a file descriptor. Backends that do not
+        /// support it natively (see `Poller::native_events()`) fall back to level-triggered
+        /// mode, which may produce extra wakeups but never misses events (so programs that
+        /// read/write until `EAGAIN` work unchanged).
+        const EDGE = 0b0100_0000;
+        /// Register the file descriptor in one-shot mode: after an event is reported, the
+        /// file descriptor is disarmed until it is re-armed with `Poller::modify()`.
+        ///
+        /// This is only valid when registering a file descriptor. Backends that do not
+        /// support it natively (see `Poller::native_events()`) emulate it by automatically
+        /// disarming the file descriptor.
+        const ONESHOT = 0b1000_0000;
+
+        /// Register the file descriptor in exclusive wakeup mode, to avoid "thundering herds"
+        /// when several pollers are waiting on the same file (see `epoll::Events::EXCLUSIVE` for
+        /// details and restrictions).
+        ///
+        /// This is only valid when registering a file descriptor, and it is ignored by backends
+        /// that do not support it natively.
+        const EXCLUSIVE = 0b0001_0000_0000;
+        /// Prevent the system from suspending while events are pending for this file
+        /// descriptor (see `epoll::Events::WAKEUP`).
+        ///
+        /// This is only valid when registering a file descriptor, and it is ignored by backends
+        /// that do not support it natively.
+        const WAKEUP = 0b0010_0000_0000;
     }
 }
 
+/// A value that identifies a registered file descriptor, which is returned along with its
+/// events by `Poller::poll_tokens()`.
+///
+/// Tokens are stored as `u64`s (like the `data` field of `epoll::Epoll::add3()`), so a token
+/// type must be able to round-trip through a `u64`. This is implemented for the integer types;
+/// other small `Copy` types (for example, indices into a slab or newtype wrappers) can
+/// implement it too.
+pub trait Token: Copy {
+    fn into_u64(self) -> u64;
+
+    fn from_u64(data: u64) -> Self;
+}
+
+macro_rules! impl_token {
+    ($($t:ty)*) => {
+        $(
+            impl Token for $t {
+                #[inline]
+                fn into_u64(self) -> u64 {
+                    self as u64
+                }
+
+                #[inline]
+                fn from_u64(data: u64) -> Self {
+                    data as $t
+                }
+            }
+        )*
+    };
+}
+
+impl_token! { u8 u16 u32 u64 usize i8 i16 i32 i64 isize }
+
 pub trait Poller: Sized {
     fn new() -> io::Result<Self>;
 
     /// Begin monitoring the given file descriptor for the given events.
     ///
+    /// The file descriptor itself is used as the token (see `register_token()`).
+    ///
+    /// If the file object was already registered, this returns an `EEXIST` error.
+    #[inline]
+    fn register(&mut self, fd: RawFd, events: Events) -> io::Result<()> {
+        self.register_token(fd, events, fd)
+    }
+
+    /// Begin monitoring the given file descriptor for the given events, identifying it by the
+    /// given token in the results of `poll_tokens()`.
+    ///
     /// If the file object was already registered, this returns an `EEXIST` error.
-    fn register(&mut self, fd: RawFd, events: Events) -> io::Result<()>;
+    fn register_token<T: Token>(&mut self, fd: RawFd, events: Events, token: T) -> io::Result<()>;
 
     /// Stop monitoring the given file descriptor.
     ///
@@ -41,22 +139,155 @@ pub trait Poller: Sized {
 
     /// Modify the events being monitored for the given file descriptor.
     ///
+    /// The file descriptor itself is used as the token (see `modify_token()`).
+    ///
     /// If the file object was not already registered, this returns an `ENOENT` error.
+    #[inline]
     fn modify(&mut self, fd: RawFd, events: Events) -> io::Result<()> {
+        self.modify_token(fd, events, fd)
+    }
+
+    /// Modify the events being monitored for the given file descriptor, and the token that
+    /// identifies it.
+    ///
+    /// If the file object was not already registered, this returns an `ENOENT` error.
+    fn modify_token<T: Token>(&mut self, fd: RawFd, events: Events, token: T) -> io::Result<()> {
         self.unregister(fd)?;
-        self.register(fd, events)?;
+        self.register_token(fd, events, token)?;
         Ok(())
     }
 
-    fn poll(&mut self, timeout: Option<Duration>) -> io::Result<Vec<(RawFd, Events)>>;
+    /// Wait for events on the registered file descriptors.
+    ///
+    /// This returns the tokens as `RawFd`s, so it should only be used if every file
+    /// descriptor was registered with `register()`/`modify()` (or with tokens that are file
+    /// descriptors).
+    #[inline]
+    fn poll(&mut self, timeout: Option<Duration>) -> io::Result<Vec<(RawFd, Events)>> {
+        self.poll_tokens(timeout)
+    }
+
+    /// Wait for events on the registered file descriptors, returning the token that each file
+    /// descriptor was registered with.
+    ///
+    /// This allocates a new `Vec` on every call; busy event loops should use `poll_into()` or
+    /// `poll_into_slice()` instead.
+    #[inline]
+    fn poll_tokens<T: Token>(&mut self, timeout: Option<Duration>) -> io::Result<Vec<(T, Events)>> {
+        let mut events = Vec::new();
+        self.poll_into(timeout, &mut events)?;
+        Ok(events)
+    }
+
+    /// Wait for events on the registered file descriptors, and store them (along with their
+    /// tokens) in `events`, reusing its storage.
+    ///
+    /// `events` is cleared first. The number of events that were stored is returned.
+    fn poll_into<T: Token>(
+        &mut self,
+        timeout: Option<Duration>,
+        events: &mut Vec<(T, Events)>,
+    ) -> io::Result<usize>;
+
+    /// Wait for events on the registered file descriptors, and store at most `events.len()` of
+    /// them (along with their tokens) at the start of `events`.
+    ///
+    /// The number of events that were stored is returned. Any other events that are pending will
+    /// be returned by subsequent calls; the next call starts with the file descriptors that were
+    /// left out, so one that stays ready can't starve the others. (`EpollPoller` returns an
+    /// `EINVAL` error if `events` is empty.)
+    fn poll_into_slice<T: Token>(
+        &mut self,
+        timeout: Option<Duration>,
+        events: &mut [(T, Events)],
+    ) -> io::Result<usize>;
+
+    /// Create a `Waker` that can be used to wake up this poller from other threads, and register
+    /// it with the given token.
+    ///
+    /// By default, the `Waker` is backed by a pipe (see `Waker::new_pipe()`).
+    #[inline]
+    fn waker<T: Token>(&mut self, token: T) -> io::Result<Waker> {
+        Waker::new_pipe(self, token)
+    }
+
+    /// Get the set of `Events` flags (including the `EDGE` and `ONESHOT` modes) that this
+    /// poller supports natively.
+    ///
+    /// `EDGE` and `ONESHOT` are emulated if they are not supported natively (see their
+    /// documentation). Other events that are not supported natively are never reported.
+    #[inline]
+    fn native_events() -> Events {
+        Events::READ | Events::WRITE | Events::ERROR
+    }
 }
 
 pub trait Ppoller: Poller {
+    #[inline]
     fn ppoll(
         &mut self,
         timeout: Option<Duration>,
         sigmask: Option<Sigset>,
-    ) -> io::Result<Vec<(RawFd, Events)>>;
+    ) -> io::Result<Vec<(RawFd, Events)>> {
+        self.ppoll_tokens(timeout, sigmask)
+    }
+
+    #[inline]
+    fn ppoll_tokens<T: Token>(
+        &mut self,
+        timeout: Option<Duration>,
+        sigmask: Option<Sigset>,
+    ) -> io::Result<Vec<(T, Events)>> {
+        let mut events = Vec::new();
+        self.ppoll_into(timeout, sigmask, &mut events)?;
+        Ok(events)
+    }
+
+    /// Like `Poller::poll_into()`, but with a signal mask (like `ppoll()`).
+    fn ppoll_into<T: Token>(
+        &mut self,
+        timeout: Option<Duration>,
+        sigmask: Option<Sigset>,
+        events: &mut Vec<(T, Events)>,
+    ) -> io::Result<usize>;
+
+    /// Like `Poller::poll_into_slice()`, but with a signal mask (like `ppoll()`).
+    fn ppoll_into_slice<T: Token>(
+        &mut self,
+        timeout: Option<Duration>,
+        sigmask: Option<Sigset>,
+        events: &mut [(T, Events)],
+    ) -> io::Result<usize>;
+}
+
+// Helpers for the backends to store events into a `Vec` or a slice. The closures they return
+// accept an event and return `false` if it could not be stored.
+
+#[inline]
+fn vec_sink<T>(events: &mut Vec<(T, Events)>) -> impl FnMut((T, Events)) -> bool + '_ {
+    events.clear();
+
+    move |ev| {
+        events.push(ev);
+        true
+    }
+}
+
+#[inline]
+fn slice_sink<'a, T>(
+    events: &'a mut [(T, Events)],
+    count: &'a mut usize,
+) -> impl FnMut((T, Events)) -> bool + 'a {
+    *count = 0;
+
+    move |ev| match events.get_mut(*count) {
+        Some(slot) => {
+            *slot = ev;
+            *count += 1;
+            true
+        }
+        None => false,
+    }
 }
 
 crate::attr_group! {
@@ -84,3 +315,362 @@ crate::attr_group! {
     pub type DefaultPoller = PollPoller;
     pub type DefaultPpoller = SelectPoller;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::Write;
+    use std::net::Shutdown;
+    use std::os::unix::net::UnixStream;
+
+    // Run the checks that every backend must pass. `native` is the set of events that the poller
+    // supports natively (usually `P::native_events()`).
+    pub(super) fn check_poller<P: Poller>(native: Events) {
+        check_modes::<P>(native);
+        check_tokens::<P>();
+        check_into::<P>();
+        check_waker::<P>();
+    }
+
+    fn check_modes<P: Poller>(native: Events) {
+        let timeout_0 = Some(Duration::from_secs(0));
+        assert!(native.contains(Events::READ | Events::WRITE | Events::ERROR));
+
+        let (r1, mut w1) = crate::pipe().unwrap();
+        let (r2, mut w2) = crate::pipe().unwrap();
+
+        let mut poller = P::new().unwrap();
+
+        // One-shot: disarmed after the first event, until it's re-armed with modify()
+        poller
+            .register(r1.as_raw_fd(), Events::READ | Events::ONESHOT)
+            .unwrap();
+        assert_eq!(poller.poll(timeout_0).unwrap(), vec![]);
+        w1.write_all(b"a").unwrap();
+        assert_eq!(
+            poller.poll(timeout_0).unwrap(),
+            vec![(r1.as_raw_fd(), Events::READ)]
+        );
+        assert_eq!(poller.poll(timeout_0).unwrap(), vec![]);
+        poller
+            .modify(r1.as_raw_fd(), Events::READ | Events::ONESHOT)
+            .unwrap();
+        assert_eq!(
+            poller.poll(timeout_0).unwrap(),
+            vec![(r1.as_raw_fd(), Events::READ)]
+        );
+        assert_eq!(poller.poll(timeout_0).unwrap(), vec![]);
+        poller.unregister(r1.as_raw_fd()).unwrap();
+
+        // Edge-triggered: only reported once unless it's emulated
+        poller
+            .register(r2.as_raw_fd(), Events::READ | Events::EDGE)
+            .unwrap();
+        w2.write_all(b"a").unwrap();
+        assert_eq!(
+            poller.poll(timeout_0).unwrap(),
+            vec![(r2.as_raw_fd(), Events::READ)]
+        );
+        if native.contains(Events::EDGE) {
+            assert_eq!(poller.poll(timeout_0).unwrap(), vec![]);
+        } else {
+            assert_eq!(
+                poller.poll(timeout_0).unwrap(),
+                vec![(r2.as_raw_fd(), Events::READ)]
+            );
+        }
+        w2.write_all(b"a").unwrap();
+        assert_eq!(
+            poller.poll(timeout_0).unwrap(),
+            vec![(r2.as_raw_fd(), Events::READ)]
+        );
+        poller.unregister(r2.as_raw_fd()).unwrap();
+
+        // Hangup (select() reports it as readable)
+        drop(w2);
+        poller.register(r2.as_raw_fd(), Events::READ).unwrap();
+        let events = poller.poll(timeout_0).unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].0, r2.as_raw_fd());
+        if native.contains(Events::HUP) {
+            assert!(events[0].1.contains(Events::HUP));
+        } else {
+            assert!(events[0].1.contains(Events::READ));
+        }
+        poller.unregister(r2.as_raw_fd()).unwrap();
+
+        // Peer shut down writing
+        let (s1, s2) = UnixStream::pair().unwrap();
+        poller
+            .register(s1.as_raw_fd(), Events::READ | Events::RDHUP)
+            .unwrap();
+        assert_eq!(poller.poll(timeout_0).unwrap(), vec![]);
+        s2.shutdown(Shutdown::Write).unwrap();
+        let expected = if native.contains(Events::RDHUP) {
+            Events::READ | Events::RDHUP
+        } else {
+            Events::READ
+        };
+        assert_eq!(
+            poller.poll(timeout_0).unwrap(),
+            vec![(s1.as_raw_fd(), expected)]
+        );
+    }
+
+    fn check_tokens<P: Poller>() {
+        let timeout_0 = Some(Duration::from_secs(0));
+
+        let (r1, mut w1) = crate::pipe().unwrap();
+        let (r2, mut w2) = crate::pipe().unwrap();
+
+        let mut poller = P::new().unwrap();
+        poller
+            .register_token(r1.as_raw_fd(), Events::READ, 100u64)
+            .unwrap();
+        poller
+            .register_token(r2.as_raw_fd(), Events::READ, u64::MAX)
+            .unwrap();
+        assert_eq!(poller.poll_tokens::<u64>(timeout_0).unwrap(), vec![]);
+
+        w1.write_all(b"a").unwrap();
+        w2.write_all(b"a").unwrap();
+        let mut events = poller.poll_tokens::<u64>(timeout_0).unwrap();
+        events.sort();
+        assert_eq!(events, vec![(100, Events::READ), (u64::MAX, Events::READ)]);
+
+        // The token can be changed with modify_token()
+        poller
+            .modify_token(r1.as_raw_fd(), Events::READ, 200u64)
+            .unwrap();
+        poller.unregister(r2.as_raw_fd()).unwrap();
+        assert_eq!(
+            poller.poll_tokens::<u64>(timeout_0).unwrap(),
+            vec![(200, Events::READ)]
+        );
+
+        // modify() resets the token to the file descriptor
+        poller.modify(r1.as_raw_fd(), Events::READ).unwrap();
+        assert_eq!(
+            poller.poll(timeout_0).unwrap(),
+            vec![(r1.as_raw_fd(), Events::READ)]
+        );
+
+        // Huge timeouts don't overflow
+        assert_eq!(
+            poller.poll(Some(Duration::MAX)).unwrap(),
+            vec![(r1.as_raw_fd(), Events::READ)]
+        );
+    }
+
+    fn check_into<P: Poller>() {
+        let timeout_0 = Some(Duration::from_secs(0));
+
+        let pipes: Vec<_> = (0..3).map(|_| crate::pipe().unwrap()).collect();
+
+        let mut poller = P::new().unwrap();
+        for (i, (r, _)) in pipes.iter().enumerate() {
+            poller
+                .register_token(r.as_raw_fd(), Events::READ, i)
+                .unwrap();
+        }
+
+        let mut events = Vec::with_capacity(8);
+        assert_eq!(poller.poll_into(timeout_0, &mut events).unwrap(), 0);
+        assert_eq!(events, vec![]);
+
+        for (_, w) in pipes.iter() {
+            (&*w).write_all(b"a").unwrap();
+        }
+
+        // The old contents are cleared, and the storage is reused
+        events.push((100, Events::WRITE));
+        let ptr = events.as_ptr();
+        assert_eq!(poller.poll_into(timeout_0, &mut events).unwrap(), 3);
+        assert_eq!(events.as_ptr(), ptr);
+        events.sort();
+        assert_eq!(
+            events,
+            vec![(0, Events::READ), (1, Events::READ), (2, Events::READ)]
+        );
+
+        // Only as many events as will fit in the slice are returned
+        let mut slice = [(0usize, Events::empty()); 2];
+        assert_eq!(poller.poll_into_slice(timeout_0, &mut slice).unwrap(), 2);
+        assert_ne!(slice[0].0, slice[1].0);
+        assert_eq!(slice[0].1, Events::READ);
+        assert_eq!(slice[1].1, Events::READ);
+
+        let mut slice = [(0usize, Events::empty()); 4];
+        assert_eq!(poller.poll_into_slice(timeout_0, &mut slice).unwrap(), 3);
+
+        // File descriptors that stay ready don't starve the others
+        let mut slice = [(0usize, Events::empty()); 1];
+        let mut tokens: Vec<usize> = (0..3)
+            .map(|_| {
+                assert_eq!(poller.poll_into_slice(timeout_0, &mut slice).unwrap(), 1);
+                slice[0].0
+            })
+            .collect();
+        tokens.sort_unstable();
+        assert_eq!(tokens, vec![0, 1, 2]);
+
+        // One-shot file descriptors are only disarmed if their events were returned
+        for (i, (r, _)) in pipes.iter().enumerate() {
+            poller
+                .modify_token(r.as_raw_fd(), Events::READ | Events::ONESHOT, i)
+                .unwrap();
+        }
+        let mut slice = [(0usize, Events::empty()); 1];
+        for _ in 0..3 {
+            assert_eq!(poller.poll_into_slice(timeout_0, &mut slice).unwrap(), 1);
+        }
+        assert_eq!(poller.poll_into_slice(timeout_0, &mut slice).unwrap(), 0);
+
+        // Changing the registrations is reflected in the next call
+        poller.unregister(pipes[0].0.as_raw_fd()).unwrap();
+        poller
+            .modify_token(pipes[1].0.as_raw_fd(), Events::READ, 1)
+            .unwrap();
+        assert_eq!(poller.poll_into(timeout_0, &mut events).unwrap(), 1);
+        assert_eq!(events, vec![(1, Events::READ)]);
+    }
+
+    fn check_waker<P: Poller>() {
+        use std::sync::{Arc, Barrier};
+
+        const WAKER_TOKEN: u64 = u64::MAX;
+
+        let mut poller = P::new().unwrap();
+        let waker = poller.waker(WAKER_TOKEN).unwrap();
+
+        assert_eq!(
+            poller
+                .poll_tokens::<u64>(Some(Duration::from_secs(0)))
+                .unwrap(),
+            vec![]
+        );
+
+        // Waking multiple times is the same as waking once
+        waker.wake().unwrap();
+        waker.wake().unwrap();
+        assert_eq!(
+            poller
+                .poll_tokens::<u64>(Some(Duration::from_secs(0)))
+                .unwrap(),
+            vec![(WAKER_TOKEN, Events::READ)]
+        );
+        waker.reset().unwrap();
+        assert_eq!(
+            poller
+                .poll_tokens::<u64>(Some(Duration::from_secs(0)))
+                .unwrap(),
+            vec![]
+        );
+
+        // Wake up a blocked poll() from another thread
+        let barrier = Arc::new(Barrier::new(2));
+        let thread = {
+            let waker = waker.clone();
+            let barrier = barrier.clone();
+            std::thread::spawn(move || {
+                barrier.wait();
+                std::thread::sleep(Duration::from_millis(20));
+                waker.wake().unwrap();
+            })
+        };
+
+        barrier.wait();
+        assert_eq!(
+            poller.poll_tokens::<u64>(None).unwrap(),
+            vec![(WAKER_TOKEN, Events::READ)]
+        );
+        thread.join().unwrap();
+        waker.reset().unwrap();
+
+        poller.unregister(waker.as_raw_fd()).unwrap();
+    }
+
+    // Not really a benchmark, but running this with `--nocapture` shows the difference between
+    // the backends, and between allocating and reusing storage
+    fn bench_poller<P: Poller>(name: &str) {
+        use std::time::Instant;
+
+        const NPIPES: usize = 64;
+        const ITERATIONS: usize = 1000;
+
+        let timeout_0 = Some(Duration::from_secs(0));
+
+        let pipes: Vec<_> = (0..NPIPES).map(|_| crate::pipe().unwrap()).collect();
+
+        let mut poller = P::new().unwrap();
+        for (i, (r, w)) in pipes.iter().enumerate() {
+            poller
+                .register_token(r.as_raw_fd(), Events::READ, i)
+                .unwrap();
+            // Only half of them are ready
+            if i % 2 == 0 {
+                (&*w).write_all(b"a").unwrap();
+            }
+        }
+
+        let start = Instant::now();
+        for _ in 0..ITERATIONS {
+            assert_eq!(
+                poller.poll_tokens::<usize>(timeout_0).unwrap().len(),
+                NPIPES / 2
+            );
+        }
+        let alloc_time = start.elapsed();
+
+        let mut events: Vec<(usize, Events)> = Vec::with_capacity(NPIPES);
+        let start = Instant::now();
+        for _ in 0..ITERATIONS {
+            assert_eq!(
+                poller.poll_into(timeout_0, &mut events).unwrap(),
+                NPIPES / 2
+            );
+        }
+        let into_time = start.elapsed();
+
+        let mut slice = [(0usize, Events::empty()); NPIPES];
+        let start = Instant::now();
+        for _ in 0..ITERATIONS {
+            assert_eq!(
+                poller.poll_into_slice(timeout_0, &mut slice).unwrap(),
+                NPIPES / 2
+            );
+        }
+        let slice_time = start.elapsed();
+
+        println!(
+            "{}: poll_tokens() {:?}, poll_into() {:?}, poll_into_slice() {:?} ({} iterations)",
+            name, alloc_time, into_time, slice_time, ITERATIONS,
+        );
+    }
+
+    // io_uring may not be supported by the kernel (or it may be disabled)
+    #[cfg(target_os = "linux")]
+    pub(super) fn io_uring_supported() -> bool {
+        match IoUringPoller::new() {
+            Ok(_) => true,
+            Err(e) if e.raw_os_error() == Some(libc::ENOSYS) => false,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn test_bench_pollers() {
+        #[cfg(target_os = "linux")]
+        bench_poller::<EpollPoller>("epoll");
+        #[cfg(target_os = "linux")]
+        {
+            if io_uring_supported() {
+                bench_poller::<IoUringPoller>("io_uring");
+            }
+        }
+        bench_poller::<PollPoller>("poll");
+        bench_poller::<SelectPoller>("select");
+    }
+}
@@ -3,7 +3,7 @@ use std::io;
 use std::os::unix::prelude::*;
 use std::time::Duration;
 
-use super::{Events, Poller};
+use super::{slice_sink, vec_sink, Events, Poller, Token};
 use crate::poll::{poll, Events as PollEvents, PollFd};
 
 #[cfg(any(
@@ -15,24 +15,52 @@ use crate::poll::{poll, Events as PollEvents, PollFd};
 ))]
 use crate::poll::ppoll;
 
+#[derive(Copy, Clone, Debug)]
+struct Registration {
+    fd: RawFd,
+    events: Events,
+    token: u64,
+}
+
 #[derive(Debug)]
 pub struct PollPoller {
     pollfds: Vec<PollFd>,
+    // The registrations, in the same order as `pollfds`. (The corresponding entry in `pollfds`
+    // has its file descriptor set to -1 if it has been disarmed in one-shot mode.)
+    regs: Vec<Registration>,
     fdset: HashSet<RawFd>,
+    // The index in `regs` to start collecting events from, so that when events are truncated
+    // (by poll_into_slice()) the ones that were left out are returned first next time
+    next_start: usize,
 }
 
 impl PollPoller {
+    // The mapping between our events and poll()'s events
+    #[cfg(target_os = "linux")]
+    const EVENT_MAP: [(Events, PollEvents); 6] = [
+        (Events::READ, PollEvents::IN),
+        (Events::WRITE, PollEvents::OUT),
+        (Events::ERROR, PollEvents::ERR),
+        (Events::HUP, PollEvents::HUP),
+        (Events::RDHUP, PollEvents::RDHUP),
+        (Events::PRIORITY, PollEvents::PRI),
+    ];
+    #[cfg(not(target_os = "linux"))]
+    const EVENT_MAP: [(Events, PollEvents); 5] = [
+        (Events::READ, PollEvents::IN),
+        (Events::WRITE, PollEvents::OUT),
+        (Events::ERROR, PollEvents::ERR),
+        (Events::HUP, PollEvents::HUP),
+        (Events::PRIORITY, PollEvents::PRI),
+    ];
+
     fn translate_events(events: Events) -> PollEvents {
         let mut ev = PollEvents::empty();
 
-        if events.contains(Events::READ) {
-            ev.insert(PollEvents::IN);
-        }
-        if events.contains(Events::WRITE) {
-            ev.insert(PollEvents::OUT);
-        }
-        if events.contains(Events::ERROR) {
-            ev.insert(PollEvents::ERR);
+        for &(event, poll_event) in Self::EVENT_MAP.iter() {
+            if events.contains(event) {
+                ev.insert(poll_event);
+            }
         }
 
         ev
@@ -41,14 +69,10 @@ impl PollPoller {
     fn translate_events_rev(events: PollEvents) -> Option<Events> {
         let mut ev = Events::empty();
 
-        if events.contains(PollEvents::IN) {
-            ev.insert(Events::READ);
-        }
-        if events.contains(PollEvents::OUT) {
-            ev.insert(Events::WRITE);
-        }
-        if events.contains(PollEvents::ERR) {
-            ev.insert(Events::ERROR);
+        for &(event, poll_event) in Self::EVENT_MAP.iter() {
+            if events.contains(poll_event) {
+                ev.insert(event);
+            }
         }
 
         if ev.is_empty() {
@@ -58,11 +82,42 @@ impl PollPoller {
         }
     }
 
-    #[allow(clippy::trivially_copy_pass_by_ref)]
-    fn translate_pollfd_event(pfd: &PollFd) -> Option<(RawFd, Events)> {
-        match Self::translate_events_rev(pfd.revents) {
-            Some(ev) => Some((pfd.fd, ev)),
-            None => None,
+    // Collect the events that were reported by poll()/ppoll() (which returned n) and pass them to
+    // `push` (stopping if it returns false), and disarm any one-shot file descriptors whose
+    // events were collected.
+    fn collect_events<T: Token, F: FnMut((T, Events)) -> bool>(&mut self, n: usize, mut push: F) {
+        let len = self.regs.len();
+        let start = if self.next_start < len {
+            self.next_start
+        } else {
+            0
+        };
+
+        let mut seen = 0;
+
+        for i in (start..len).chain(0..start) {
+            if seen >= n {
+                break;
+            }
+
+            let pfd = &mut self.pollfds[i];
+            let reg = &self.regs[i];
+
+            if pfd.revents.is_empty() {
+                continue;
+            }
+            seen += 1;
+
+            if let Some(ev) = Self::translate_events_rev(pfd.revents) {
+                if !push((T::from_u64(reg.token), ev)) {
+                    self.next_start = i;
+                    break;
+                }
+
+                if reg.events.contains(Events::ONESHOT) {
+                    pfd.fd = -1;
+                }
+            }
         }
     }
 }
@@ -71,11 +126,13 @@ impl Poller for PollPoller {
     fn new() -> io::Result<Self> {
         Ok(Self {
             pollfds: Vec::new(),
+            regs: Vec::new(),
             fdset: HashSet::new(),
+            next_start: 0,
         })
     }
 
-    fn register(&mut self, fd: RawFd, events: Events) -> io::Result<()> {
+    fn register_token<T: Token>(&mut self, fd: RawFd, events: Events, token: T) -> io::Result<()> {
         if self.fdset.contains(&fd) {
             Err(io::Error::from_raw_os_error(libc::EEXIST))
         } else {
@@ -84,6 +141,11 @@ impl Poller for PollPoller {
                 events: Self::translate_events(events),
                 revents: PollEvents::empty(),
             });
+            self.regs.push(Registration {
+                fd,
+                events,
+                token: token.into_u64(),
+            });
 
             self.fdset.insert(fd);
 
@@ -93,8 +155,9 @@ impl Poller for PollPoller {
 
     fn unregister(&mut self, fd: RawFd) -> io::Result<()> {
         if self.fdset.contains(&fd) {
-            if let Some(index) = self.pollfds.iter().position(|pfd| pfd.fd == fd) {
+            if let Some(index) = self.regs.iter().position(|reg| reg.fd == fd) {
                 self.pollfds.remove(index);
+                self.regs.remove(index);
             }
 
             self.fdset.remove(&fd);
@@ -105,10 +168,14 @@ impl Poller for PollPoller {
         }
     }
 
-    fn modify(&mut self, fd: RawFd, events: Events) -> io::Result<()> {
-        for pfd in self.pollfds.iter_mut() {
-            if pfd.fd == fd {
+    fn modify_token<T: Token>(&mut self, fd: RawFd, events: Events, token: T) -> io::Result<()> {
+        for (pfd, reg) in self.pollfds.iter_mut().zip(self.regs.iter_mut()) {
+            if reg.fd == fd {
+                // This also re-arms the file descriptor if it was disarmed
+                pfd.fd = fd;
                 pfd.events = Self::translate_events(events);
+                reg.events = events;
+                reg.token = token.into_u64();
                 return Ok(());
             }
         }
@@ -116,14 +183,37 @@ impl Poller for PollPoller {
         Err(io::Error::from_raw_os_error(libc::ENOENT))
     }
 
-    fn poll(&mut self, timeout: Option<Duration>) -> io::Result<Vec<(RawFd, Events)>> {
+    fn poll_into<T: Token>(
+        &mut self,
+        timeout: Option<Duration>,
+        events: &mut Vec<(T, Events)>,
+    ) -> io::Result<usize> {
+        // The pollfd array is kept up to date by register()/unregister()/modify(), so it can be
+        // passed directly to poll().
+        let n = poll(&mut self.pollfds, timeout)?;
+        self.collect_events(n, vec_sink(events));
+        Ok(events.len())
+    }
+
+    fn poll_into_slice<T: Token>(
+        &mut self,
+        timeout: Option<Duration>,
+        events: &mut [(T, Events)],
+    ) -> io::Result<usize> {
         let n = poll(&mut self.pollfds, timeout)?;
-        Ok(self
-            .pollfds
-            .iter()
-            .filter_map(Self::translate_pollfd_event)
-            .take(n)
-            .collect())
+        let mut count = 0;
+        self.collect_events(n, slice_sink(events, &mut count));
+        Ok(count)
+    }
+
+    #[inline]
+    fn native_events() -> Events {
+        #[cfg(target_os = "linux")]
+        let rdhup = Events::RDHUP;
+        #[cfg(not(target_os = "linux"))]
+        let rdhup = Events::empty();
+
+        Events::READ | Events::WRITE | Events::ERROR | Events::HUP | Events::PRIORITY | rdhup
     }
 }
 
@@ -135,18 +225,27 @@ impl Poller for PollPoller {
     target_os = "dragonfly",
 ))]
 impl super::Ppoller for PollPoller {
-    fn ppoll(
+    fn ppoll_into<T: Token>(
+        &mut self,
+        timeout: Option<Duration>,
+        sigmask: Option<crate::signal::Sigset>,
+        events: &mut Vec<(T, Events)>,
+    ) -> io::Result<usize> {
+        let n = ppoll(&mut self.pollfds, timeout, sigmask)?;
+        self.collect_events(n, vec_sink(events));
+        Ok(events.len())
+    }
+
+    fn ppoll_into_slice<T: Token>(
         &mut self,
         timeout: Option<Duration>,
         sigmask: Option<crate::signal::Sigset>,
-    ) -> io::Result<Vec<(RawFd, Events)>> {
+        events: &mut [(T, Events)],
+    ) -> io::Result<usize> {
         let n = ppoll(&mut self.pollfds, timeout, sigmask)?;
-        Ok(self
-            .pollfds
-            .iter()
-            .filter_map(Self::translate_pollfd_event)
-            .take(n)
-            .collect())
+        let mut count = 0;
+        self.collect_events(n, slice_sink(events, &mut count));
+        Ok(count)
     }
 }
 
@@ -156,6 +255,12 @@ mod tests {
 
     use std::io::Write;
 
+    #[test]
+    fn test_poll_poller_common() {
+        assert!(!PollPoller::native_events().intersects(Events::EDGE | Events::ONESHOT));
+        crate::pollers::tests::check_poller::<PollPoller>(PollPoller::native_events());
+    }
+
     #[test]
     fn test_poll_poller() {
         let timeout_0 = Some(Duration::from_secs(0));
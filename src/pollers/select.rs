@@ -1,23 +1,40 @@
 use std::collections::hash_map;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::io;
 use std::os::unix::prelude::*;
 use std::time::Duration;
 
-use super::{Events, Poller, Ppoller};
+use super::{slice_sink, vec_sink, Events, Poller, Ppoller, Token};
 use crate::select::{build_fdset_opt, pselect_raw, FdSet};
 use crate::signal::Sigset;
 
 #[derive(Debug)]
 pub struct SelectPoller {
-    files: HashMap<RawFd, Events>,
+    // The events being monitored for each file descriptor, and its token
+    files: HashMap<RawFd, (Events, u64)>,
+    // File descriptors registered in one-shot mode that have been disarmed
+    disarmed: HashSet<RawFd>,
+    // The file descriptor sets built from `files` and `disarmed`, or `None` if they have changed
+    // since the sets were last built
+    fdsets: Option<FdSets>,
+    // The file descriptor to start collecting events from, so that when events are truncated (by
+    // poll_into_slice()) the ones that were left out are returned first next time
+    next_start: RawFd,
+}
+
+#[derive(Copy, Clone, Debug)]
+struct FdSets {
+    read: Option<FdSet>,
+    write: Option<FdSet>,
+    error: Option<FdSet>,
+    nfds: RawFd,
 }
 
 impl SelectPoller {
     fn build_fdset(&self, events: Events, nfds: RawFd) -> (Option<FdSet>, RawFd) {
         build_fdset_opt(
-            self.files.iter().filter_map(|(fd, mon_ev)| {
-                if mon_ev.contains(events) {
+            self.files.iter().filter_map(|(fd, (mon_ev, _))| {
+                if mon_ev.intersects(events) && !self.disarmed.contains(fd) {
                     Some(*fd)
                 } else {
                     None
@@ -26,58 +43,40 @@ impl SelectPoller {
             nfds,
         )
     }
-}
-
-impl Poller for SelectPoller {
-    #[inline]
-    fn new() -> io::Result<Self> {
-        Ok(Self {
-            files: HashMap::new(),
-        })
-    }
-
-    fn register(&mut self, fd: RawFd, events: Events) -> io::Result<()> {
-        match self.files.entry(fd) {
-            hash_map::Entry::Vacant(e) => {
-                e.insert(events);
-                Ok(())
-            }
-            hash_map::Entry::Occupied(_) => Err(io::Error::from_raw_os_error(libc::EEXIST)),
-        }
-    }
 
-    fn unregister(&mut self, fd: RawFd) -> io::Result<()> {
-        if self.files.remove(&fd).is_some() {
-            Ok(())
-        } else {
-            Err(io::Error::from_raw_os_error(libc::ENOENT))
+    fn get_fdsets(&mut self) -> FdSets {
+        if let Some(fdsets) = self.fdsets {
+            return fdsets;
         }
-    }
 
-    fn modify(&mut self, fd: RawFd, events: Events) -> io::Result<()> {
-        match self.files.entry(fd) {
-            hash_map::Entry::Occupied(mut e) => {
-                e.insert(events);
-                Ok(())
-            }
-            hash_map::Entry::Vacant(_) => Err(io::Error::from_raw_os_error(libc::ENOENT)),
-        }
-    }
+        let (read, nfds) = self.build_fdset(Events::READ, 0);
+        let (write, nfds) = self.build_fdset(Events::WRITE, nfds);
+        let (error, nfds) = self.build_fdset(Events::ERROR | Events::PRIORITY, nfds);
 
-    fn poll(&mut self, timeout: Option<Duration>) -> io::Result<Vec<(RawFd, Events)>> {
-        self.ppoll(timeout, None)
+        let fdsets = FdSets {
+            read,
+            write,
+            error,
+            nfds,
+        };
+        self.fdsets = Some(fdsets);
+        fdsets
     }
-}
 
-impl Ppoller for SelectPoller {
-    fn ppoll(
+    // Wait for events and pass them to `push` (stopping if it returns false)
+    fn wait<T: Token, F: FnMut((T, Events)) -> bool>(
         &mut self,
         timeout: Option<Duration>,
         sigmask: Option<Sigset>,
-    ) -> io::Result<Vec<(RawFd, Events)>> {
-        let (mut read_fdset, nfds) = self.build_fdset(Events::READ, 0);
-        let (mut write_fdset, nfds) = self.build_fdset(Events::WRITE, nfds);
-        let (mut error_fdset, nfds) = self.build_fdset(Events::ERROR, nfds);
+        mut push: F,
+    ) -> io::Result<()> {
+        // pselect() modifies the sets, so this works on copies of the cached sets
+        let FdSets {
+            read: mut read_fdset,
+            write: mut write_fdset,
+            error: mut error_fdset,
+            nfds,
+        } = self.get_fdsets();
 
         let n = pselect_raw(
             nfds,
@@ -88,39 +87,154 @@ impl Ppoller for SelectPoller {
             sigmask,
         )?;
 
-        let mut res: Vec<(RawFd, Events)> = Vec::with_capacity(n);
+        let start = if self.next_start < nfds {
+            self.next_start
+        } else {
+            0
+        };
+
+        let mut seen = 0;
 
-        for fd in self.files.keys() {
-            if res.len() >= n {
+        for fd in (start..nfds).chain(0..start) {
+            if seen >= n {
                 break;
             }
 
+            let (mon_ev, token) = match self.files.get(&fd) {
+                Some(&file) => file,
+                None => continue,
+            };
+
             let mut triggered_events = Events::empty();
 
             if let Some(mut s) = read_fdset {
-                if s.contains(*fd) {
+                if s.contains(fd) {
                     triggered_events |= Events::READ;
+                    seen += 1;
                 }
             }
 
             if let Some(mut s) = write_fdset {
-                if s.contains(*fd) {
+                if s.contains(fd) {
                     triggered_events |= Events::WRITE;
+                    seen += 1;
                 }
             }
 
             if let Some(mut s) = error_fdset {
-                if s.contains(*fd) {
-                    triggered_events |= Events::ERROR;
+                if s.contains(fd) {
+                    triggered_events |= mon_ev & (Events::ERROR | Events::PRIORITY);
+                    seen += 1;
                 }
             }
 
             if !triggered_events.is_empty() {
-                res.push((*fd, triggered_events));
+                if !push((T::from_u64(token), triggered_events)) {
+                    self.next_start = fd;
+                    break;
+                }
+
+                if mon_ev.contains(Events::ONESHOT) {
+                    self.disarmed.insert(fd);
+                    self.fdsets = None;
+                }
             }
         }
 
-        Ok(res)
+        Ok(())
+    }
+}
+
+impl Poller for SelectPoller {
+    #[inline]
+    fn new() -> io::Result<Self> {
+        Ok(Self {
+            files: HashMap::new(),
+            disarmed: HashSet::new(),
+            fdsets: None,
+            next_start: 0,
+        })
+    }
+
+    fn register_token<T: Token>(&mut self, fd: RawFd, events: Events, token: T) -> io::Result<()> {
+        match self.files.entry(fd) {
+            hash_map::Entry::Vacant(e) => {
+                e.insert((events, token.into_u64()));
+                self.fdsets = None;
+                Ok(())
+            }
+            hash_map::Entry::Occupied(_) => Err(io::Error::from_raw_os_error(libc::EEXIST)),
+        }
+    }
+
+    fn unregister(&mut self, fd: RawFd) -> io::Result<()> {
+        if self.files.remove(&fd).is_some() {
+            self.disarmed.remove(&fd);
+            self.fdsets = None;
+            Ok(())
+        } else {
+            Err(io::Error::from_raw_os_error(libc::ENOENT))
+        }
+    }
+
+    fn modify_token<T: Token>(&mut self, fd: RawFd, events: Events, token: T) -> io::Result<()> {
+        match self.files.entry(fd) {
+            hash_map::Entry::Occupied(mut e) => {
+                e.insert((events, token.into_u64()));
+                // Re-arm it if it was disarmed
+                self.disarmed.remove(&fd);
+                self.fdsets = None;
+                Ok(())
+            }
+            hash_map::Entry::Vacant(_) => Err(io::Error::from_raw_os_error(libc::ENOENT)),
+        }
+    }
+
+    #[inline]
+    fn poll_into<T: Token>(
+        &mut self,
+        timeout: Option<Duration>,
+        events: &mut Vec<(T, Events)>,
+    ) -> io::Result<usize> {
+        self.ppoll_into(timeout, None, events)
+    }
+
+    #[inline]
+    fn poll_into_slice<T: Token>(
+        &mut self,
+        timeout: Option<Duration>,
+        events: &mut [(T, Events)],
+    ) -> io::Result<usize> {
+        self.ppoll_into_slice(timeout, None, events)
+    }
+
+    #[inline]
+    fn native_events() -> Events {
+        // select() reports priority data as an "exceptional condition"
+        Events::READ | Events::WRITE | Events::ERROR | Events::PRIORITY
+    }
+}
+
+impl Ppoller for SelectPoller {
+    fn ppoll_into<T: Token>(
+        &mut self,
+        timeout: Option<Duration>,
+        sigmask: Option<Sigset>,
+        events: &mut Vec<(T, Events)>,
+    ) -> io::Result<usize> {
+        self.wait(timeout, sigmask, vec_sink(events))?;
+        Ok(events.len())
+    }
+
+    fn ppoll_into_slice<T: Token>(
+        &mut self,
+        timeout: Option<Duration>,
+        sigmask: Option<Sigset>,
+        events: &mut [(T, Events)],
+    ) -> io::Result<usize> {
+        let mut count = 0;
+        self.wait(timeout, sigmask, slice_sink(events, &mut count))?;
+        Ok(count)
     }
 }
 
@@ -132,6 +246,12 @@ mod tests {
     use std::io::Write;
     use std::iter::FromIterator;
 
+    #[test]
+    fn test_select_poller_common() {
+        assert!(!SelectPoller::native_events().intersects(Events::EDGE | Events::ONESHOT));
+        crate::pollers::tests::check_poller::<SelectPoller>(SelectPoller::native_events());
+    }
+
     #[test]
     fn test_select_poller() {
         let timeout_0 = Some(Duration::from_secs(0));
@@ -0,0 +1,117 @@
+use std::fs;
+use std::io;
+use std::io::{Read, Write};
+use std::os::unix::prelude::*;
+use std::sync::Arc;
+
+use super::{Events, Poller, Token};
+use crate::error;
+
+#[cfg(target_os = "linux")]
+use crate::eventfd::EventFd;
+
+#[derive(Debug)]
+enum WakerFds {
+    #[cfg(target_os = "linux")]
+    EventFd(EventFd),
+    // The read and write ends of a nonblocking pipe
+    Pipe(fs::File, fs::File),
+}
+
+/// A handle that can be used to wake up a thread that is blocked in `Poller::poll()` (or
+/// `Ppoller::ppoll()`), usually from another thread.
+///
+/// A `Waker` is created with `Poller::waker()`, which registers a file descriptor with the
+/// poller for readability (`EpollPoller` uses an `EventFd`; other backends use a pipe). When
+/// `wake()` is called, that file descriptor becomes readable, so `poll()` returns an event with
+/// the waker's token. The waker stays "woken" until `reset()` is called.
+///
+/// `Waker`s can be cloned and shared between threads; all of the clones refer to the same file
+/// descriptor. Before the last clone is dropped, the file descriptor should be unregistered from
+/// the poller (with `poller.unregister(waker.as_raw_fd())`).
+#[derive(Clone, Debug)]
+pub struct Waker {
+    fds: Arc<WakerFds>,
+}
+
+impl Waker {
+    /// Create a `Waker` backed by an `EventFd`, and register it with `poller`.
+    #[cfg(target_os = "linux")]
+    pub fn new_eventfd<P: Poller, T: Token>(poller: &mut P, token: T) -> io::Result<Self> {
+        Self::new(
+            poller,
+            token,
+            WakerFds::EventFd(EventFd::new(0, false, true)?),
+        )
+    }
+
+    /// Create a `Waker` backed by a pipe, and register it with `poller`.
+    pub fn new_pipe<P: Poller, T: Token>(poller: &mut P, token: T) -> io::Result<Self> {
+        let (r, w) = crate::pipe()?;
+        crate::fcntl::set_nonblocking(r.as_raw_fd(), true)?;
+        crate::fcntl::set_nonblocking(w.as_raw_fd(), true)?;
+
+        Self::new(poller, token, WakerFds::Pipe(r, w))
+    }
+
+    fn new<P: Poller, T: Token>(poller: &mut P, token: T, fds: WakerFds) -> io::Result<Self> {
+        let waker = Self { fds: Arc::new(fds) };
+        poller.register_token(waker.as_raw_fd(), Events::READ, token)?;
+        Ok(waker)
+    }
+
+    /// Wake up the poller that this `Waker` is registered with.
+    ///
+    /// Waking a `Waker` that has already been woken (and not reset) has no effect.
+    pub fn wake(&self) -> io::Result<()> {
+        let res = match &*self.fds {
+            #[cfg(target_os = "linux")]
+            WakerFds::EventFd(efd) => efd.write(1),
+            WakerFds::Pipe(_, w) => (&*w).write_all(&[1]),
+        };
+
+        match res {
+            // The counter is full/the pipe is full, so it's already readable
+            Err(e) if error::is_eagain(&e) => Ok(()),
+            res => res,
+        }
+    }
+
+    /// Reset the `Waker` so that the file descriptor is no longer readable.
+    ///
+    /// This should be called after `poll()` reports an event for this `Waker`.
+    pub fn reset(&self) -> io::Result<()> {
+        match &*self.fds {
+            #[cfg(target_os = "linux")]
+            WakerFds::EventFd(efd) => match efd.read() {
+                Err(e) if error::is_eagain(&e) => Ok(()),
+                res => res.map(|_| ()),
+            },
+
+            WakerFds::Pipe(r, _) => {
+                let mut buf = [0; 64];
+                loop {
+                    match (&*r).read(&mut buf) {
+                        Ok(0) => return Ok(()),
+                        Ok(_) => (),
+                        Err(e) if error::is_eagain(&e) => return Ok(()),
+                        Err(e) if error::is_eintr(&e) => (),
+                        Err(e) => return Err(e),
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl AsRawFd for Waker {
+    /// Get the file descriptor that is registered with the poller.
+    #[inline]
+    fn as_raw_fd(&self) -> RawFd {
+        match &*self.fds {
+            #[cfg(target_os = "linux")]
+            WakerFds::EventFd(efd) => efd.as_raw_fd(),
+            WakerFds::Pipe(r, _) => r.as_raw_fd(),
+        }
+    }
+}
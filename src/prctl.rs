@@ -1,11 +1,17 @@
+use std::collections::HashSet;
 use std::convert::TryInto;
-use std::ffi::OsStr;
+use std::ffi::{CString, OsStr};
 use std::fmt;
 use std::io;
 use std::iter::FromIterator;
 use std::ops::{BitAnd, BitOr, BitXor, Not, Sub};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::MetadataExt;
+use std::sync::atomic::{AtomicI32, AtomicPtr, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
-#[cfg(any(all(feature = "serde", feature = "strum"), test))]
+#[cfg(any(feature = "strum", test))]
 use std::str::FromStr;
 
 #[cfg(feature = "serde")]
@@ -18,7 +24,7 @@ use crate::error;
 use crate::externs;
 use crate::types;
 
-use crate::{Int, PidT, UidT, Ulong};
+use crate::{GidT, Int, PidT, UidT, Ulong};
 
 #[cfg_attr(
     any(feature = "strum", test),
@@ -322,6 +328,13 @@ impl CapSet {
         }
     }
 
+    // Unlike from_bits_safe(), this keeps bits for capabilities that we don't know about
+    #[cfg(any(feature = "strum", test))]
+    #[inline]
+    const fn from_bits_retain(bits: u64) -> Self {
+        Self { bits }
+    }
+
     #[cfg(feature = "serde")]
     fn from_bits_checked(bits: u64) -> Option<Self> {
         if bits & (!CAP_BITMASK) == 0 {
@@ -608,6 +621,47 @@ impl FileCaps {
         Self::extract_attr_or_error(&data, res)
     }
 
+    /// Write these file capabilities to the `security.capability` extended attribute of the
+    /// given file.
+    ///
+    /// This requires `CAP_SETFCAP`.
+    pub fn set_for_file<P: AsRef<OsStr>>(&self, path: P, follow_links: bool) -> io::Result<()> {
+        crate::xattr::setxattr(
+            path,
+            constants::XATTR_NAME_CAPS,
+            &self.pack_attrs(),
+            crate::xattr::SetFlags::empty(),
+            follow_links,
+        )
+    }
+
+    /// Write these file capabilities to the `security.capability` extended attribute of the
+    /// file referred to by the given file descriptor.
+    ///
+    /// This requires `CAP_SETFCAP`.
+    pub fn set_for_fd(&self, fd: Int) -> io::Result<()> {
+        crate::xattr::fsetxattr(
+            fd,
+            constants::XATTR_NAME_CAPS,
+            &self.pack_attrs(),
+            crate::xattr::SetFlags::empty(),
+        )
+    }
+
+    /// Remove all file capabilities from the given file.
+    ///
+    /// This fails with `ENODATA` if the file does not have any capabilities.
+    pub fn remove_for_file<P: AsRef<OsStr>>(path: P, follow_links: bool) -> io::Result<()> {
+        crate::xattr::removexattr(path, constants::XATTR_NAME_CAPS, follow_links)
+    }
+
+    /// Remove all file capabilities from the file referred to by the given file descriptor.
+    ///
+    /// This fails with `ENODATA` if the file does not have any capabilities.
+    pub fn remove_for_fd(fd: Int) -> io::Result<()> {
+        crate::xattr::fremovexattr(fd, constants::XATTR_NAME_CAPS)
+    }
+
     fn extract_attr_or_error(data: &[u8], attr_res: io::Result<usize>) -> io::Result<Option<Self>> {
         match attr_res {
             Ok(n) => Ok(Some(Self::unpack_attrs(&data[..n])?)),
@@ -621,6 +675,40 @@ impl FileCaps {
         }
     }
 
+    /// Pack these file capabilities into the format used for the `security.capability`
+    /// extended attribute.
+    ///
+    /// If `rootid` is `None`, a `VFS_CAP_REVISION_2` attribute is produced; otherwise a
+    /// `VFS_CAP_REVISION_3` attribute (which makes the capabilities only apply in user
+    /// namespaces where `rootid` is mapped to root) is produced.
+    pub fn pack_attrs(&self) -> Vec<u8> {
+        let mut magic = if self.rootid.is_some() {
+            constants::VFS_CAP_REVISION_3
+        } else {
+            constants::VFS_CAP_REVISION_2
+        };
+
+        if self.effective {
+            magic |= constants::VFS_CAP_FLAGS_EFFECTIVE;
+        }
+
+        let permitted = self.permitted.bits;
+        let inheritable = self.inheritable.bits;
+
+        let mut attrs = Vec::with_capacity(constants::XATTR_CAPS_MAX_SIZE);
+        attrs.extend_from_slice(&magic.to_le_bytes());
+        attrs.extend_from_slice(&(permitted as u32).to_le_bytes());
+        attrs.extend_from_slice(&(inheritable as u32).to_le_bytes());
+        attrs.extend_from_slice(&((permitted >> 32) as u32).to_le_bytes());
+        attrs.extend_from_slice(&((inheritable >> 32) as u32).to_le_bytes());
+
+        if let Some(rootid) = self.rootid {
+            attrs.extend_from_slice(&rootid.to_le_bytes());
+        }
+
+        attrs
+    }
+
     pub fn unpack_attrs(attrs: &[u8]) -> io::Result<Self> {
         let len = attrs.len();
 
@@ -679,6 +767,328 @@ impl FileCaps {
     }
 }
 
+// The values libcap uses for the different sets when converting to/from text. These determine the
+// order in which clauses are output, so they must match libcap's for the output to be identical.
+#[cfg(any(feature = "strum", test))]
+const TEXT_EFFECTIVE: usize = 1;
+#[cfg(any(feature = "strum", test))]
+const TEXT_PERMITTED: usize = 2;
+#[cfg(any(feature = "strum", test))]
+const TEXT_INHERITABLE: usize = 4;
+
+#[cfg(any(feature = "strum", test))]
+fn text_flags_str(flags: usize) -> String {
+    let mut s = String::new();
+
+    if flags & TEXT_EFFECTIVE != 0 {
+        s.push('e');
+    }
+    if flags & TEXT_INHERITABLE != 0 {
+        s.push('i');
+    }
+    if flags & TEXT_PERMITTED != 0 {
+        s.push('p');
+    }
+
+    s
+}
+
+#[cfg(any(feature = "strum", test))]
+fn text_cap_flags(state: &CapState, bit: u64) -> usize {
+    let mut flags = 0;
+    if state.effective.bits & (1 << bit) != 0 {
+        flags |= TEXT_EFFECTIVE;
+    }
+    if state.permitted.bits & (1 << bit) != 0 {
+        flags |= TEXT_PERMITTED;
+    }
+    if state.inheritable.bits & (1 << bit) != 0 {
+        flags |= TEXT_INHERITABLE;
+    }
+    flags
+}
+
+/// Format the given capability state in the same way as libcap's `cap_to_text()`.
+#[cfg(any(feature = "strum", test))]
+fn caps_to_text(state: &CapState) -> String {
+    let cap_flags: Vec<(Cap, usize)> = Cap::iter()
+        .map(|cap| (cap, text_cap_flags(state, cap as u64)))
+        .collect();
+
+    let mut histo = [0; 8];
+    for &(_, flags) in cap_flags.iter() {
+        histo[flags] += 1;
+    }
+
+    // The most common combination of flags is used as the "base"; libcap breaks ties by
+    // preferring lower values.
+    let mut base = 7;
+    for flags in (0..7).rev() {
+        if histo[flags] >= histo[base] {
+            base = flags;
+        }
+    }
+
+    let mut clauses = Vec::new();
+
+    for flags in (0..8).rev() {
+        if flags == base || histo[flags] == 0 {
+            continue;
+        }
+
+        let mut clause = cap_flags
+            .iter()
+            .filter(|&&(_, f)| f == flags)
+            .map(|(cap, _)| cap.to_string().to_lowercase())
+            .collect::<Vec<String>>()
+            .join(",");
+
+        let raised = flags & !base;
+        if raised != 0 {
+            // If the base is empty, the first clause is written as "caps=flags" instead of
+            // "= caps+flags".
+            clause.push(if base == 0 && clauses.is_empty() {
+                '='
+            } else {
+                '+'
+            });
+            clause.push_str(&text_flags_str(raised));
+        }
+
+        let lowered = base & !flags;
+        if lowered != 0 {
+            clause.push('-');
+            clause.push_str(&text_flags_str(lowered));
+        }
+
+        clauses.push(clause);
+    }
+
+    if base != 0 || clauses.is_empty() {
+        clauses.insert(0, format!("={}", text_flags_str(base)));
+    }
+
+    // Capabilities that we don't know about are listed by number at the end, always with '+'
+    let unknown_flags: Vec<(u64, usize)> = (constants::CAP_MAX as u64 + 1..64)
+        .map(|bit| (bit, text_cap_flags(state, bit)))
+        .collect();
+
+    for flags in (1..8).rev() {
+        let caps = unknown_flags
+            .iter()
+            .filter(|&&(_, f)| f == flags)
+            .map(|(bit, _)| bit.to_string())
+            .collect::<Vec<String>>();
+
+        if !caps.is_empty() {
+            clauses.push(format!("{}+{}", caps.join(","), text_flags_str(flags)));
+        }
+    }
+
+    clauses.join(" ")
+}
+
+#[cfg(any(feature = "strum", test))]
+fn parse_text_cap(name: &str) -> io::Result<u64> {
+    if !name.is_empty() && name.bytes().all(|c| c.is_ascii_digit()) {
+        // libcap accepts numbers for any of the 64 possible capabilities. Numbers above
+        // Cap::CheckpointRestore are kept in the resulting sets (and formatted as numbers).
+        return match name.parse::<u64>() {
+            Ok(n) if n < 64 => Ok(1 << n),
+            _ => Err(io::Error::from_raw_os_error(libc::EINVAL)),
+        };
+    }
+
+    match Cap::from_str(&name.to_uppercase()) {
+        Ok(cap) => Ok(cap.to_single_bitfield()),
+        Err(_) => Err(io::Error::from_raw_os_error(libc::EINVAL)),
+    }
+}
+
+#[cfg(any(feature = "strum", test))]
+fn parse_text_caplist(list: &str) -> io::Result<u64> {
+    let mut bits = 0;
+
+    for name in list.split(',') {
+        let mut parts = name.split('-');
+        let first = parts.next().unwrap_or("");
+
+        if first.eq_ignore_ascii_case("all") {
+            // "all-cap_foo-cap_bar" means "all capabilities except cap_foo and cap_bar". ("all"
+            // only includes the capabilities that we know about.)
+            let mut all_bits = CAP_BITMASK;
+            for excluded in parts {
+                all_bits &= !parse_text_cap(excluded)?;
+            }
+            bits |= all_bits;
+        } else if parts.next().is_none() {
+            bits |= parse_text_cap(first)?;
+        } else {
+            return Err(io::Error::from_raw_os_error(libc::EINVAL));
+        }
+    }
+
+    Ok(bits)
+}
+
+/// Parse the given text in the same way as libcap's `cap_from_text()`.
+#[cfg(any(feature = "strum", test))]
+fn caps_from_text(text: &str) -> io::Result<CapState> {
+    // Indexed by TEXT_EFFECTIVE/TEXT_PERMITTED/TEXT_INHERITABLE
+    let mut sets = [0u64; 5];
+
+    for clause in text.split_whitespace() {
+        // Find where the capability list ends and the operators begin. "all-cap_foo" means the
+        // '-' may be part of the list, so we look for the first operator that is followed by
+        // flags (or the end of the clause).
+        let bytes = clause.as_bytes();
+        let ops_start = (0..bytes.len())
+            .find(|&i| match bytes[i] {
+                b'=' => true,
+                b'+' | b'-' => matches!(
+                    bytes.get(i + 1),
+                    Some(b'e') | Some(b'i') | Some(b'p') | None
+                ),
+                _ => false,
+            })
+            .ok_or_else(|| io::Error::from_raw_os_error(libc::EINVAL))?;
+
+        let (list, mut ops) = clause.split_at(ops_start);
+
+        let caps = if list.is_empty() {
+            // Only "=" may be used without a list of capabilities, in which case it applies to
+            // all of the ones that we know about.
+            if !ops.starts_with('=') {
+                return Err(io::Error::from_raw_os_error(libc::EINVAL));
+            }
+            CAP_BITMASK
+        } else {
+            parse_text_caplist(list)?
+        };
+
+        while let Some(op) = ops.chars().next() {
+            ops = &ops[1..];
+
+            let flags_end = ops.find(&['=', '+', '-'][..]).unwrap_or(ops.len());
+            let (flags_str, rest) = ops.split_at(flags_end);
+            ops = rest;
+
+            let mut flags = 0;
+            for c in flags_str.chars() {
+                flags |= match c {
+                    'e' => TEXT_EFFECTIVE,
+                    'i' => TEXT_INHERITABLE,
+                    'p' => TEXT_PERMITTED,
+                    _ => return Err(io::Error::from_raw_os_error(libc::EINVAL)),
+                };
+            }
+
+            if op != '=' && flags == 0 {
+                return Err(io::Error::from_raw_os_error(libc::EINVAL));
+            }
+
+            for &set_flag in [TEXT_EFFECTIVE, TEXT_PERMITTED, TEXT_INHERITABLE].iter() {
+                if flags & set_flag != 0 {
+                    if op == '-' {
+                        sets[set_flag] &= !caps;
+                    } else {
+                        sets[set_flag] |= caps;
+                    }
+                } else if op == '=' {
+                    sets[set_flag] &= !caps;
+                }
+            }
+
+            // libcap only allows "=" as the first operator in a clause
+            if ops.starts_with('=') {
+                return Err(io::Error::from_raw_os_error(libc::EINVAL));
+            }
+        }
+    }
+
+    Ok(CapState {
+        effective: CapSet::from_bits_retain(sets[TEXT_EFFECTIVE]),
+        permitted: CapSet::from_bits_retain(sets[TEXT_PERMITTED]),
+        inheritable: CapSet::from_bits_retain(sets[TEXT_INHERITABLE]),
+    })
+}
+
+/// Formats the capability state in libcap's text format (for example,
+/// `cap_net_bind_service,cap_net_raw=ep cap_sys_admin+i`).
+///
+/// The output should be identical to that of `cap_to_text()` from libcap.
+#[cfg(any(feature = "strum", test))]
+impl fmt::Display for CapState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&caps_to_text(self))
+    }
+}
+
+/// Parses a capability state from libcap's text format, as accepted by `cap_from_text()`.
+///
+/// Capability names are case-insensitive, and numeric names are accepted (including those of
+/// capabilities above `Cap::CheckpointRestore`, which are kept in the resulting sets).
+///
+/// As an extension, the `all-cap_foo` form (all capabilities except `cap_foo`) is also
+/// accepted. libcap's `cap_from_text()` rejects it (as of libcap 2.66), so text that must also
+/// be read by libcap should use the equivalent `all=eip cap_foo-eip` form instead; the output
+/// of `Display` always uses that form.
+///
+/// `EINVAL` is returned if the text is invalid.
+#[cfg(any(feature = "strum", test))]
+impl FromStr for CapState {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> io::Result<Self> {
+        caps_from_text(s)
+    }
+}
+
+/// Formats the file capabilities in libcap's text format, as `cap_to_text()` would for the
+/// result of `cap_get_file()`.
+///
+/// Note that `rootid` is NOT included in the output.
+#[cfg(any(feature = "strum", test))]
+impl fmt::Display for FileCaps {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        CapState {
+            effective: if self.effective {
+                self.permitted | self.inheritable
+            } else {
+                CapSet::empty()
+            },
+            permitted: self.permitted,
+            inheritable: self.inheritable,
+        }
+        .fmt(f)
+    }
+}
+
+/// Parses file capabilities from libcap's text format.
+///
+/// Since the effective set for file capabilities is a single flag, the effective set in the text
+/// must either be empty or be a subset of the permitted and inheritable sets; otherwise `EINVAL`
+/// is returned. `rootid` is always set to `None`.
+#[cfg(any(feature = "strum", test))]
+impl FromStr for FileCaps {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> io::Result<Self> {
+        let state = caps_from_text(s)?;
+
+        if !(state.effective - (state.permitted | state.inheritable)).is_empty() {
+            return Err(io::Error::from_raw_os_error(libc::EINVAL));
+        }
+
+        Ok(Self {
+            effective: !state.effective.is_empty(),
+            permitted: state.permitted,
+            inheritable: state.inheritable,
+            rootid: None,
+        })
+    }
+}
+
 unsafe fn prctl(option: Int, arg2: Ulong, arg3: Ulong, arg4: Ulong, arg5: Ulong) -> io::Result<Int> {
     error::convert_neg_ret(libc::prctl(option, arg2, arg3, arg4, arg5))
 }
@@ -711,6 +1121,261 @@ pub fn set_keepcaps(keep: bool) -> io::Result<()> {
     Ok(())
 }
 
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum Dumpable {
+    /// The process is not dumpable.
+    Disabled,
+    /// The process is dumpable (the default).
+    User,
+    /// Core dumps are only readable by root. This can only be reached through
+    /// `fs.suid_dumpable`; trying to set it with `set_dumpable()` will fail with
+    /// `EINVAL`.
+    Root,
+}
+
+impl Dumpable {
+    fn from_raw(raw: Int) -> Option<Self> {
+        match raw {
+            constants::SUID_DUMP_DISABLE => Some(Self::Disabled),
+            constants::SUID_DUMP_USER => Some(Self::User),
+            constants::SUID_DUMP_ROOT => Some(Self::Root),
+            _ => None,
+        }
+    }
+
+    fn to_raw(self) -> Int {
+        match self {
+            Self::Disabled => constants::SUID_DUMP_DISABLE,
+            Self::User => constants::SUID_DUMP_USER,
+            Self::Root => constants::SUID_DUMP_ROOT,
+        }
+    }
+}
+
+pub fn get_dumpable() -> io::Result<Dumpable> {
+    let res = unsafe { prctl(libc::PR_GET_DUMPABLE, 0, 0, 0, 0) }?;
+
+    Dumpable::from_raw(res).ok_or_else(|| io::Error::from_raw_os_error(libc::EINVAL))
+}
+
+#[inline]
+pub fn set_dumpable(dumpable: Dumpable) -> io::Result<()> {
+    unsafe { prctl(libc::PR_SET_DUMPABLE, dumpable.to_raw() as Ulong, 0, 0, 0) }?;
+
+    Ok(())
+}
+
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum TimerSlack {
+    /// Reset the "current" timer slack to the thread's "default" timer slack.
+    Default,
+    /// Set the timer slack to the given value. This is rounded down to
+    /// the nearest nanosecond; a value of 0 is treated the same as `Default`.
+    Value(Duration),
+}
+
+/// Get the current thread's timer slack.
+pub fn get_timerslack() -> io::Result<Duration> {
+    // The timer slack may not fit in an Int, so we can't use our prctl() wrapper (which checks
+    // for negative return values).
+    error::set_errno_success();
+    let res = unsafe { libc::prctl(libc::PR_GET_TIMERSLACK, 0, 0, 0, 0) };
+    let res = error::convert_if_errno_ret(res)?;
+
+    Ok(Duration::from_nanos(res as u32 as u64))
+}
+
+pub fn set_timerslack(slack: TimerSlack) -> io::Result<()> {
+    let nanos = match slack {
+        TimerSlack::Default => 0,
+        TimerSlack::Value(dur) => dur.as_nanos().try_into().unwrap_or(Ulong::MAX),
+    };
+
+    unsafe { prctl(libc::PR_SET_TIMERSLACK, nanos, 0, 0, 0) }?;
+
+    Ok(())
+}
+
+#[inline]
+pub fn get_thp_disable() -> io::Result<bool> {
+    let res = unsafe { prctl(libc::PR_GET_THP_DISABLE, 0, 0, 0, 0) }?;
+
+    Ok(res != 0)
+}
+
+#[inline]
+pub fn set_thp_disable(disable: bool) -> io::Result<()> {
+    unsafe { prctl(libc::PR_SET_THP_DISABLE, disable as Ulong, 0, 0, 0) }?;
+
+    Ok(())
+}
+
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum MceKillPolicy {
+    /// Kill the thread as soon as corruption is detected.
+    Early,
+    /// Kill the thread only when it accesses a corrupted page.
+    Late,
+    /// Use the system-wide default (`vm.memory_failure_early_kill`).
+    Default,
+}
+
+pub fn get_mce_kill() -> io::Result<MceKillPolicy> {
+    match unsafe { prctl(libc::PR_MCE_KILL_GET, 0, 0, 0, 0) }? {
+        libc::PR_MCE_KILL_EARLY => Ok(MceKillPolicy::Early),
+        libc::PR_MCE_KILL_LATE => Ok(MceKillPolicy::Late),
+        libc::PR_MCE_KILL_DEFAULT => Ok(MceKillPolicy::Default),
+        _ => Err(io::Error::from_raw_os_error(libc::EINVAL)),
+    }
+}
+
+pub fn set_mce_kill(policy: MceKillPolicy) -> io::Result<()> {
+    let raw_policy = match policy {
+        MceKillPolicy::Early => libc::PR_MCE_KILL_EARLY,
+        MceKillPolicy::Late => libc::PR_MCE_KILL_LATE,
+        MceKillPolicy::Default => libc::PR_MCE_KILL_DEFAULT,
+    };
+
+    unsafe {
+        prctl(
+            libc::PR_MCE_KILL,
+            libc::PR_MCE_KILL_SET as Ulong,
+            raw_policy as Ulong,
+            0,
+            0,
+        )
+    }?;
+
+    Ok(())
+}
+
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum TscMode {
+    /// The `rdtsc` instruction may be executed.
+    Enable,
+    /// Executing `rdtsc` will raise `SIGSEGV`.
+    SigSegv,
+}
+
+/// Get the state of the flag determining whether the `rdtsc` instruction can be executed.
+///
+/// This is only supported on x86; other architectures will fail with `EINVAL`.
+pub fn get_tsc() -> io::Result<TscMode> {
+    let mut mode: Int = 0;
+
+    unsafe { prctl(libc::PR_GET_TSC, &mut mode as *mut Int as Ulong, 0, 0, 0) }?;
+
+    match mode {
+        constants::PR_TSC_ENABLE => Ok(TscMode::Enable),
+        constants::PR_TSC_SIGSEGV => Ok(TscMode::SigSegv),
+        _ => Err(io::Error::from_raw_os_error(libc::EINVAL)),
+    }
+}
+
+pub fn set_tsc(mode: TscMode) -> io::Result<()> {
+    let raw_mode = match mode {
+        TscMode::Enable => constants::PR_TSC_ENABLE,
+        TscMode::SigSegv => constants::PR_TSC_SIGSEGV,
+    };
+
+    unsafe { prctl(libc::PR_SET_TSC, raw_mode as Ulong, 0, 0, 0) }?;
+
+    Ok(())
+}
+
+/// Check whether the current process is marked as an IO flusher (`PR_SET_IO_FLUSHER`).
+///
+/// Note that this requires `CAP_SYS_RESOURCE`.
+#[inline]
+pub fn get_io_flusher() -> io::Result<bool> {
+    let res = unsafe { prctl(constants::PR_GET_IO_FLUSHER, 0, 0, 0, 0) }?;
+
+    Ok(res != 0)
+}
+
+#[inline]
+pub fn set_io_flusher(flusher: bool) -> io::Result<()> {
+    unsafe { prctl(constants::PR_SET_IO_FLUSHER, flusher as Ulong, 0, 0, 0) }?;
+
+    Ok(())
+}
+
+/// Set the name of the anonymous memory region starting at `addr` and extending for `len`
+/// bytes (`PR_SET_VMA_ANON_NAME`).
+///
+/// The name will show up in `/proc/<pid>/maps` as `[anon:<name>]`. If `name` is `None`, any
+/// existing name is cleared.
+///
+/// This requires a kernel built with `CONFIG_ANON_VMA_NAME`.
+pub fn set_vma_anon_name(addr: usize, len: usize, name: Option<&OsStr>) -> io::Result<()> {
+    let c_name = match name {
+        Some(name) => Some(CString::new(name.as_bytes())?),
+        None => None,
+    };
+
+    unsafe {
+        prctl(
+            constants::PR_SET_VMA,
+            constants::PR_SET_VMA_ANON_NAME,
+            addr as Ulong,
+            len as Ulong,
+            c_name.as_ref().map_or(std::ptr::null(), |n| n.as_ptr()) as Ulong,
+        )
+    }?;
+
+    Ok(())
+}
+
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum Ptracer {
+    /// Clear any previously set ptracer.
+    None,
+    /// Allow any process to ptrace this one (subject to the other ptrace checks).
+    Any,
+    /// Allow the given process (and its descendants) to ptrace this one.
+    Pid(PidT),
+}
+
+/// Set the process that is allowed to ptrace the current process when the Yama LSM is in
+/// "restricted ptrace" mode.
+///
+/// This will fail with `EINVAL` if Yama is not enabled.
+pub fn set_ptracer(ptracer: Ptracer) -> io::Result<()> {
+    let arg = match ptracer {
+        Ptracer::None => 0,
+        Ptracer::Any => libc::PR_SET_PTRACER_ANY,
+        Ptracer::Pid(pid) => {
+            if pid <= 0 {
+                return Err(io::Error::from_raw_os_error(libc::EINVAL));
+            }
+            pid as Ulong
+        }
+    };
+
+    unsafe { prctl(libc::PR_SET_PTRACER, arg, 0, 0, 0) }?;
+
+    Ok(())
+}
+
+/// Get the `clear_child_tid` address set by `set_tid_address(2)` or `CLONE_CHILD_CLEARTID`.
+///
+/// This requires a kernel built with `CONFIG_CHECKPOINT_RESTORE`.
+pub fn get_tid_address() -> io::Result<*mut Int> {
+    let mut addr: *mut Int = std::ptr::null_mut();
+
+    unsafe {
+        prctl(
+            libc::PR_GET_TID_ADDRESS,
+            &mut addr as *mut *mut Int as Ulong,
+            0,
+            0,
+            0,
+        )
+    }?;
+
+    Ok(addr)
+}
+
 pub mod ambient {
     use std::io;
 
@@ -902,37 +1567,857 @@ pub mod secbits {
     }
 }
 
-pub fn with_effective_capset<T, F: FnOnce() -> T>(capset: CapSet, f: F) -> io::Result<(T, io::Result<()>)> {
-    let orig_state = CapState::get_current()?;
-
-    let mut new_state = orig_state;
-    new_state.effective = capset;
-    new_state.set_current()?;
-
-    let retval = f();
+pub mod speculation {
+    use std::io;
 
-    let after_res = orig_state.set_current();
+    use bitflags::bitflags;
 
-    Ok((retval, after_res))
-}
+    use crate::constants;
+    use crate::Ulong;
 
-pub fn with_effective_cap<T, F: FnOnce() -> T>(cap: Cap, f: F) -> io::Result<(T, io::Result<()>)> {
-    let orig_state = CapState::get_current()?;
+    #[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+    pub enum Misfeature {
+        /// Speculative Store Bypass
+        StoreBypass,
+        /// Indirect Branch Speculation in user processes
+        IndirectBranch,
+        /// Flushing of the L1D cache on context switch out of the task
+        L1dFlush,
+    }
+
+    impl Misfeature {
+        fn to_raw(self) -> Ulong {
+            match self {
+                Self::StoreBypass => constants::PR_SPEC_STORE_BYPASS,
+                Self::IndirectBranch => constants::PR_SPEC_INDIRECT_BRANCH,
+                Self::L1dFlush => constants::PR_SPEC_L1D_FLUSH,
+            }
+        }
+    }
 
-    let mut new_state = orig_state;
-    new_state.effective.add(cap);
-    new_state.set_current()?;
+    bitflags! {
+        pub struct SpecFlags: Ulong {
+            const PRCTL = constants::PR_SPEC_PRCTL;
+            const ENABLE = constants::PR_SPEC_ENABLE;
+            const DISABLE = constants::PR_SPEC_DISABLE;
+            const FORCE_DISABLE = constants::PR_SPEC_FORCE_DISABLE;
+            const DISABLE_NOEXEC = constants::PR_SPEC_DISABLE_NOEXEC;
+        }
+    }
 
-    let retval = f();
+    impl SpecFlags {
+        /// Whether the CPU is affected by the speculation misfeature.
+        #[inline]
+        pub fn is_affected(self) -> bool {
+            !self.is_empty()
+        }
 
-    let after_res = orig_state.set_current();
+        /// Whether the mitigation can be controlled per-task with `set()`.
+        #[inline]
+        pub fn is_controllable(self) -> bool {
+            self.contains(Self::PRCTL)
+        }
+    }
 
-    Ok((retval, after_res))
-}
+    #[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+    pub enum Control {
+        /// The speculation feature is enabled; mitigation is disabled.
+        Enable,
+        /// The speculation feature is disabled; mitigation is enabled.
+        Disable,
+        /// Same as `Disable`, but cannot be undone.
+        ForceDisable,
+        /// Same as `Disable`, but the state will be cleared on `execve()`.
+        DisableNoexec,
+    }
+
+    impl Control {
+        fn to_raw(self) -> Ulong {
+            match self {
+                Self::Enable => constants::PR_SPEC_ENABLE,
+                Self::Disable => constants::PR_SPEC_DISABLE,
+                Self::ForceDisable => constants::PR_SPEC_FORCE_DISABLE,
+                Self::DisableNoexec => constants::PR_SPEC_DISABLE_NOEXEC,
+            }
+        }
+    }
 
-#[cfg(test)]
-mod tests {
-    use std::os::unix::prelude::*;
+    #[inline]
+    pub fn get(misfeature: Misfeature) -> io::Result<SpecFlags> {
+        let f = unsafe {
+            super::prctl(
+                constants::PR_GET_SPECULATION_CTRL,
+                misfeature.to_raw(),
+                0,
+                0,
+                0,
+            )
+        }?;
+
+        Ok(SpecFlags::from_bits_truncate(f as Ulong))
+    }
+
+    #[inline]
+    pub fn set(misfeature: Misfeature, control: Control) -> io::Result<()> {
+        unsafe {
+            super::prctl(
+                constants::PR_SET_SPECULATION_CTRL,
+                misfeature.to_raw(),
+                control.to_raw(),
+                0,
+                0,
+            )
+        }?;
+
+        Ok(())
+    }
+}
+
+pub fn with_effective_capset<T, F: FnOnce() -> T>(capset: CapSet, f: F) -> io::Result<(T, io::Result<()>)> {
+    let orig_state = CapState::get_current()?;
+
+    let mut new_state = orig_state;
+    new_state.effective = capset;
+    new_state.set_current()?;
+
+    let retval = f();
+
+    let after_res = orig_state.set_current();
+
+    Ok((retval, after_res))
+}
+
+pub fn with_effective_cap<T, F: FnOnce() -> T>(cap: Cap, f: F) -> io::Result<(T, io::Result<()>)> {
+    let orig_state = CapState::get_current()?;
+
+    let mut new_state = orig_state;
+    new_state.effective.add(cap);
+    new_state.set_current()?;
+
+    let retval = f();
+
+    let after_res = orig_state.set_current();
+
+    Ok((retval, after_res))
+}
+
+/// The capability-related state of a process, as used by `simulate_execve()`.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct ExecProcState {
+    pub caps: CapState,
+    pub ambient: CapSet,
+    pub bounding: CapSet,
+    pub secbits: secbits::SecFlags,
+    pub ruid: UidT,
+    pub euid: UidT,
+    pub no_new_privs: bool,
+}
+
+impl ExecProcState {
+    /// Get the state of the current thread.
+    pub fn get_current() -> io::Result<Self> {
+        let (ruid, euid) = crate::process::getreuid();
+
+        Ok(Self {
+            caps: CapState::get_current()?,
+            ambient: ambient::probe().unwrap_or_else(CapSet::empty),
+            bounding: bounding::probe(),
+            secbits: secbits::get()?,
+            ruid,
+            euid,
+            no_new_privs: get_no_new_privs()?,
+        })
+    }
+}
+
+/// Information about a file that is about to be executed, as used by `simulate_execve()`.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct ExecFileInfo {
+    /// The file's capabilities (for example, from `FileCaps::get_for_file()`).
+    ///
+    /// Note that the `rootid` is not checked; if the capabilities do not apply in the current
+    /// user namespace, pass `None`.
+    pub caps: Option<FileCaps>,
+    /// Whether the file is owned by root and has the set-user-ID bit set.
+    pub setuid_root: bool,
+}
+
+impl ExecFileInfo {
+    /// Get the information for the given file. Symbolic links are followed.
+    pub fn get_for_file<P: AsRef<OsStr>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        let metadata = std::fs::metadata(path)?;
+
+        Ok(Self {
+            caps: FileCaps::get_for_file(path, true)?,
+            setuid_root: metadata.uid() == 0 && metadata.mode() & libc::S_ISUID != 0,
+        })
+    }
+}
+
+/// Predict the capabilities that a process would have after calling `execve()` on the given
+/// file, following the rules in capabilities(7).
+///
+/// On success, returns the new capability state and the new ambient set. (The bounding set and
+/// the inheritable set are not changed by `execve()`.) If the kernel would refuse to execute the
+/// file because the file's permitted capabilities could not all be granted and the file's
+/// effective bit is set, `EPERM` is returned.
+///
+/// This does not take into account set-group-ID programs (the effective GID is assumed to be
+/// the same as the real GID), ptrace, or `nosuid` mounts.
+pub fn simulate_execve(
+    proc_state: &ExecProcState,
+    file: &ExecFileInfo,
+) -> io::Result<(CapState, CapSet)> {
+    // The set-user-ID bit is ignored if no_new_privs is set
+    let new_euid = if file.setuid_root && !proc_state.no_new_privs {
+        0
+    } else {
+        proc_state.euid
+    };
+
+    let mut permitted = CapSet::empty();
+    let mut effective = false;
+
+    if let Some(fcaps) = file.caps {
+        // P'(permitted) = (P(inheritable) & F(inheritable)) | (F(permitted) & P(bounding))
+        permitted = (proc_state.caps.inheritable & fcaps.inheritable)
+            | (fcaps.permitted & proc_state.bounding);
+
+        if fcaps.effective {
+            // The kernel refuses to run "capability-dumb" binaries if they wouldn't get all of
+            // their permitted capabilities.
+            if !(fcaps.permitted - permitted).is_empty() {
+                return Err(io::Error::from_raw_os_error(libc::EPERM));
+            }
+
+            effective = true;
+        }
+    }
+
+    // Special handling for root. This is skipped for set-user-ID-root programs with file
+    // capabilities that are run by non-root users.
+    let skip_root = proc_state.secbits.contains(secbits::SecFlags::NOROOT)
+        || (file.caps.is_some() && proc_state.ruid != 0 && new_euid == 0);
+
+    if !skip_root {
+        if proc_state.ruid == 0 || new_euid == 0 {
+            permitted = proc_state.bounding | proc_state.caps.inheritable;
+        }
+
+        // If only the real UID is 0, the effective set is not filled in
+        if new_euid == 0 {
+            effective = true;
+        }
+    }
+
+    let is_setid = new_euid != proc_state.ruid;
+
+    if proc_state.no_new_privs && (is_setid || !(permitted - proc_state.caps.permitted).is_empty())
+    {
+        permitted = permitted & proc_state.caps.permitted;
+    }
+
+    // File capabilities or a change of UID clear the ambient set
+    let ambient = if file.caps.is_some() || is_setid {
+        CapSet::empty()
+    } else {
+        proc_state.ambient
+    };
+
+    permitted = permitted | ambient;
+
+    Ok((
+        CapState {
+            effective: if effective { permitted } else { ambient },
+            permitted,
+            inheritable: proc_state.caps.inheritable,
+        },
+        ambient,
+    ))
+}
+
+/// The capability state of a single thread, as reported in `/proc/<pid>/task/<tid>/status`.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct ThreadCapState {
+    pub tid: PidT,
+    pub caps: CapState,
+    pub ambient: CapSet,
+    pub bounding: CapSet,
+}
+
+impl ThreadCapState {
+    /// Get the capability state of every thread in the given process (or the current process
+    /// if `pid` is 0).
+    ///
+    /// Threads that exit while the list is being built are omitted.
+    pub fn get_for_process(pid: PidT) -> io::Result<Vec<Self>> {
+        let pid_dir = if pid == 0 {
+            "self".to_string()
+        } else {
+            pid.to_string()
+        };
+
+        let mut states = Vec::new();
+
+        for tid in crate::process::list_threads(pid)? {
+            let status_path = format!("/proc/{}/task/{}/status", pid_dir, tid);
+
+            match Self::read_status(tid, &status_path) {
+                Ok(state) => states.push(state),
+                // The thread exited
+                Err(e) if e.raw_os_error() == Some(libc::ENOENT) => (),
+                Err(e) if e.raw_os_error() == Some(libc::ESRCH) => (),
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(states)
+    }
+
+    fn read_status(tid: PidT, path: &str) -> io::Result<Self> {
+        let status = std::fs::read_to_string(path)?;
+
+        let mut sets = [None; 5];
+
+        for line in status.lines() {
+            let index = match line.split(':').next() {
+                Some("CapInh") => 0,
+                Some("CapPrm") => 1,
+                Some("CapEff") => 2,
+                Some("CapBnd") => 3,
+                Some("CapAmb") => 4,
+                _ => continue,
+            };
+
+            let bits = u64::from_str_radix(line[7..].trim(), 16)
+                .map_err(|_| io::Error::from_raw_os_error(libc::EINVAL))?;
+            sets[index] = Some(CapSet::from_bits_safe(bits));
+        }
+
+        let get_set = |i: usize| sets[i].ok_or_else(|| io::Error::from_raw_os_error(libc::EINVAL));
+
+        Ok(Self {
+            tid,
+            caps: CapState {
+                inheritable: get_set(0)?,
+                permitted: get_set(1)?,
+                effective: get_set(2)?,
+            },
+            bounding: get_set(3)?,
+            // Older kernels don't support ambient capabilities
+            ambient: sets[4].unwrap_or_else(CapSet::empty),
+        })
+    }
+}
+
+/// Find the threads in the given process (or the current process if `pid` is 0) whose
+/// effective, permitted, or ambient capability sets differ from those of the main thread.
+///
+/// This can be used to check that capabilities were dropped from every thread, since
+/// `CapState::set_current()` only affects the calling thread.
+pub fn find_inconsistent_threads(pid: PidT) -> io::Result<Vec<ThreadCapState>> {
+    let states = ThreadCapState::get_for_process(pid)?;
+
+    let main_tid = if pid == 0 {
+        crate::process::getpid()
+    } else {
+        pid
+    };
+
+    let main_state = match states.iter().find(|state| state.tid == main_tid) {
+        Some(state) => *state,
+        None => return Err(io::Error::from_raw_os_error(libc::ESRCH)),
+    };
+
+    Ok(states
+        .into_iter()
+        .filter(|state| {
+            state.caps.effective != main_state.caps.effective
+                || state.caps.permitted != main_state.caps.permitted
+                || state.ambient != main_state.ambient
+        })
+        .collect())
+}
+
+static BROADCAST_LOCK: Mutex<()> = Mutex::new(());
+// The capabilities being applied (effective, permitted, inheritable)
+static BROADCAST_CAPS: [AtomicU64; 3] = [AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0)];
+// The first error that occurred in any thread
+static BROADCAST_ERRNO: AtomicI32 = AtomicI32::new(0);
+// The IDs of the threads that have been signaled but have not yet applied the capabilities. Each
+// thread sets its own entry to 0 once it's done. The pointer (and the length stored alongside the
+// entries) is replaced together, and the old list is only freed once `BROADCAST_ACTIVE` shows
+// that no handler can still be reading it.
+static BROADCAST_TIDS: AtomicPtr<Box<[AtomicI32]>> = AtomicPtr::new(std::ptr::null_mut());
+// The number of handlers that are currently running
+static BROADCAST_ACTIVE: AtomicUsize = AtomicUsize::new(0);
+
+extern "C" fn broadcast_caps_handler(_sig: Int) {
+    BROADCAST_ACTIVE.fetch_add(1, Ordering::SeqCst);
+
+    let saved_errno = error::get_errno();
+
+    let state = CapState {
+        effective: CapSet::from_bits_safe(BROADCAST_CAPS[0].load(Ordering::SeqCst)),
+        permitted: CapSet::from_bits_safe(BROADCAST_CAPS[1].load(Ordering::SeqCst)),
+        inheritable: CapSet::from_bits_safe(BROADCAST_CAPS[2].load(Ordering::SeqCst)),
+    };
+
+    if let Err(e) = state.set_current() {
+        let _ = BROADCAST_ERRNO.compare_exchange(
+            0,
+            e.raw_os_error().unwrap_or(libc::EPERM),
+            Ordering::SeqCst,
+            Ordering::SeqCst,
+        );
+    }
+
+    let tid = crate::process::gettid();
+
+    let tids = BROADCAST_TIDS.load(Ordering::SeqCst);
+    if !tids.is_null() {
+        // Only clear our own entry
+        if let Some(entry) = unsafe { &*tids }
+            .iter()
+            .find(|entry| entry.load(Ordering::SeqCst) == tid)
+        {
+            entry.store(0, Ordering::SeqCst);
+        }
+    }
+
+    error::set_errno(saved_errno);
+
+    BROADCAST_ACTIVE.fetch_sub(1, Ordering::SeqCst);
+}
+
+impl CapState {
+    /// Apply this capability state to every thread in the current process.
+    ///
+    /// `CapState::set_current()` only affects the calling thread. This function sets the state
+    /// for the current thread, then sends `sig` to every other thread (using `tgkill()`) and
+    /// applies the state from a signal handler. It waits until every thread has either applied
+    /// the state or exited, then restores the original handler for `sig`.
+    ///
+    /// `sig` should be a signal that the program does not otherwise use (such as one of the
+    /// realtime signals). If any thread has `sig` blocked when it is about to be signaled, this
+    /// fails with `EINVAL`. If a thread blocks `sig` after that check (or otherwise takes too
+    /// long to handle it), this fails with `ETIMEDOUT` once `timeout` has passed; in that case,
+    /// any instances of `sig` that are still pending are discarded. Only one call can be in
+    /// progress at a time.
+    ///
+    /// If any thread fails to apply the state, the first error is returned (though other
+    /// threads may still have applied it).
+    pub fn set_all_threads(&self, sig: Int, timeout: Duration) -> io::Result<()> {
+        let _guard = BROADCAST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+        self.set_current()?;
+
+        BROADCAST_CAPS[0].store(self.effective.bits, Ordering::SeqCst);
+        BROADCAST_CAPS[1].store(self.permitted.bits, Ordering::SeqCst);
+        BROADCAST_CAPS[2].store(self.inheritable.bits, Ordering::SeqCst);
+        BROADCAST_ERRNO.store(0, Ordering::SeqCst);
+
+        let old_action = crate::sigaction::sig_setaction(
+            sig,
+            crate::sigaction::Sigaction {
+                handler: crate::sigaction::SigHandler::Handler(broadcast_caps_handler),
+                mask: crate::signal::Sigset::empty(),
+                flags: crate::sigaction::Flags::RESTART,
+            },
+        )?;
+
+        let res = Self::broadcast_signal(sig, Instant::now().checked_add(timeout));
+
+        if matches!(res, Err(ref e) if e.raw_os_error() == Some(libc::ETIMEDOUT)) {
+            // Ignoring the signal discards any pending instances, so they won't be delivered
+            // later (with the original disposition)
+            crate::sigaction::sig_setaction(sig, crate::sigaction::Sigaction::ignore())?;
+        }
+
+        crate::sigaction::sig_setaction(sig, old_action)?;
+
+        res?;
+
+        match BROADCAST_ERRNO.load(Ordering::SeqCst) {
+            0 => Ok(()),
+            errno => Err(io::Error::from_raw_os_error(errno)),
+        }
+    }
+
+    fn broadcast_signal(sig: Int, deadline: Option<Instant>) -> io::Result<()> {
+        let pid = crate::process::getpid();
+
+        let mut signaled = HashSet::new();
+        signaled.insert(crate::process::gettid());
+
+        // Threads may be created while we're working, and they inherit the capabilities of the
+        // thread that created them. So we keep going until we don't find any new threads.
+        loop {
+            let tids: Box<[AtomicI32]> = crate::process::list_threads(0)?
+                .into_iter()
+                .filter(|tid| signaled.insert(*tid))
+                .map(AtomicI32::new)
+                .collect();
+
+            if tids.is_empty() {
+                return Ok(());
+            }
+
+            let tids = Box::into_raw(Box::new(tids));
+            BROADCAST_TIDS.store(tids, Ordering::SeqCst);
+
+            let res = Self::signal_and_wait(pid, sig, unsafe { &*tids }, deadline);
+
+            // A handler that starts after this will see the null pointer, so once there are no
+            // handlers running it's safe to free the list.
+            BROADCAST_TIDS.store(std::ptr::null_mut(), Ordering::SeqCst);
+            while BROADCAST_ACTIVE.load(Ordering::SeqCst) != 0 {
+                std::thread::yield_now();
+            }
+            drop(unsafe { Box::from_raw(tids) });
+
+            res?;
+        }
+    }
+
+    fn signal_and_wait(
+        pid: PidT,
+        sig: Int,
+        tids: &[AtomicI32],
+        deadline: Option<Instant>,
+    ) -> io::Result<()> {
+        // A thread that has the signal blocked would never handle it, so we'd wait forever
+        for entry in tids {
+            if Self::is_sig_blocked(entry.load(Ordering::SeqCst), sig)? {
+                return Err(io::Error::from_raw_os_error(libc::EINVAL));
+            }
+        }
+
+        for entry in tids {
+            let tid = entry.load(Ordering::SeqCst);
+
+            match crate::tgkill(pid, tid, sig) {
+                Ok(()) => (),
+                // The thread exited
+                Err(e) if e.raw_os_error() == Some(libc::ESRCH) => entry.store(0, Ordering::SeqCst),
+                Err(e) => return Err(e),
+            }
+        }
+
+        loop {
+            let mut pending = false;
+
+            for entry in tids {
+                let tid = entry.load(Ordering::SeqCst);
+
+                if tid != 0 {
+                    if std::path::Path::new(&format!("/proc/self/task/{}", tid)).exists() {
+                        pending = true;
+                    } else {
+                        // The thread exited before handling the signal
+                        let _ = entry.compare_exchange(tid, 0, Ordering::SeqCst, Ordering::SeqCst);
+                    }
+                }
+            }
+
+            if !pending {
+                return Ok(());
+            } else if matches!(deadline, Some(deadline) if Instant::now() >= deadline) {
+                return Err(io::Error::from_raw_os_error(libc::ETIMEDOUT));
+            }
+
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    // Check whether the given thread in the current process has `sig` blocked (as reported by
+    // the `SigBlk` field of its status file). Threads that have exited are ignored.
+    fn is_sig_blocked(tid: PidT, sig: Int) -> io::Result<bool> {
+        let status = match std::fs::read_to_string(format!("/proc/self/task/{}/status", tid)) {
+            Ok(status) => status,
+            Err(e) if e.raw_os_error() == Some(libc::ENOENT) => return Ok(false),
+            Err(e) if e.raw_os_error() == Some(libc::ESRCH) => return Ok(false),
+            Err(e) => return Err(e),
+        };
+
+        for line in status.lines() {
+            if let Some(mask) = line.strip_prefix("SigBlk:") {
+                return crate::signal::Sigset::from_kernel_mask(mask)?.ismember(sig);
+            }
+        }
+
+        Err(io::Error::from_raw_os_error(libc::EINVAL))
+    }
+}
+
+// Everything that `exec_with_caps()` needs, gathered (and checked) in advance so that applying
+// it only makes raw syscalls. This lets `spawn_with_caps()` do the allocations and file
+// inspection in the parent, since the child of a multithreaded process may only call
+// async-signal-safe functions before it execs.
+struct ExecCapsPlan<'a> {
+    uid: UidT,
+    gid: GidT,
+    groups: &'a [GidT],
+    caps: CapSet,
+    set_keepcaps: bool,
+    prog: CString,
+    // Owns the strings that argv_ptrs points to
+    _argv: Vec<CString>,
+    argv_ptrs: Vec<*const crate::Char>,
+}
+
+impl<'a> ExecCapsPlan<'a> {
+    fn new<T: AsRef<OsStr>, U: AsRef<OsStr>>(
+        uid: UidT,
+        gid: GidT,
+        groups: &'a [GidT],
+        caps: CapSet,
+        prog: T,
+        argv: &[U],
+    ) -> io::Result<Self> {
+        let mut secflags = secbits::get()?;
+
+        // Without ambient capabilities, the new program would lose every capability that the
+        // file doesn't grant
+        if secflags.contains(secbits::SecFlags::NO_CAP_AMBIENT_RAISE) && !caps.is_empty() {
+            return Err(io::Error::from_raw_os_error(libc::EPERM));
+        }
+
+        // If the "keep capabilities" flag is locked, we can't change it; that's only a problem if
+        // it's locked off (since the permitted set would be cleared when we switch UIDs).
+        let set_keepcaps = !secflags.contains(secbits::SecFlags::KEEP_CAPS_LOCKED);
+        if set_keepcaps {
+            secflags.remove(secbits::SecFlags::KEEP_CAPS);
+        } else if !secflags.contains(secbits::SecFlags::KEEP_CAPS) {
+            return Err(io::Error::from_raw_os_error(libc::EPERM));
+        }
+
+        // Predict what the new program will get. This takes SECBIT_NOROOT into account, which
+        // only matters if `uid` is 0 or the program is set-user-ID-root (and since we raise
+        // `caps` in the ambient set, it can't take away any of them).
+        let proc_state = ExecProcState {
+            caps: CapState {
+                effective: caps,
+                permitted: caps,
+                inheritable: caps,
+            },
+            ambient: caps,
+            bounding: bounding::probe(),
+            secbits: secflags,
+            ruid: uid,
+            euid: uid,
+            no_new_privs: get_no_new_privs()?,
+        };
+
+        let (new_caps, _) =
+            simulate_execve(&proc_state, &ExecFileInfo::get_for_file(prog.as_ref())?)?;
+
+        if !(caps - new_caps.permitted).is_empty() || !(caps - new_caps.effective).is_empty() {
+            return Err(io::Error::from_raw_os_error(libc::EPERM));
+        }
+
+        let prog = CString::new(prog.as_ref().as_bytes())?;
+        let argv = argv
+            .iter()
+            .map(|arg| CString::new(arg.as_ref().as_bytes()))
+            .collect::<Result<Vec<CString>, _>>()?;
+
+        let mut argv_ptrs: Vec<*const crate::Char> = argv.iter().map(|arg| arg.as_ptr()).collect();
+        argv_ptrs.push(std::ptr::null());
+
+        Ok(Self {
+            uid,
+            gid,
+            groups,
+            caps,
+            set_keepcaps,
+            prog,
+            _argv: argv,
+            argv_ptrs,
+        })
+    }
+
+    // Only makes raw syscalls (and doesn't allocate), so it's safe to call in the child after
+    // fork(). Only returns if an error occurs.
+    fn apply_and_exec(&self) -> io::Error {
+        match self.apply() {
+            Ok(()) => {
+                unsafe {
+                    libc::execv(self.prog.as_ptr(), self.argv_ptrs.as_ptr());
+                }
+                io::Error::last_os_error()
+            }
+            Err(e) => e,
+        }
+    }
+
+    fn apply(&self) -> io::Result<()> {
+        crate::process::setgroups(self.groups)?;
+        crate::process::setresgid(self.gid, self.gid, self.gid)?;
+
+        if self.set_keepcaps {
+            set_keepcaps(true)?;
+        }
+        crate::process::setresuid(self.uid, self.uid, self.uid)?;
+        if self.set_keepcaps {
+            set_keepcaps(false)?;
+        }
+
+        let new_state = CapState {
+            effective: self.caps,
+            permitted: self.caps,
+            inheritable: self.caps,
+        };
+        new_state.set_current()?;
+
+        ambient::clear()?;
+        for cap in self.caps.iter() {
+            ambient::raise(cap)?;
+        }
+
+        // Now verify that everything worked (so the prediction made in new() holds)
+
+        let ambient_raised = self
+            .caps
+            .iter()
+            .all(|cap| ambient::is_set(cap) == Some(true));
+
+        if crate::process::getresuid() != (self.uid, self.uid, self.uid)
+            || crate::process::getresgid() != (self.gid, self.gid, self.gid)
+            || CapState::get_current()? != new_state
+            || !ambient_raised
+        {
+            return Err(io::Error::from_raw_os_error(libc::EPERM));
+        }
+
+        Ok(())
+    }
+}
+
+/// Switch to the given user and group while keeping the given capabilities, then execute the
+/// given program with the given arguments, replacing the current process.
+///
+/// The current process must have `CAP_SETUID`, `CAP_SETGID`, and all of the capabilities in
+/// `caps` in its permitted set. Before changing anything, this checks the securebits (failing
+/// with `EPERM` if `SECBIT_NO_CAP_AMBIENT_RAISE` is set, or if `SECBIT_KEEP_CAPS_LOCKED` is set
+/// without `SECBIT_KEEP_CAPS`), and uses `simulate_execve()` to check that the new program will
+/// have (at least) every capability in `caps` in its permitted and effective sets (failing with
+/// `EPERM` if not; this accounts for `SECBIT_NOROOT`). Then it performs the following steps, in
+/// order:
+///
+/// 1. Set the supplementary groups to `groups`, and the real, effective, and saved GIDs to `gid`.
+/// 2. Set the "keep capabilities" flag, then set the real, effective, and saved UIDs to `uid`
+///    (so the permitted set is not cleared), then clear the flag again. (If the flag is locked,
+///    it is left alone.)
+/// 3. Set the permitted, effective, and inheritable sets to `caps`.
+/// 4. Clear the ambient set, then raise every capability in `caps` in it (so that they are
+///    preserved across `execve()`).
+/// 5. Verify the new UIDs, GIDs, capability sets, and ambient set.
+///
+/// Like `exec::execv()`, this does not perform a `PATH` lookup. If this function returns, it
+/// means an error occurred (and the process may have been left in a partially modified state,
+/// so it should usually exit).
+///
+/// The checks allocate memory and read files, so this function should not be called in the
+/// child of a multithreaded process after `fork()`. Use `spawn_with_caps()` instead, which
+/// performs the checks before forking.
+pub fn exec_with_caps<T: AsRef<OsStr>, U: AsRef<OsStr>>(
+    uid: UidT,
+    gid: GidT,
+    groups: &[GidT],
+    caps: CapSet,
+    prog: T,
+    argv: &[U],
+) -> io::Result<()> {
+    let plan = ExecCapsPlan::new(uid, gid, groups, caps, prog, argv)?;
+
+    Err(plan.apply_and_exec())
+}
+
+/// Fork a child process that calls `exec_with_caps()` with the given arguments.
+///
+/// The checks that `exec_with_caps()` performs up front are done before forking (and any
+/// errors are returned directly), so the child only makes raw syscalls before it execs.
+///
+/// On success, returns the PID of the child process, which has successfully executed the
+/// given program; the caller should `waitpid()` on it. If `exec_with_caps()` fails in the child,
+/// the child exits (with status 127), and it is reaped before the error is returned.
+pub fn spawn_with_caps<T: AsRef<OsStr>, U: AsRef<OsStr>>(
+    uid: UidT,
+    gid: GidT,
+    groups: &[GidT],
+    caps: CapSet,
+    prog: T,
+    argv: &[U],
+) -> io::Result<PidT> {
+    use std::io::Read;
+    use std::os::unix::io::AsRawFd;
+
+    let plan = ExecCapsPlan::new(uid, gid, groups, caps, prog, argv)?;
+
+    // Used to report errors from the child. It's close-on-exec, so if the exec succeeds, the
+    // parent will see EOF.
+    let (mut r, w) = crate::pipe()?;
+
+    let pid = crate::process::fork()?;
+
+    if pid == 0 {
+        let errno = plan.apply_and_exec().raw_os_error().unwrap_or(libc::EINVAL);
+
+        unsafe {
+            libc::write(
+                w.as_raw_fd(),
+                &errno as *const Int as *const libc::c_void,
+                std::mem::size_of::<Int>(),
+            );
+            libc::_exit(127);
+        }
+    }
+
+    drop(w);
+
+    let mut buf = [0; std::mem::size_of::<Int>()];
+    let mut nread = 0;
+
+    let err = loop {
+        match r.read(&mut buf[nread..]) {
+            // The exec succeeded
+            Ok(0) if nread == 0 => return Ok(pid),
+            // The child exited after a partial write
+            Ok(0) => break io::Error::from_raw_os_error(libc::EIO),
+            Ok(n) => {
+                nread += n;
+                if nread == buf.len() {
+                    break io::Error::from_raw_os_error(Int::from_ne_bytes(buf));
+                }
+            }
+            Err(e) if error::is_eintr(&e) => (),
+            Err(e) => {
+                // We can't tell whether the exec succeeded, so make sure the child exits
+                let _ = crate::kill(crate::KillSpec::Pid(pid), libc::SIGKILL);
+                break e;
+            }
+        }
+    };
+
+    // The child has exited (or is about to), so reap it
+    loop {
+        match crate::wait::waitpid(
+            crate::wait::WaitpidSpec::Pid(pid),
+            crate::wait::WaitpidOptions::empty(),
+        ) {
+            Err(e) if error::is_eintr(&e) => (),
+            _ => break,
+        }
+    }
+
+    Err(err)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::prelude::*;
 
     use super::*;
     use crate::constants;
@@ -1339,6 +2824,118 @@ mod tests {
         set_keepcaps(old_keepcaps).unwrap();
     }
 
+    #[test]
+    fn test_dumpable() {
+        // The "dumpable" flag is process-wide (and other tests that fork depend on it), so change
+        // it in a child process.
+        match crate::process::fork().unwrap() {
+            0 => {
+                let res = std::panic::catch_unwind(check_dumpable);
+                unsafe { libc::_exit(if res.is_ok() { 0 } else { 1 }) }
+            }
+            pid => {
+                let (_, status) = crate::wait::waitpid(
+                    crate::wait::WaitpidSpec::Pid(pid),
+                    crate::wait::WaitpidOptions::empty(),
+                )
+                .unwrap()
+                .unwrap();
+
+                assert_eq!(status, crate::wait::ProcStatus::Exited(0));
+            }
+        }
+    }
+
+    fn check_dumpable() {
+        set_dumpable(Dumpable::Disabled).unwrap();
+        assert_eq!(get_dumpable().unwrap(), Dumpable::Disabled);
+
+        set_dumpable(Dumpable::User).unwrap();
+        assert_eq!(get_dumpable().unwrap(), Dumpable::User);
+
+        assert_eq!(
+            set_dumpable(Dumpable::Root).unwrap_err().raw_os_error(),
+            Some(libc::EINVAL)
+        );
+    }
+
+    #[test]
+    fn test_timerslack() {
+        let old_slack = get_timerslack().unwrap();
+
+        set_timerslack(TimerSlack::Value(Duration::from_micros(100))).unwrap();
+        assert_eq!(get_timerslack().unwrap(), Duration::from_micros(100));
+
+        set_timerslack(TimerSlack::Value(old_slack)).unwrap();
+        assert_eq!(get_timerslack().unwrap(), old_slack);
+    }
+
+    #[test]
+    fn test_thp_disable() {
+        let old_disable = get_thp_disable().unwrap();
+
+        set_thp_disable(true).unwrap();
+        assert!(get_thp_disable().unwrap());
+
+        set_thp_disable(old_disable).unwrap();
+        assert_eq!(get_thp_disable().unwrap(), old_disable);
+    }
+
+    #[test]
+    fn test_mce_kill() {
+        let old_policy = get_mce_kill().unwrap();
+
+        set_mce_kill(MceKillPolicy::Early).unwrap();
+        assert_eq!(get_mce_kill().unwrap(), MceKillPolicy::Early);
+
+        set_mce_kill(old_policy).unwrap();
+        assert_eq!(get_mce_kill().unwrap(), old_policy);
+    }
+
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    #[test]
+    fn test_tsc() {
+        assert_eq!(get_tsc().unwrap(), TscMode::Enable);
+        set_tsc(TscMode::Enable).unwrap();
+    }
+
+    #[test]
+    fn test_speculation() {
+        for &misfeature in [
+            speculation::Misfeature::StoreBypass,
+            speculation::Misfeature::IndirectBranch,
+        ]
+        .iter()
+        {
+            match speculation::get(misfeature) {
+                Ok(flags) => {
+                    if !flags.is_affected() {
+                        assert!(!flags.is_controllable());
+                    }
+                }
+                Err(e) => assert_eq!(e.raw_os_error(), Some(libc::EINVAL)),
+            }
+        }
+    }
+
+    #[test]
+    fn test_ptracer() {
+        assert_eq!(
+            set_ptracer(Ptracer::Pid(0)).unwrap_err().raw_os_error(),
+            Some(libc::EINVAL)
+        );
+    }
+
+    #[test]
+    fn test_vma_anon_name() {
+        assert_eq!(
+            set_vma_anon_name(0, 0, Some(OsStr::new("a\0b")))
+                .unwrap_err()
+                .kind(),
+            io::ErrorKind::InvalidInput,
+        );
+    }
+
     #[test]
     fn test_ambient() {
         ambient::probe().unwrap();
@@ -1365,6 +2962,320 @@ mod tests {
         assert!(empty_caps.rootid.is_none());
     }
 
+    #[test]
+    fn test_capstate_text() {
+        // The expected output was generated with libcap's cap_from_text() and cap_to_text()
+        for &(text, expected) in [
+            ("", "="),
+            ("=", "="),
+            ("=ep", "=ep"),
+            ("all=", "="),
+            ("cap_chown=", "="),
+            ("CAP_CHOWN+ep", "cap_chown=ep"),
+            ("cap_chown=ep-e", "cap_chown=p"),
+            ("cap_chown,all=i", "=i"),
+            ("40=ep", "cap_checkpoint_restore=ep"),
+            ("all=ep cap_sys_module-ep", "=ep cap_sys_module-ep"),
+            (
+                "cap_net_bind_service,cap_net_raw=ep cap_sys_admin+i",
+                "cap_sys_admin=i cap_net_bind_service,cap_net_raw+ep",
+            ),
+            (
+                "cap_chown=e cap_kill=i cap_setuid=p cap_fowner=eip cap_setgid=ei",
+                "cap_fowner=eip cap_setgid+ei cap_kill+i cap_setuid+p cap_chown+e",
+            ),
+            (
+                "=i cap_chown+e cap_kill+p cap_setuid-i",
+                "=i cap_kill+p cap_chown+e cap_setuid-i",
+            ),
+            (
+                "=eip cap_chown-e cap_kill-i cap_setuid-p cap_setgid-eip",
+                "=eip cap_chown-e cap_setuid-p cap_kill-i cap_setgid-eip",
+            ),
+            ("cap_kill,cap_chown=ep", "cap_chown,cap_kill=ep"),
+            // Unknown capabilities are listed by number
+            ("41=ep", "= 41+ep"),
+            ("41=e 42=e", "= 41,42+e"),
+            ("41=eip 42=e 43=p", "= 41+eip 43+p 42+e"),
+            ("cap_chown=e 63=p", "cap_chown=e 63+p"),
+            ("=ep 41+i", "=ep 41+i"),
+            // "=" and "all" only apply to known capabilities
+            ("=ep 63-ep", "=ep"),
+        ]
+        .iter()
+        {
+            let state = CapState::from_str(text).unwrap();
+            assert_eq!(state.to_string(), expected);
+            assert_eq!(CapState::from_str(expected).unwrap(), state);
+        }
+
+        let state =
+            CapState::from_str("cap_net_bind_service,cap_net_raw=ep cap_sys_admin+i").unwrap();
+        assert_eq!(
+            state.effective,
+            CapSet::from_iter(vec![Cap::NetBindService, Cap::NetRaw])
+        );
+        assert_eq!(
+            state.permitted,
+            CapSet::from_iter(vec![Cap::NetBindService, Cap::NetRaw])
+        );
+        assert_eq!(state.inheritable, CapSet::from_iter(vec![Cap::SysAdmin]));
+
+        // libcap 2.66's cap_from_text() rejects "all-cap_foo" (with EINVAL), but we accept it as
+        // shorthand for "all=eip cap_sys_module-eip" (which libcap accepts, and formats as
+        // shown here)
+        assert_eq!(
+            CapState::from_str("all-cap_sys_module=eip")
+                .unwrap()
+                .to_string(),
+            "=eip cap_sys_module-eip"
+        );
+        assert_eq!(
+            CapState::from_str("all-cap_sys_module=eip").unwrap(),
+            CapState::from_str("all=eip cap_sys_module-eip").unwrap()
+        );
+
+        for &text in [
+            "+ep",
+            "-ep",
+            "cap_chown+",
+            "cap_chown-",
+            "cap_chown+e=p",
+            "cap_chown",
+            "cap_chown=x",
+            "cap_bad=ep",
+            "cap_chown,=ep",
+            "cap_chown-cap_kill=ep",
+            "64=ep",
+        ]
+        .iter()
+        {
+            assert_eq!(
+                CapState::from_str(text).unwrap_err().raw_os_error(),
+                Some(libc::EINVAL)
+            );
+        }
+    }
+
+    #[test]
+    fn test_filecaps_text() {
+        let mut caps = FileCaps::empty();
+        assert_eq!(caps.to_string(), "=");
+        assert_eq!(FileCaps::from_str("=").unwrap(), caps);
+
+        caps.permitted.add(Cap::NetBindService);
+        assert_eq!(caps.to_string(), "cap_net_bind_service=p");
+        assert_eq!(FileCaps::from_str("cap_net_bind_service=p").unwrap(), caps);
+
+        caps.effective = true;
+        assert_eq!(caps.to_string(), "cap_net_bind_service=ep");
+        assert_eq!(FileCaps::from_str("cap_net_bind_service+ep").unwrap(), caps);
+
+        assert_eq!(
+            FileCaps::from_str("cap_net_bind_service=p cap_kill=e")
+                .unwrap_err()
+                .raw_os_error(),
+            Some(libc::EINVAL)
+        );
+    }
+
+    #[test]
+    fn test_filecaps_pack_unpack() {
+        let mut caps = FileCaps::empty();
+        let attrs = caps.pack_attrs();
+        assert_eq!(attrs.len(), constants::XATTR_CAPS_SZ_2);
+        assert_eq!(&attrs[..4], &constants::VFS_CAP_REVISION_2.to_le_bytes());
+        assert_eq!(FileCaps::unpack_attrs(&attrs).unwrap(), caps);
+
+        caps.effective = true;
+        caps.permitted.add(Cap::NetBindService);
+        caps.permitted.add(Cap::CheckpointRestore);
+        caps.inheritable.add(Cap::Kill);
+        let attrs = caps.pack_attrs();
+        assert_eq!(attrs.len(), constants::XATTR_CAPS_SZ_2);
+        assert_eq!(
+            &attrs[..4],
+            &(constants::VFS_CAP_REVISION_2 | constants::VFS_CAP_FLAGS_EFFECTIVE).to_le_bytes()
+        );
+        assert_eq!(FileCaps::unpack_attrs(&attrs).unwrap(), caps);
+
+        caps.rootid = Some(1000);
+        let attrs = caps.pack_attrs();
+        assert_eq!(attrs.len(), constants::XATTR_CAPS_SZ_3);
+        assert_eq!(&attrs[20..], &1000u32.to_le_bytes());
+        assert_eq!(FileCaps::unpack_attrs(&attrs).unwrap(), caps);
+    }
+
+    #[test]
+    fn test_filecaps_set_remove() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("file");
+        let f = std::fs::File::create(&path).unwrap();
+
+        let mut caps = FileCaps::empty();
+        caps.effective = true;
+        caps.permitted.add(Cap::NetBindService);
+
+        match caps.set_for_file(&path, true) {
+            // Either we don't have CAP_SETFCAP or the filesystem doesn't support it
+            Err(e) if e.raw_os_error() == Some(libc::EPERM) => return,
+            Err(e) if e.raw_os_error() == Some(libc::ENOTSUP) => return,
+            res => res.unwrap(),
+        }
+
+        assert_eq!(FileCaps::get_for_file(&path, true).unwrap(), Some(caps));
+        assert_eq!(FileCaps::get_for_fd(f.as_raw_fd()).unwrap(), Some(caps));
+
+        caps.inheritable.add(Cap::Kill);
+        caps.set_for_fd(f.as_raw_fd()).unwrap();
+        assert_eq!(FileCaps::get_for_fd(f.as_raw_fd()).unwrap(), Some(caps));
+
+        FileCaps::remove_for_fd(f.as_raw_fd()).unwrap();
+        assert_eq!(FileCaps::get_for_file(&path, false).unwrap(), None);
+        assert_eq!(
+            FileCaps::remove_for_file(&path, false)
+                .unwrap_err()
+                .raw_os_error(),
+            Some(libc::ENODATA)
+        );
+    }
+
+    #[test]
+    fn test_simulate_execve() {
+        let nbs = CapSet::from_iter(vec![Cap::NetBindService]);
+
+        let user = ExecProcState {
+            caps: CapState {
+                effective: CapSet::empty(),
+                permitted: CapSet::empty(),
+                inheritable: CapSet::empty(),
+            },
+            ambient: CapSet::empty(),
+            bounding: CapSet::full(),
+            secbits: secbits::SecFlags::empty(),
+            ruid: 1000,
+            euid: 1000,
+            no_new_privs: false,
+        };
+
+        let plain = ExecFileInfo {
+            caps: None,
+            setuid_root: false,
+        };
+
+        let mut nbs_file = plain;
+        nbs_file.caps = Some(FileCaps {
+            effective: true,
+            permitted: nbs,
+            inheritable: CapSet::empty(),
+            rootid: None,
+        });
+
+        let mut setuid_root = plain;
+        setuid_root.setuid_root = true;
+
+        // Unprivileged user running a normal file
+        assert_eq!(
+            simulate_execve(&user, &plain).unwrap(),
+            (user.caps, CapSet::empty())
+        );
+
+        // Ambient capabilities are preserved for normal files...
+        let mut amb_user = user;
+        amb_user.caps.permitted = nbs;
+        amb_user.caps.inheritable = nbs;
+        amb_user.ambient = nbs;
+        assert_eq!(
+            simulate_execve(&amb_user, &plain).unwrap(),
+            (
+                CapState {
+                    effective: nbs,
+                    permitted: nbs,
+                    inheritable: nbs,
+                },
+                nbs
+            ),
+        );
+
+        // ... but not for set-user-ID programs
+        let (state, ambient) = simulate_execve(&amb_user, &setuid_root).unwrap();
+        assert!(ambient.is_empty());
+        assert!(state.permitted.is_full());
+        assert!(state.effective.is_full());
+
+        // File capabilities
+        let (state, ambient) = simulate_execve(&user, &nbs_file).unwrap();
+        assert_eq!(state.permitted, nbs);
+        assert_eq!(state.effective, nbs);
+        assert!(ambient.is_empty());
+
+        // Without the effective bit
+        let mut nbs_file_noeff = nbs_file;
+        nbs_file_noeff.caps.as_mut().unwrap().effective = false;
+        let (state, _) = simulate_execve(&user, &nbs_file_noeff).unwrap();
+        assert_eq!(state.permitted, nbs);
+        assert!(state.effective.is_empty());
+
+        // Dropped from the bounding set
+        let mut bounded_user = user;
+        bounded_user.bounding.drop(Cap::NetBindService);
+        assert_eq!(
+            simulate_execve(&bounded_user, &nbs_file)
+                .unwrap_err()
+                .raw_os_error(),
+            Some(libc::EPERM)
+        );
+        let (state, _) = simulate_execve(&bounded_user, &nbs_file_noeff).unwrap();
+        assert!(state.permitted.is_empty());
+
+        // no_new_privs prevents gaining capabilities
+        let mut nnp_user = user;
+        nnp_user.no_new_privs = true;
+        assert_eq!(
+            simulate_execve(&nnp_user, &setuid_root).unwrap(),
+            (user.caps, CapSet::empty())
+        );
+        assert_eq!(
+            simulate_execve(&nnp_user, &nbs_file).unwrap(),
+            (user.caps, CapSet::empty())
+        );
+
+        // Root gets all the capabilities in the bounding set...
+        let mut root = user;
+        root.ruid = 0;
+        root.euid = 0;
+        root.bounding.drop(Cap::SysModule);
+        let (state, _) = simulate_execve(&root, &plain).unwrap();
+        assert_eq!(state.permitted, root.bounding);
+        assert_eq!(state.effective, root.bounding);
+
+        // ... unless SECBIT_NOROOT is set
+        let mut noroot = root;
+        noroot.secbits = secbits::SecFlags::NOROOT;
+        assert_eq!(
+            simulate_execve(&noroot, &plain).unwrap(),
+            (user.caps, CapSet::empty())
+        );
+
+        // If only the real UID is 0, the effective set is empty
+        let mut real_root = root;
+        real_root.euid = 1000;
+        let (state, _) = simulate_execve(&real_root, &plain).unwrap();
+        assert_eq!(state.permitted, root.bounding);
+        assert!(state.effective.is_empty());
+
+        // Set-user-ID-root programs with file capabilities only get the file capabilities
+        let mut setuid_root_nbs = nbs_file;
+        setuid_root_nbs.setuid_root = true;
+        let (state, _) = simulate_execve(&user, &setuid_root_nbs).unwrap();
+        assert_eq!(state.permitted, nbs);
+        assert_eq!(state.effective, nbs);
+
+        let cur = ExecProcState::get_current().unwrap();
+        let (state, _) = simulate_execve(&cur, &plain).unwrap();
+        assert_eq!(state.inheritable, cur.caps.inheritable);
+    }
+
     #[test]
     fn test_filecaps_get() {
         let current_exe = std::env::current_exe().unwrap();
@@ -1375,4 +3286,65 @@ mod tests {
         let f = std::fs::File::open(&current_exe).unwrap();
         FileCaps::get_for_fd(f.as_raw_fd()).unwrap();
     }
+
+    #[test]
+    fn test_thread_caps() {
+        let states = ThreadCapState::get_for_process(0).unwrap();
+        let state = states
+            .iter()
+            .find(|state| state.tid == crate::process::gettid())
+            .unwrap();
+        assert_eq!(state.caps, CapState::get_current().unwrap());
+        assert_eq!(state.bounding, bounding::probe());
+
+        assert_eq!(
+            ThreadCapState::get_for_process(-1)
+                .unwrap_err()
+                .raw_os_error(),
+            Some(libc::ESRCH)
+        );
+    }
+
+    #[test]
+    fn test_spawn_with_caps() {
+        let state = CapState::get_current().unwrap();
+        if !state.effective.has(Cap::Setuid)
+            || !state.effective.has(Cap::Setgid)
+            || !state.permitted.has(Cap::NetBindService)
+        {
+            return;
+        }
+
+        let caps = CapSet::from_iter(vec![Cap::NetBindService]);
+
+        let pid = spawn_with_caps(
+            65534,
+            65534,
+            &[],
+            caps,
+            "/bin/sh",
+            &[
+                "sh",
+                "-c",
+                "grep -q '^CapAmb:[[:space:]]*0000000000000400$' /proc/self/status \
+                    && grep -q '^CapEff:[[:space:]]*0000000000000400$' /proc/self/status \
+                    && [ \"$(id -u)\" = 65534 ]",
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(
+            crate::wait::waitpid(crate::wait::WaitpidSpec::Pid(pid), Default::default()).unwrap(),
+            Some((pid, crate::wait::ProcStatus::Exited(0)))
+        );
+
+        // Errors are reported before forking...
+        let err = spawn_with_caps(65534, 65534, &[], caps, "/NOEXIST", &["/NOEXIST"]).unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::ENOENT));
+
+        // ... or from the child
+        let err =
+            spawn_with_caps(65534, 65534, &[], caps, "/etc/passwd", &["/etc/passwd"]).unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::EACCES));
+    }
 }
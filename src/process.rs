@@ -617,6 +617,41 @@ pub fn requires_secure_execution() -> bool {
     rgid != egid
 }
 
+/// List the IDs of all the threads in the given process (or the current process if `pid` is 0).
+///
+/// This reads the contents of `/proc/<pid>/task`. Note that threads may be created or exit
+/// at any time, so the list may be out of date by the time it is returned.
+#[cfg(target_os = "linux")]
+pub fn list_threads(pid: PidT) -> io::Result<Vec<PidT>> {
+    let task_path = Path::new("/proc/")
+        .join(if pid == 0 {
+            "self".to_string()
+        } else {
+            pid.to_string()
+        })
+        .join("task");
+
+    let entries = match std::fs::read_dir(task_path) {
+        Ok(entries) => entries,
+        Err(e) if crate::error::is_raw(&e, libc::ENOENT) => {
+            return Err(io::Error::from_raw_os_error(libc::ESRCH))
+        }
+        Err(e) => return Err(e),
+    };
+
+    let mut tids = Vec::new();
+
+    for entry in entries {
+        if let Some(tid) = entry?.file_name().to_str().and_then(|s| s.parse().ok()) {
+            tids.push(tid);
+        }
+    }
+
+    tids.sort_unstable();
+
+    Ok(tids)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -633,6 +668,20 @@ mod tests {
         gettid();
     }
 
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_list_threads() {
+        let tids = list_threads(0).unwrap();
+        assert!(tids.contains(&getpid()));
+        assert!(tids.contains(&gettid()));
+        assert!(list_threads(getpid()).unwrap().contains(&getpid()));
+
+        assert_eq!(
+            list_threads(-1).unwrap_err().raw_os_error(),
+            Some(libc::ESRCH)
+        );
+    }
+
     #[test]
     fn test_getuidgid() {
         assert_eq!((getuid(), geteuid()), getreuid());
@@ -1,4 +1,8 @@
+use std::collections::BTreeMap;
+use std::fmt;
 use std::io;
+use std::os::unix::prelude::*;
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicUsize, Ordering};
 
 use bitflags::bitflags;
 
@@ -89,8 +93,13 @@ impl From<Sigaction> for libc::sigaction {
 
 impl From<libc::sigaction> for Sigaction {
     fn from(act: libc::sigaction) -> Sigaction {
+        #[cfg_attr(not(target_os = "linux"), allow(unused_mut))]
+        let mut mask = Sigset::from(act.sa_mask);
+        #[cfg(target_os = "linux")]
+        mask.clear_unused_bits();
+
         Sigaction {
-            mask: Sigset::from(act.sa_mask),
+            mask,
             flags: Flags::from_bits_truncate(act.sa_flags),
             handler: match act.sa_sigaction {
                 libc::SIG_DFL => SigHandler::Default,
@@ -130,6 +139,398 @@ pub fn sig_setaction(sig: Int, act: Sigaction) -> io::Result<Sigaction> {
 
 pub extern "C" fn empty_sighandler(_sig: Int) {}
 
+/// A snapshot of the dispositions of every catchable signal (including realtime signals).
+///
+/// This can be used to save the signal handlers before changing them and restore them
+/// afterward, or to reset all signal handlers before (for example) running code in a child
+/// process.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SignalDispositions {
+    actions: Vec<(Int, Sigaction)>,
+}
+
+impl SignalDispositions {
+    /// Capture the current dispositions of all catchable signals.
+    pub fn capture() -> io::Result<Self> {
+        let actions = Sigset::full()
+            .iter()
+            .filter(|&sig| crate::signal::can_catch(sig))
+            .map(|sig| Ok((sig, sig_getaction(sig)?)))
+            .collect::<io::Result<Vec<(Int, Sigaction)>>>()?;
+
+        Ok(Self { actions })
+    }
+
+    /// Get the disposition of the given signal in this snapshot.
+    pub fn get(&self, sig: Int) -> Option<&Sigaction> {
+        self.actions
+            .iter()
+            .find(|(s, _)| *s == sig)
+            .map(|(_, act)| act)
+    }
+
+    /// Iterate over the signals and their dispositions in this snapshot, in order of signal
+    /// number.
+    pub fn iter(&self) -> impl Iterator<Item = (Int, &Sigaction)> {
+        self.actions.iter().map(|(sig, act)| (*sig, act))
+    }
+
+    /// Restore the dispositions of all the signals in this snapshot.
+    pub fn restore(&self) -> io::Result<()> {
+        for &(sig, act) in self.actions.iter() {
+            sig_setaction(sig, act)?;
+        }
+
+        Ok(())
+    }
+
+    /// Reset every signal that has a handler in this snapshot to the default disposition
+    /// (`Sigaction::default()`).
+    ///
+    /// Signals that are ignored are left ignored. This matches what happens to signal
+    /// dispositions when a process calls `exec()`.
+    pub fn reset_handlers(&self) -> io::Result<()> {
+        for &(sig, act) in self.actions.iter() {
+            match act.handler {
+                SigHandler::Handler(_) | SigHandler::ActionHandler(_) => {
+                    sig_setaction(sig, Sigaction::default())?;
+                }
+                SigHandler::Default | SigHandler::Ignore => (),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Compare this snapshot with another one.
+    ///
+    /// Returns a list of `(sig, self_action, other_action)` for every signal whose disposition
+    /// differs between the two snapshots (in order of signal number).
+    pub fn diff(&self, other: &Self) -> Vec<(Int, Sigaction, Sigaction)> {
+        self.actions
+            .iter()
+            .filter_map(|&(sig, act)| match other.get(sig) {
+                Some(&other_act) if other_act != act => Some((sig, act, other_act)),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+// The number of slots in the signal registry; signals must be less than this.
+const REGISTRY_MAX_SIG: usize = 129;
+
+struct RegistrySigState {
+    pending: AtomicBool,
+    // The previous handler (0 if it should not be called), and whether it takes a siginfo_t
+    chain_handler: AtomicUsize,
+    chain_siginfo: AtomicBool,
+}
+
+#[allow(clippy::declare_interior_mutable_const)]
+const REGISTRY_SIG_STATE_INIT: RegistrySigState = RegistrySigState {
+    pending: AtomicBool::new(false),
+    chain_handler: AtomicUsize::new(0),
+    chain_siginfo: AtomicBool::new(false),
+};
+
+static REGISTRY_SIG_STATES: [RegistrySigState; REGISTRY_MAX_SIG] =
+    [REGISTRY_SIG_STATE_INIT; REGISTRY_MAX_SIG];
+static REGISTRY_ACTIVE: AtomicBool = AtomicBool::new(false);
+static REGISTRY_WAKE_FD: AtomicI32 = AtomicI32::new(-1);
+// The number of handlers that may be using the wake file descriptor; it is only closed once
+// this drops to 0
+static REGISTRY_HANDLERS_ACTIVE: AtomicUsize = AtomicUsize::new(0);
+
+extern "C" fn registry_handler(sig: Int, info: *mut libc::siginfo_t, context: *mut libc::c_void) {
+    let saved_errno = crate::error::get_errno();
+
+    let state = &REGISTRY_SIG_STATES[sig as usize];
+    state.pending.store(true, Ordering::SeqCst);
+
+    REGISTRY_HANDLERS_ACTIVE.fetch_add(1, Ordering::SeqCst);
+    let wake_fd = REGISTRY_WAKE_FD.load(Ordering::SeqCst);
+    if wake_fd >= 0 {
+        // If this fails with EAGAIN, the registry is already going to wake up
+        #[cfg(target_os = "linux")]
+        let buf = 1u64.to_ne_bytes();
+        #[cfg(not(target_os = "linux"))]
+        let buf = [0u8];
+
+        unsafe {
+            libc::write(wake_fd, buf.as_ptr() as *const libc::c_void, buf.len());
+        }
+    }
+    REGISTRY_HANDLERS_ACTIVE.fetch_sub(1, Ordering::SeqCst);
+
+    let chain_handler = state.chain_handler.load(Ordering::SeqCst);
+    if chain_handler != 0 {
+        if state.chain_siginfo.load(Ordering::SeqCst) {
+            let handler: extern "C" fn(Int, *mut libc::siginfo_t, *mut libc::c_void) =
+                unsafe { std::mem::transmute(chain_handler) };
+            handler(sig, info, context);
+        } else {
+            let handler: extern "C" fn(Int) = unsafe { std::mem::transmute(chain_handler) };
+            handler(sig);
+        }
+    }
+
+    crate::error::set_errno(saved_errno);
+}
+
+type RegistryCallback = Box<dyn FnMut(Int) + Send>;
+
+/// A registry of signal handlers that run outside of signal context.
+///
+/// When a signal is registered, an internal signal handler is installed for it. That handler
+/// just records that the signal was received and wakes up a file descriptor (an eventfd on
+/// Linux, or a self-pipe on other platforms). The file descriptor (available with
+/// `as_raw_fd()`) can be monitored for readability (for example, with a
+/// `pollers::Poller`; see `register_with()`), and when it becomes readable, `dispatch()` should
+/// be called to run the callbacks for any signals that were received.
+///
+/// Since the callbacks run outside of signal context, they can be ordinary Rust closures and
+/// do not need to be async-signal-safe. Note that if a signal is received multiple times before
+/// `dispatch()` is called, the callbacks will only be run once.
+///
+/// Signal dispositions are process-wide, so only one `SignalRegistry` can exist at a time;
+/// attempting to create another fails with `EBUSY`. When the registry is dropped, the previous
+/// actions for all the registered signals are restored.
+pub struct SignalRegistry {
+    read_fd: Int,
+    write_fd: Int,
+    callbacks: BTreeMap<Int, Vec<RegistryCallback>>,
+    old_actions: BTreeMap<Int, Sigaction>,
+}
+
+impl SignalRegistry {
+    pub fn new() -> io::Result<Self> {
+        if REGISTRY_ACTIVE
+            .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+            .is_err()
+        {
+            return Err(io::Error::from_raw_os_error(libc::EBUSY));
+        }
+
+        match Self::open_wake_fds() {
+            Ok((read_fd, write_fd)) => {
+                REGISTRY_WAKE_FD.store(write_fd, Ordering::SeqCst);
+
+                Ok(Self {
+                    read_fd,
+                    write_fd,
+                    callbacks: BTreeMap::new(),
+                    old_actions: BTreeMap::new(),
+                })
+            }
+            Err(e) => {
+                REGISTRY_ACTIVE.store(false, Ordering::SeqCst);
+                Err(e)
+            }
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    fn open_wake_fds() -> io::Result<(Int, Int)> {
+        let fd = crate::error::convert_neg_ret(unsafe {
+            libc::eventfd(0, libc::EFD_CLOEXEC | libc::EFD_NONBLOCK)
+        })?;
+
+        Ok((fd, fd))
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn open_wake_fds() -> io::Result<(Int, Int)> {
+        let (r, w) = crate::pipe()?;
+
+        crate::fcntl::set_nonblocking(r.as_raw_fd(), true)?;
+        crate::fcntl::set_nonblocking(w.as_raw_fd(), true)?;
+
+        Ok((r.into_raw_fd(), w.into_raw_fd()))
+    }
+
+    /// Register a callback to be run (by `dispatch()`) when the given signal is received.
+    ///
+    /// Multiple callbacks can be registered for the same signal; they are run in the order they
+    /// were registered.
+    ///
+    /// When the first callback is registered for a signal, the internal handler is installed.
+    /// If `chain` is true and a signal handler function was previously installed for the
+    /// signal, the internal handler will also call that function (in signal context). The
+    /// `chain` argument is ignored for subsequent registrations.
+    ///
+    /// Fails with `EINVAL` if the signal is invalid or cannot be caught.
+    pub fn register<F: FnMut(Int) + Send + 'static>(
+        &mut self,
+        sig: Int,
+        chain: bool,
+        callback: F,
+    ) -> io::Result<()> {
+        if sig <= 0 || sig as usize >= REGISTRY_MAX_SIG || !crate::signal::can_catch(sig) {
+            return Err(io::Error::from_raw_os_error(libc::EINVAL));
+        }
+
+        if !self.callbacks.contains_key(&sig) {
+            let state = &REGISTRY_SIG_STATES[sig as usize];
+            state.pending.store(false, Ordering::SeqCst);
+            state.chain_handler.store(0, Ordering::SeqCst);
+
+            let old_action = sig_setaction(
+                sig,
+                Sigaction {
+                    handler: SigHandler::ActionHandler(registry_handler),
+                    mask: Sigset::empty(),
+                    flags: Flags::RESTART,
+                },
+            )?;
+
+            // There's a small window here where the previous handler won't be called, but
+            // there's no way to avoid that without blocking the signal.
+            if chain {
+                match old_action.handler {
+                    SigHandler::Handler(f) => {
+                        state.chain_siginfo.store(false, Ordering::SeqCst);
+                        state.chain_handler.store(f as usize, Ordering::SeqCst);
+                    }
+                    SigHandler::ActionHandler(f) => {
+                        state.chain_siginfo.store(true, Ordering::SeqCst);
+                        state.chain_handler.store(f as usize, Ordering::SeqCst);
+                    }
+                    SigHandler::Default | SigHandler::Ignore => (),
+                }
+            }
+
+            self.old_actions.insert(sig, old_action);
+        }
+
+        self.callbacks
+            .entry(sig)
+            .or_default()
+            .push(Box::new(callback));
+
+        Ok(())
+    }
+
+    /// Remove all the callbacks for the given signal and restore the action that was installed
+    /// before it was registered.
+    ///
+    /// Fails with `ENOENT` if the signal is not registered.
+    pub fn unregister(&mut self, sig: Int) -> io::Result<()> {
+        let old_action = match self.old_actions.get(&sig) {
+            Some(action) => *action,
+            None => return Err(io::Error::from_raw_os_error(libc::ENOENT)),
+        };
+
+        sig_setaction(sig, old_action)?;
+
+        REGISTRY_SIG_STATES[sig as usize]
+            .chain_handler
+            .store(0, Ordering::SeqCst);
+
+        self.old_actions.remove(&sig);
+        self.callbacks.remove(&sig);
+
+        Ok(())
+    }
+
+    /// Check whether the given signal is registered.
+    #[inline]
+    pub fn is_registered(&self, sig: Int) -> bool {
+        self.callbacks.contains_key(&sig)
+    }
+
+    /// Register this registry's file descriptor with the given poller (monitoring it for
+    /// readability).
+    #[inline]
+    pub fn register_with<P: crate::pollers::Poller>(&self, poller: &mut P) -> io::Result<()> {
+        poller.register(self.read_fd, crate::pollers::Events::READ)
+    }
+
+    /// Run the callbacks for all the signals that have been received since the last call.
+    ///
+    /// The signals are handled in order of signal number. This does not block. It returns the
+    /// number of signals whose callbacks were run.
+    pub fn dispatch(&mut self) -> io::Result<usize> {
+        // Drain the file descriptor first, so that any signals received while we're running
+        // the callbacks will wake it up again
+        let mut buf = [0u8; 64];
+        loop {
+            match crate::error::convert_neg_ret(unsafe {
+                libc::read(
+                    self.read_fd,
+                    buf.as_mut_ptr() as *mut libc::c_void,
+                    buf.len(),
+                )
+            }) {
+                Ok(0) => break,
+                Ok(_) => (),
+                Err(e) if crate::error::is_eagain(&e) || crate::error::is_ewouldblock(&e) => break,
+                Err(e) if crate::error::is_eintr(&e) => (),
+                Err(e) => return Err(e),
+            }
+        }
+
+        let mut count = 0;
+
+        for (&sig, callbacks) in self.callbacks.iter_mut() {
+            if REGISTRY_SIG_STATES[sig as usize]
+                .pending
+                .swap(false, Ordering::SeqCst)
+            {
+                count += 1;
+
+                for callback in callbacks.iter_mut() {
+                    callback(sig);
+                }
+            }
+        }
+
+        Ok(count)
+    }
+}
+
+impl AsRawFd for SignalRegistry {
+    #[inline]
+    fn as_raw_fd(&self) -> Int {
+        self.read_fd
+    }
+}
+
+impl fmt::Debug for SignalRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SignalRegistry")
+            .field("read_fd", &self.read_fd)
+            .field("write_fd", &self.write_fd)
+            .field("signals", &self.callbacks.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl Drop for SignalRegistry {
+    fn drop(&mut self) {
+        let sigs: Vec<Int> = self.old_actions.keys().copied().collect();
+        for sig in sigs {
+            let _ = self.unregister(sig);
+        }
+
+        // A handler that starts after this will see -1, so once there are no handlers running
+        // it's safe to close the file descriptors.
+        REGISTRY_WAKE_FD.store(-1, Ordering::SeqCst);
+        while REGISTRY_HANDLERS_ACTIVE.load(Ordering::SeqCst) != 0 {
+            std::thread::yield_now();
+        }
+
+        unsafe {
+            libc::close(self.read_fd);
+            if self.write_fd != self.read_fd {
+                libc::close(self.write_fd);
+            }
+        }
+
+        REGISTRY_ACTIVE.store(false, Ordering::SeqCst);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -169,4 +570,149 @@ mod tests {
         // Just a dummy call so it shows up as covered
         empty_sighandler(crate::signal::SIGINT);
     }
+
+    #[test]
+    fn test_signal_registry() {
+        // Changing signal dispositions affects every thread, so do this in a (single-threaded)
+        // child process.
+        match crate::process::fork().unwrap() {
+            0 => {
+                let res = std::panic::catch_unwind(check_signal_registry);
+                unsafe { libc::_exit(if res.is_ok() { 0 } else { 1 }) }
+            }
+            pid => {
+                let (_, status) = crate::wait::waitpid(
+                    crate::wait::WaitpidSpec::Pid(pid),
+                    crate::wait::WaitpidOptions::empty(),
+                )
+                .unwrap()
+                .unwrap();
+
+                assert_eq!(status, crate::wait::ProcStatus::Exited(0));
+            }
+        }
+    }
+
+    fn check_signal_registry() {
+        use std::sync::{Arc, Mutex};
+        use std::time::Duration;
+
+        use crate::pollers::Poller;
+
+        static CHAINED_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+        extern "C" fn chained_handler(_sig: Int) {
+            CHAINED_COUNT.fetch_add(1, Ordering::SeqCst);
+        }
+
+        let sig = crate::signal::SIGUSR2;
+        let send_sig = || {
+            crate::tgkill(crate::process::getpid(), crate::process::gettid(), sig).unwrap();
+        };
+
+        let orig_action = sig_setaction(
+            sig,
+            Sigaction {
+                handler: SigHandler::Handler(chained_handler),
+                mask: Sigset::empty(),
+                flags: Flags::empty(),
+            },
+        )
+        .unwrap();
+
+        let mut registry = SignalRegistry::new().unwrap();
+        assert_eq!(
+            SignalRegistry::new().unwrap_err().raw_os_error(),
+            Some(libc::EBUSY)
+        );
+
+        let mut poller = crate::pollers::PollPoller::new().unwrap();
+        registry.register_with(&mut poller).unwrap();
+
+        let count = Arc::new(AtomicUsize::new(0));
+        let count2 = count.clone();
+        registry
+            .register(sig, true, move |s| {
+                assert_eq!(s, sig);
+                count2.fetch_add(1, Ordering::SeqCst);
+            })
+            .unwrap();
+        assert!(registry.is_registered(sig));
+
+        assert_eq!(
+            registry
+                .register(crate::signal::SIGKILL, false, |_| ())
+                .unwrap_err()
+                .raw_os_error(),
+            Some(libc::EINVAL)
+        );
+
+        // Nothing pending
+        assert_eq!(poller.poll(Some(Duration::from_secs(0))).unwrap(), vec![]);
+        assert_eq!(registry.dispatch().unwrap(), 0);
+
+        send_sig();
+        assert_eq!(CHAINED_COUNT.load(Ordering::SeqCst), 1);
+        assert_eq!(count.load(Ordering::SeqCst), 0);
+
+        assert_eq!(
+            poller.poll(Some(Duration::from_secs(5))).unwrap(),
+            vec![(registry.as_raw_fd(), crate::pollers::Events::READ)]
+        );
+        assert_eq!(registry.dispatch().unwrap(), 1);
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+
+        // The file descriptor was drained
+        assert_eq!(poller.poll(Some(Duration::from_secs(0))).unwrap(), vec![]);
+        assert_eq!(registry.dispatch().unwrap(), 0);
+
+        // Unregistering restores the previous handler
+        registry.unregister(sig).unwrap();
+        assert!(!registry.is_registered(sig));
+        assert_eq!(
+            registry.unregister(sig).unwrap_err().raw_os_error(),
+            Some(libc::ENOENT)
+        );
+        assert_eq!(
+            sig_getaction(sig).unwrap().handler,
+            SigHandler::Handler(chained_handler)
+        );
+
+        send_sig();
+        assert_eq!(CHAINED_COUNT.load(Ordering::SeqCst), 2);
+        assert_eq!(registry.dispatch().unwrap(), 0);
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+
+        // Without chaining
+        registry.register(sig, false, |_| ()).unwrap();
+        send_sig();
+        assert_eq!(CHAINED_COUNT.load(Ordering::SeqCst), 2);
+        assert_eq!(registry.dispatch().unwrap(), 1);
+
+        // Signals are dispatched in order
+        let order = Arc::new(Mutex::new(Vec::new()));
+        for &s in [sig, crate::signal::SIGUSR1, crate::signal::SIGHUP].iter() {
+            let order = order.clone();
+            registry
+                .register(s, false, move |s| order.lock().unwrap().push(s))
+                .unwrap();
+        }
+        for &s in [crate::signal::SIGUSR1, sig, crate::signal::SIGHUP].iter() {
+            crate::tgkill(crate::process::getpid(), crate::process::gettid(), s).unwrap();
+        }
+        assert_eq!(registry.dispatch().unwrap(), 3);
+        let mut expected = vec![sig, crate::signal::SIGUSR1, crate::signal::SIGHUP];
+        expected.sort();
+        assert_eq!(*order.lock().unwrap(), expected);
+
+        // Dropping the registry restores the previous handler and lets another one be created
+        drop(registry);
+        assert_eq!(
+            sig_getaction(sig).unwrap().handler,
+            SigHandler::Handler(chained_handler)
+        );
+        drop(SignalRegistry::new().unwrap());
+
+        sig_setaction(sig, orig_action).unwrap();
+    }
 }
@@ -0,0 +1,422 @@
+use std::io;
+use std::sync::atomic::{AtomicPtr, Ordering};
+
+use bitflags::bitflags;
+
+use crate::sigaction::{Flags, SigHandler, Sigaction};
+use crate::signal::Sigset;
+use crate::Int;
+
+bitflags! {
+    pub struct AltStackFlags: Int {
+        const ONSTACK = libc::SS_ONSTACK;
+        const DISABLE = libc::SS_DISABLE;
+    }
+}
+
+/// Information on an alternate signal stack, as used by `sigaltstack()`.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct AltStackInfo {
+    /// The base (lowest address) of the stack.
+    pub base: *mut libc::c_void,
+    pub size: usize,
+    pub flags: AltStackFlags,
+}
+
+impl AltStackInfo {
+    /// An `AltStackInfo` that, when passed to `set_altstack()`, disables the alternate signal
+    /// stack.
+    pub fn disabled() -> Self {
+        Self {
+            base: std::ptr::null_mut(),
+            size: 0,
+            flags: AltStackFlags::DISABLE,
+        }
+    }
+}
+
+impl From<libc::stack_t> for AltStackInfo {
+    fn from(stack: libc::stack_t) -> Self {
+        Self {
+            base: stack.ss_sp as _,
+            size: stack.ss_size as _,
+            flags: AltStackFlags::from_bits_truncate(stack.ss_flags),
+        }
+    }
+}
+
+impl From<AltStackInfo> for libc::stack_t {
+    fn from(info: AltStackInfo) -> Self {
+        libc::stack_t {
+            ss_sp: info.base as _,
+            ss_size: info.size as _,
+            ss_flags: info.flags.bits,
+        }
+    }
+}
+
+fn sigaltstack(new_stack: Option<AltStackInfo>) -> io::Result<AltStackInfo> {
+    let mut old_stack: libc::stack_t = unsafe { std::mem::zeroed() };
+
+    let new_stack = new_stack.map(libc::stack_t::from);
+
+    crate::error::convert_nzero_ret(unsafe {
+        libc::sigaltstack(
+            crate::internal::ptr_from_opt_ref(new_stack.as_ref()),
+            &mut old_stack,
+        )
+    })?;
+
+    Ok(AltStackInfo::from(old_stack))
+}
+
+/// Get information on the current thread's alternate signal stack.
+#[inline]
+pub fn get_altstack() -> io::Result<AltStackInfo> {
+    sigaltstack(None)
+}
+
+/// Set the current thread's alternate signal stack, returning information on the previous
+/// stack.
+///
+/// # Safety
+///
+/// The given memory must remain valid (and must not be used for anything else) for as long as
+/// it is installed as the alternate signal stack.
+#[inline]
+pub unsafe fn set_altstack(stack: AltStackInfo) -> io::Result<AltStackInfo> {
+    sigaltstack(Some(stack))
+}
+
+/// Disable the current thread's alternate signal stack, returning information on the previous
+/// stack.
+///
+/// This fails with `EPERM` if the thread is currently executing on the alternate signal stack.
+#[inline]
+pub fn disable_altstack() -> io::Result<AltStackInfo> {
+    sigaltstack(Some(AltStackInfo::disabled()))
+}
+
+fn page_size() -> usize {
+    unsafe { libc::sysconf(libc::_SC_PAGESIZE) as usize }
+}
+
+/// An alternate signal stack allocated with `mmap()`, installed for the current thread.
+///
+/// The stack is preceded by a guard page, so overflowing it will crash the process instead of
+/// silently corrupting memory. When this is dropped, the previous alternate signal stack (if
+/// any) is restored, and the memory is unmapped.
+///
+/// Alternate signal stacks are per-thread, so this cannot be sent to other threads.
+#[derive(Debug)]
+pub struct AltStack {
+    map: *mut libc::c_void,
+    map_len: usize,
+    old_stack: AltStackInfo,
+}
+
+impl AltStack {
+    /// The default stack size used by `install_default()` (64 KiB, or `SIGSTKSZ` if that is
+    /// larger).
+    pub const DEFAULT_SIZE: usize = if libc::SIGSTKSZ > 65536 {
+        libc::SIGSTKSZ
+    } else {
+        65536
+    };
+
+    /// Allocate a new stack of (at least) the given size and install it as the current
+    /// thread's alternate signal stack.
+    ///
+    /// The size is rounded up to a multiple of the page size, and to at least `MINSIGSTKSZ`.
+    pub fn install(size: usize) -> io::Result<Self> {
+        let page_size = page_size();
+
+        let size = std::cmp::max(size, libc::MINSIGSTKSZ);
+        // The page size is always a power of 2
+        let size = (size + page_size - 1) & !(page_size - 1);
+        let map_len = size + page_size;
+
+        let map = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                map_len,
+                libc::PROT_NONE,
+                libc::MAP_PRIVATE | libc::MAP_ANON,
+                -1,
+                0,
+            )
+        };
+        if map == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+
+        // Stacks grow down, so the guard page goes at the bottom
+        let base = unsafe { (map as *mut u8).add(page_size) } as *mut libc::c_void;
+
+        let res = crate::error::convert_nzero_ret(unsafe {
+            libc::mprotect(base, size, libc::PROT_READ | libc::PROT_WRITE)
+        })
+        .and_then(|()| unsafe {
+            set_altstack(AltStackInfo {
+                base,
+                size,
+                flags: AltStackFlags::empty(),
+            })
+        });
+
+        match res {
+            Ok(old_stack) => Ok(Self {
+                map,
+                map_len,
+                old_stack,
+            }),
+            Err(e) => {
+                unsafe {
+                    libc::munmap(map, map_len);
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// Equivalent to `AltStack::install(AltStack::DEFAULT_SIZE)`.
+    #[inline]
+    pub fn install_default() -> io::Result<Self> {
+        Self::install(Self::DEFAULT_SIZE)
+    }
+
+    /// Get the base (lowest address) of the usable portion of the stack.
+    #[inline]
+    pub fn base(&self) -> *mut libc::c_void {
+        unsafe { (self.map as *mut u8).add(self.map_len - self.size()) as *mut libc::c_void }
+    }
+
+    /// Get the size of the usable portion of the stack (not including the guard page).
+    #[inline]
+    pub fn size(&self) -> usize {
+        self.map_len - page_size()
+    }
+
+    /// Get information on the alternate signal stack that was installed before this one.
+    #[inline]
+    pub fn old_stack(&self) -> AltStackInfo {
+        self.old_stack
+    }
+}
+
+impl Drop for AltStack {
+    fn drop(&mut self) {
+        let old_stack = if self.old_stack.flags.contains(AltStackFlags::DISABLE) {
+            AltStackInfo::disabled()
+        } else {
+            AltStackInfo {
+                flags: AltStackFlags::empty(),
+                ..self.old_stack
+            }
+        };
+
+        // If this fails (probably because we're currently running on the stack), we have to
+        // leak the memory.
+        if unsafe { set_altstack(old_stack) }.is_ok() {
+            unsafe {
+                libc::munmap(self.map, self.map_len);
+            }
+        }
+    }
+}
+
+/// Information on a fault that triggered a crash handler installed by
+/// `install_crash_handlers()`.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct FaultInfo {
+    /// The signal (`SIGSEGV`, `SIGBUS`, or `SIGFPE`).
+    pub sig: Int,
+    /// The signal code (`si_code`), such as `SEGV_MAPERR` or `FPE_INTDIV`. If the signal was
+    /// sent by a process (for example, with `kill()`), this will be a value such as `SI_USER`.
+    pub code: Int,
+    /// The address of the fault (`si_addr`). For `SIGSEGV` and `SIGBUS`, this is the memory
+    /// address that was accessed; for `SIGFPE`, it is the address of the instruction.
+    pub addr: *mut libc::c_void,
+}
+
+/// A callback passed to `install_crash_handlers()`.
+///
+/// This is called from a signal handler, so it must only perform async-signal-safe operations
+/// (for example, it must not allocate memory or acquire locks).
+pub type CrashCallback = fn(&FaultInfo);
+
+static CRASH_CALLBACK: AtomicPtr<()> = AtomicPtr::new(std::ptr::null_mut());
+
+/// The signals handled by `install_crash_handlers()`.
+pub const CRASH_SIGNALS: [Int; 3] = [libc::SIGSEGV, libc::SIGBUS, libc::SIGFPE];
+
+extern "C" fn crash_handler(sig: Int, info: *mut libc::siginfo_t, _context: *mut libc::c_void) {
+    let info = unsafe { &*info };
+
+    #[cfg(target_os = "linux")]
+    let addr = unsafe { info.si_addr() };
+    #[cfg(not(target_os = "linux"))]
+    let addr = info.si_addr;
+
+    let callback = CRASH_CALLBACK.load(Ordering::SeqCst);
+    if !callback.is_null() {
+        let callback: CrashCallback = unsafe { std::mem::transmute(callback) };
+
+        callback(&FaultInfo {
+            sig,
+            code: info.si_code,
+            addr,
+        });
+    }
+
+    // The handler was installed with SA_RESETHAND, so the default action has been restored.
+    // The signal is blocked while this handler is running, so it will be delivered again (and
+    // kill the process) as soon as we return. (We can't rely on the faulting instruction
+    // being re-executed because the signal may have been sent by another process.)
+    unsafe {
+        libc::raise(sig);
+    }
+}
+
+/// Install handlers for `SIGSEGV`, `SIGBUS`, and `SIGFPE` that call the given callback with
+/// information on the fault, then allow the process to be killed by the signal as usual.
+///
+/// The handlers run on the alternate signal stack (if one is installed), so stack overflows can
+/// be reported. This function installs an alternate signal stack of the given size for the
+/// current thread and returns it; the stack must be kept alive for as long as the handlers are
+/// installed. Other threads need their own `AltStack`s.
+///
+/// Each handler only runs once; after it runs, the default action is restored.
+pub fn install_crash_handlers(callback: CrashCallback, stack_size: usize) -> io::Result<AltStack> {
+    let stack = AltStack::install(stack_size)?;
+
+    CRASH_CALLBACK.store(callback as *mut (), Ordering::SeqCst);
+
+    for &sig in CRASH_SIGNALS.iter() {
+        crate::sigaction::sig_setaction(
+            sig,
+            Sigaction {
+                handler: SigHandler::ActionHandler(crash_handler),
+                mask: Sigset::empty(),
+                flags: Flags::ONSTACK | Flags::RESETHAND,
+            },
+        )?;
+    }
+
+    Ok(stack)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_altstack() {
+        let orig_stack = get_altstack().unwrap();
+
+        let stack = AltStack::install(1).unwrap();
+        assert!(stack.size() >= libc::MINSIGSTKSZ);
+        assert_eq!(stack.size() % page_size(), 0);
+        assert_eq!(stack.old_stack(), orig_stack);
+
+        let cur_stack = get_altstack().unwrap();
+        assert_eq!(cur_stack.base, stack.base());
+        assert_eq!(cur_stack.size, stack.size());
+        assert!(!cur_stack.flags.contains(AltStackFlags::DISABLE));
+
+        // Nested stacks restore properly
+        let stack2 = AltStack::install_default().unwrap();
+        assert!(stack2.size() >= AltStack::DEFAULT_SIZE);
+        assert_eq!(stack2.old_stack().base, stack.base());
+        assert_eq!(get_altstack().unwrap().base, stack2.base());
+
+        drop(stack2);
+        assert_eq!(get_altstack().unwrap(), cur_stack);
+
+        drop(stack);
+        let restored_stack = get_altstack().unwrap();
+        assert_eq!(
+            restored_stack.flags.contains(AltStackFlags::DISABLE),
+            orig_stack.flags.contains(AltStackFlags::DISABLE),
+        );
+
+        disable_altstack().unwrap();
+        assert!(get_altstack()
+            .unwrap()
+            .flags
+            .contains(AltStackFlags::DISABLE));
+    }
+
+    #[test]
+    fn test_crash_handlers() {
+        use std::io::Read;
+        use std::os::unix::io::AsRawFd;
+
+        static REPORT_FD: std::sync::atomic::AtomicI32 = std::sync::atomic::AtomicI32::new(-1);
+
+        fn report(info: &FaultInfo) {
+            let buf = [info.sig as u8, (info.addr as usize == 0x10) as u8];
+            unsafe {
+                libc::write(
+                    REPORT_FD.load(Ordering::SeqCst),
+                    buf.as_ptr() as *const libc::c_void,
+                    buf.len(),
+                );
+            }
+        }
+
+        #[allow(unconditional_recursion)]
+        fn overflow(depth: usize) -> usize {
+            let buf = [depth as u8; 1024];
+            overflow(depth + 1) + unsafe { std::ptr::read_volatile(&buf[depth % 1024]) } as usize
+        }
+
+        for &overflow_stack in [false, true].iter() {
+            let (mut r, w) = crate::pipe().unwrap();
+
+            match crate::process::fork().unwrap() {
+                0 => {
+                    REPORT_FD.store(w.as_raw_fd(), Ordering::SeqCst);
+
+                    let _stack = install_crash_handlers(report, AltStack::DEFAULT_SIZE).unwrap();
+
+                    if overflow_stack {
+                        overflow(0);
+                    } else {
+                        unsafe {
+                            std::ptr::read_volatile(0x10 as *const u8);
+                        }
+                    }
+
+                    unsafe {
+                        libc::_exit(0);
+                    }
+                }
+                pid => {
+                    drop(w);
+
+                    let mut buf = Vec::new();
+                    r.read_to_end(&mut buf).unwrap();
+
+                    let (_, status) = crate::wait::waitpid(
+                        crate::wait::WaitpidSpec::Pid(pid),
+                        crate::wait::WaitpidOptions::empty(),
+                    )
+                    .unwrap()
+                    .unwrap();
+
+                    assert!(matches!(
+                        status,
+                        crate::wait::ProcStatus::Signaled(libc::SIGSEGV, _)
+                    ));
+
+                    assert_eq!(buf.len(), 2);
+                    assert_eq!(buf[0] as Int, libc::SIGSEGV);
+                    if !overflow_stack {
+                        assert_eq!(buf[1], 1);
+                    }
+                }
+            }
+        }
+    }
+}
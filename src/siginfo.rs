@@ -0,0 +1,676 @@
+use std::convert::TryInto;
+use std::io;
+use std::time::Duration;
+
+use crate::sigaction::SigHandler;
+use crate::signal::Sigset;
+use crate::types::rt_siginfo;
+use crate::{Int, PidT, UidT};
+
+/// The value sent along with a signal by `sigqueue()` (a C `union sigval`).
+///
+/// The union can hold either an integer or a pointer; there is no way to tell which one the
+/// sender used.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct SigVal {
+    raw: *mut libc::c_void,
+}
+
+impl SigVal {
+    #[inline]
+    pub fn from_int(val: Int) -> Self {
+        let mut res = Self {
+            raw: std::ptr::null_mut(),
+        };
+        unsafe {
+            *(&mut res.raw as *mut *mut libc::c_void as *mut Int) = val;
+        }
+        res
+    }
+
+    #[inline]
+    pub fn from_ptr(ptr: *mut libc::c_void) -> Self {
+        Self { raw: ptr }
+    }
+
+    /// Interpret the value as an integer.
+    #[inline]
+    pub fn as_int(self) -> Int {
+        unsafe { *(&self.raw as *const *mut libc::c_void as *const Int) }
+    }
+
+    /// Interpret the value as a pointer.
+    #[inline]
+    pub fn as_ptr(self) -> *mut libc::c_void {
+        self.raw
+    }
+}
+
+impl From<SigVal> for libc::sigval {
+    #[inline]
+    fn from(val: SigVal) -> Self {
+        Self { sival_ptr: val.raw }
+    }
+}
+
+impl From<libc::sigval> for SigVal {
+    #[inline]
+    fn from(val: libc::sigval) -> Self {
+        Self::from_ptr(val.sival_ptr)
+    }
+}
+
+pub use crate::constants::{
+    POLL_ERR, POLL_HUP, POLL_IN, POLL_MSG, POLL_OUT, POLL_PRI, SEGV_ACCERR, SEGV_MAPERR,
+    SYS_SECCOMP,
+};
+
+/// Decoded information on a received signal.
+///
+/// This can be obtained from a raw `siginfo_t` (with `from_raw()`), from a
+/// `signalfd::Siginfo`, or by installing a handler created with `typed_handler()`.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum SigInfo {
+    /// The signal was sent by a process (with `kill()`, `sigqueue()`, `tgkill()`, etc.).
+    User {
+        sig: Int,
+        /// The signal code (for example, `SI_USER` or `SI_QUEUE`).
+        code: Int,
+        /// The PID of the sending process.
+        pid: PidT,
+        /// The real UID of the sending process.
+        uid: UidT,
+        /// The value passed to `sigqueue()` (only present if `code` is `SI_QUEUE` or
+        /// `SI_MESGQ`).
+        value: Option<SigVal>,
+    },
+    /// A child process changed state (usually for `SIGCHLD`).
+    Child {
+        sig: Int,
+        /// One of the `CLD_*` constants.
+        code: Int,
+        /// The PID of the child.
+        pid: PidT,
+        /// The real UID of the child.
+        uid: UidT,
+        /// The exit code (for `CLD_EXITED`) or the signal that caused the state change (for
+        /// all other codes).
+        status: Int,
+    },
+    /// A hardware fault (`SIGSEGV`, `SIGBUS`, `SIGILL`, `SIGFPE`, or `SIGTRAP`).
+    Fault {
+        sig: Int,
+        /// The fault code (for example, `SEGV_MAPERR` or `SEGV_ACCERR`).
+        code: Int,
+        /// The address of the fault.
+        addr: usize,
+    },
+    /// An I/O event on a file descriptor (`SIGIO`/`SIGPOLL`, or the signal set with
+    /// `F_SETSIG`).
+    Poll {
+        sig: Int,
+        /// One of the `POLL_*` constants.
+        code: Int,
+        /// The band event (the `POLL*` events that occurred).
+        band: crate::Long,
+        /// The file descriptor that the event occurred on.
+        fd: Int,
+    },
+    /// A POSIX timer expired.
+    Timer {
+        sig: Int,
+        /// The kernel's ID for the timer.
+        timerid: Int,
+        /// The number of additional expirations that occurred before the signal was delivered.
+        overrun: Int,
+        /// The value specified when the timer was created.
+        value: SigVal,
+    },
+    /// A system call was blocked by a seccomp filter (`SIGSYS` with code `SYS_SECCOMP`).
+    Sys {
+        sig: Int,
+        /// The address of the system call instruction.
+        call_addr: usize,
+        /// The number of the system call that was attempted.
+        syscall: Int,
+        /// The architecture of the system call (one of the `AUDIT_ARCH_*` values).
+        arch: u32,
+    },
+    /// Some other type of signal.
+    Other { sig: Int, code: Int },
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum SigInfoKind {
+    User,
+    Queue,
+    Child,
+    Fault,
+    Poll,
+    Timer,
+    Sys,
+    Other,
+}
+
+impl SigInfoKind {
+    fn classify(sig: Int, code: Int) -> Self {
+        match code {
+            libc::SI_USER | libc::SI_TKILL => Self::User,
+            libc::SI_QUEUE | libc::SI_MESGQ => Self::Queue,
+            libc::SI_TIMER => Self::Timer,
+            // Other codes <= 0 are sent from userspace; SI_KERNEL means the kernel sent it for
+            // some other reason.
+            libc::SI_KERNEL => Self::Other,
+            _ if code <= 0 => Self::Other,
+
+            _ => match sig {
+                libc::SIGCHLD => Self::Child,
+                libc::SIGSEGV | libc::SIGBUS | libc::SIGILL | libc::SIGFPE | libc::SIGTRAP => {
+                    Self::Fault
+                }
+                libc::SIGIO => Self::Poll,
+                libc::SIGSYS if code == SYS_SECCOMP => Self::Sys,
+                // Signals set with F_SETSIG
+                _ if (POLL_IN..=POLL_HUP).contains(&code) => Self::Poll,
+                _ => Self::Other,
+            },
+        }
+    }
+}
+
+impl SigInfo {
+    /// Decode the given `siginfo_t` structure.
+    pub fn from_raw(info: &libc::siginfo_t) -> Self {
+        use crate::types::{
+            fault_siginfo, poll_siginfo, sys_siginfo, timer_siginfo, waitpid_siginfo,
+        };
+
+        let sig = info.si_signo;
+        let code = info.si_code;
+        let ptr = info as *const libc::siginfo_t;
+
+        unsafe {
+            match SigInfoKind::classify(sig, code) {
+                kind @ SigInfoKind::User | kind @ SigInfoKind::Queue => {
+                    let info = &*(ptr as *const rt_siginfo);
+                    Self::User {
+                        sig,
+                        code,
+                        pid: info.si_pid,
+                        uid: info.si_uid,
+                        value: if kind == SigInfoKind::Queue {
+                            Some(SigVal::from_ptr(info.si_value))
+                        } else {
+                            None
+                        },
+                    }
+                }
+                SigInfoKind::Child => {
+                    let info = &*(ptr as *const waitpid_siginfo);
+                    Self::Child {
+                        sig,
+                        code,
+                        pid: info.si_pid,
+                        uid: info.si_uid,
+                        status: info.si_status,
+                    }
+                }
+                SigInfoKind::Fault => Self::Fault {
+                    sig,
+                    code,
+                    addr: (*(ptr as *const fault_siginfo)).si_addr as usize,
+                },
+                SigInfoKind::Poll => {
+                    let info = &*(ptr as *const poll_siginfo);
+                    Self::Poll {
+                        sig,
+                        code,
+                        band: info.si_band,
+                        fd: info.si_fd,
+                    }
+                }
+                SigInfoKind::Timer => {
+                    let info = &*(ptr as *const timer_siginfo);
+                    Self::Timer {
+                        sig,
+                        timerid: info.si_tid,
+                        overrun: info.si_overrun,
+                        value: SigVal::from_ptr(info.si_value),
+                    }
+                }
+                SigInfoKind::Sys => {
+                    let info = &*(ptr as *const sys_siginfo);
+                    Self::Sys {
+                        sig,
+                        call_addr: info.si_call_addr as usize,
+                        syscall: info.si_syscall,
+                        arch: info.si_arch,
+                    }
+                }
+                SigInfoKind::Other => Self::Other { sig, code },
+            }
+        }
+    }
+
+    /// Get the signal number.
+    pub fn sig(&self) -> Int {
+        match *self {
+            Self::User { sig, .. } => sig,
+            Self::Child { sig, .. } => sig,
+            Self::Fault { sig, .. } => sig,
+            Self::Poll { sig, .. } => sig,
+            Self::Timer { sig, .. } => sig,
+            Self::Sys { sig, .. } => sig,
+            Self::Other { sig, .. } => sig,
+        }
+    }
+
+    /// Get the signal code (`si_code`).
+    pub fn code(&self) -> Int {
+        match *self {
+            Self::User { code, .. } => code,
+            Self::Child { code, .. } => code,
+            Self::Fault { code, .. } => code,
+            Self::Poll { code, .. } => code,
+            Self::Timer { .. } => libc::SI_TIMER,
+            Self::Sys { .. } => SYS_SECCOMP,
+            Self::Other { code, .. } => code,
+        }
+    }
+}
+
+impl From<&crate::signalfd::Siginfo> for SigInfo {
+    fn from(info: &crate::signalfd::Siginfo) -> Self {
+        let sig = info.sig as Int;
+        let code = info.code;
+
+        match SigInfoKind::classify(sig, code) {
+            kind @ SigInfoKind::User | kind @ SigInfoKind::Queue => Self::User {
+                sig,
+                code,
+                pid: info.pid as PidT,
+                uid: info.uid as UidT,
+                value: if kind == SigInfoKind::Queue {
+                    Some(SigVal::from_ptr(info.ptr as usize as *mut libc::c_void))
+                } else {
+                    None
+                },
+            },
+            SigInfoKind::Child => Self::Child {
+                sig,
+                code,
+                pid: info.pid as PidT,
+                uid: info.uid as UidT,
+                status: info.status,
+            },
+            SigInfoKind::Fault => Self::Fault {
+                sig,
+                code,
+                addr: info.addr as usize,
+            },
+            SigInfoKind::Poll => Self::Poll {
+                sig,
+                code,
+                band: info.band as crate::Long,
+                fd: info.fd,
+            },
+            SigInfoKind::Timer => Self::Timer {
+                sig,
+                timerid: info.tid as Int,
+                overrun: info.overrun as Int,
+                value: SigVal::from_ptr(info.ptr as usize as *mut libc::c_void),
+            },
+            SigInfoKind::Sys => Self::Sys {
+                sig,
+                call_addr: info.call_addr as usize,
+                syscall: info.syscall,
+                arch: info.arch,
+            },
+            SigInfoKind::Other => Self::Other { sig, code },
+        }
+    }
+}
+
+impl From<crate::signalfd::Siginfo> for SigInfo {
+    #[inline]
+    fn from(info: crate::signalfd::Siginfo) -> Self {
+        Self::from(&info)
+    }
+}
+
+/// A signal handler that receives decoded signal information.
+///
+/// See `typed_handler()`.
+pub trait SigInfoHandler {
+    /// Handle a signal.
+    ///
+    /// This is called in signal context, so it may only perform async-signal-safe operations.
+    fn handle(info: &SigInfo);
+}
+
+extern "C" fn typed_handler_trampoline<H: SigInfoHandler>(
+    _sig: Int,
+    info: *mut libc::siginfo_t,
+    _context: *mut libc::c_void,
+) {
+    if let Some(info) = unsafe { info.as_ref() } {
+        H::handle(&SigInfo::from_raw(info));
+    }
+}
+
+/// Get a `SigHandler` that decodes the `siginfo_t` passed to an `SA_SIGINFO` handler and then
+/// calls `H::handle()`.
+///
+/// Example:
+/// ```
+/// use simple_libc::sigaction::{Sigaction, Flags};
+/// use simple_libc::siginfo::{typed_handler, SigInfo, SigInfoHandler};
+/// use simple_libc::signal::Sigset;
+///
+/// struct Handler;
+///
+/// impl SigInfoHandler for Handler {
+///     fn handle(info: &SigInfo) {
+///         // ...
+///     }
+/// }
+///
+/// let act = Sigaction {
+///     handler: typed_handler::<Handler>(),
+///     mask: Sigset::empty(),
+///     flags: Flags::empty(),
+/// };
+/// ```
+pub fn typed_handler<H: SigInfoHandler>() -> SigHandler {
+    SigHandler::ActionHandler(typed_handler_trampoline::<H>)
+}
+
+fn build_queue_siginfo(sig: Int, value: SigVal) -> libc::siginfo_t {
+    let mut info: libc::siginfo_t = unsafe { std::mem::zeroed() };
+
+    let rt_info = unsafe { &mut *(&mut info as *mut libc::siginfo_t as *mut rt_siginfo) };
+    rt_info.si_signo = sig;
+    rt_info.si_code = libc::SI_QUEUE;
+    rt_info.si_pid = crate::process::getpid();
+    rt_info.si_uid = crate::process::getuid();
+    rt_info.si_value = value.as_ptr();
+
+    info
+}
+
+/// Send the given signal, with the given value, to the given process.
+///
+/// The receiver can retrieve the value from the `siginfo_t` (for example, using
+/// `sigwaitinfo()`, a `SignalFd`, or an `SA_SIGINFO` handler). If `sig` is a realtime signal,
+/// multiple instances of the signal will be queued.
+pub fn sigqueue(pid: PidT, sig: Int, value: SigVal) -> io::Result<()> {
+    crate::error::convert_nzero_ret(unsafe { libc::sigqueue(pid, sig, value.into()) })
+}
+
+/// Send the given signal, with the given value, to the given thread in the given thread group.
+///
+/// This is like a combination of `tgkill()` and `sigqueue()`.
+pub fn tgsigqueue(tgid: PidT, tid: PidT, sig: Int, value: SigVal) -> io::Result<()> {
+    let mut info = build_queue_siginfo(sig, value);
+
+    crate::error::convert_nzero_ret(unsafe {
+        libc::syscall(
+            libc::SYS_rt_tgsigqueueinfo,
+            tgid as Int,
+            tid as Int,
+            sig,
+            &mut info as *mut libc::siginfo_t,
+        )
+    })
+}
+
+/// Wait for one of the signals in `set` to become pending, then remove it from the set of
+/// pending signals and return information on it.
+///
+/// The signals in `set` should be blocked (see `sigmask::block()`) when this is called.
+pub fn sigwaitinfo(set: &Sigset) -> io::Result<SigInfo> {
+    let mut info: libc::siginfo_t = unsafe { std::mem::zeroed() };
+
+    crate::error::convert_neg_ret(unsafe { libc::sigwaitinfo(set.as_ref(), &mut info) })?;
+
+    Ok(SigInfo::from_raw(&info))
+}
+
+/// Like `sigwaitinfo()`, but waits for at most the given timeout.
+///
+/// Returns `Ok(None)` if the timeout expired without a signal becoming pending. (A timeout of 0
+/// can be used to check for pending signals without blocking.)
+pub fn sigtimedwait(set: &Sigset, timeout: Duration) -> io::Result<Option<SigInfo>> {
+    let mut info: libc::siginfo_t = unsafe { std::mem::zeroed() };
+
+    let timeout = libc::timespec {
+        tv_sec: timeout.as_secs().try_into().unwrap_or(libc::time_t::MAX),
+        tv_nsec: timeout.subsec_nanos() as crate::Long,
+    };
+
+    match crate::error::convert_neg_ret(unsafe {
+        libc::sigtimedwait(set.as_ref(), &mut info, &timeout)
+    }) {
+        Ok(_) => Ok(Some(SigInfo::from_raw(&info))),
+        Err(e) if crate::error::is_eagain(&e) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::atomic::{AtomicI32, Ordering};
+
+    use crate::process::{getpid, gettid, getuid};
+    use crate::types::{fault_siginfo, poll_siginfo, sys_siginfo};
+
+    #[test]
+    fn test_sigval() {
+        assert_eq!(SigVal::from_int(42).as_int(), 42);
+        assert_eq!(SigVal::from_int(-1).as_int(), -1);
+
+        let mut x = 0;
+        let ptr = &mut x as *mut Int as *mut libc::c_void;
+        assert_eq!(SigVal::from_ptr(ptr).as_ptr(), ptr);
+
+        let raw: libc::sigval = SigVal::from_int(42).into();
+        assert_eq!(SigVal::from(raw).as_int(), 42);
+    }
+
+    #[test]
+    fn test_sigqueue_sigwait() {
+        let (sigrtmin, _) = crate::signal::get_rtsig_minmax().unwrap();
+        let sig = sigrtmin + 2;
+
+        let mut set = Sigset::empty();
+        set.add(sig).unwrap();
+        let _guard = crate::sigmask::SigmaskGuard::block(&set).unwrap();
+
+        // Nothing pending
+        assert_eq!(sigtimedwait(&set, Duration::from_secs(0)).unwrap(), None);
+
+        // Realtime signals are queued, so we should get both back
+        tgsigqueue(getpid(), gettid(), sig, SigVal::from_int(1)).unwrap();
+        tgsigqueue(getpid(), gettid(), sig, SigVal::from_int(2)).unwrap();
+
+        for &val in [1, 2].iter() {
+            match sigwaitinfo(&set).unwrap() {
+                SigInfo::User {
+                    sig: s,
+                    code,
+                    pid,
+                    uid,
+                    value,
+                } => {
+                    assert_eq!(s, sig);
+                    assert_eq!(code, libc::SI_QUEUE);
+                    assert_eq!(pid, getpid());
+                    assert_eq!(uid, getuid());
+                    assert_eq!(value.unwrap().as_int(), val);
+                }
+                info => panic!("{:?}", info),
+            }
+        }
+
+        crate::tgkill(getpid(), gettid(), sig).unwrap();
+        let info = sigtimedwait(&set, Duration::from_secs(5)).unwrap().unwrap();
+        // Some kernels report SI_USER instead of SI_TKILL
+        assert!([libc::SI_USER, libc::SI_TKILL].contains(&info.code()));
+        assert_eq!(
+            info,
+            SigInfo::User {
+                sig,
+                code: info.code(),
+                pid: getpid(),
+                uid: getuid(),
+                value: None,
+            }
+        );
+        assert_eq!(info.sig(), sig);
+
+        // sigqueue() targets the whole process, and another thread might receive it, so just
+        // check that we have permission to send to ourselves
+        sigqueue(getpid(), 0, SigVal::from_int(3)).unwrap();
+    }
+
+    #[test]
+    fn test_decode_raw() {
+        let mut info: libc::siginfo_t = unsafe { std::mem::zeroed() };
+
+        let fault_info = unsafe { &mut *(&mut info as *mut _ as *mut fault_siginfo) };
+        fault_info.si_signo = libc::SIGSEGV;
+        fault_info.si_code = SEGV_ACCERR;
+        fault_info.si_addr = 0x1000 as *mut libc::c_void;
+        assert_eq!(
+            SigInfo::from_raw(&info),
+            SigInfo::Fault {
+                sig: libc::SIGSEGV,
+                code: SEGV_ACCERR,
+                addr: 0x1000,
+            }
+        );
+
+        let poll_info = unsafe { &mut *(&mut info as *mut _ as *mut poll_siginfo) };
+        poll_info.si_signo = libc::SIGIO;
+        poll_info.si_code = POLL_IN;
+        poll_info.si_band = libc::POLLIN as crate::Long;
+        poll_info.si_fd = 3;
+        assert_eq!(
+            SigInfo::from_raw(&info),
+            SigInfo::Poll {
+                sig: libc::SIGIO,
+                code: POLL_IN,
+                band: libc::POLLIN as crate::Long,
+                fd: 3,
+            }
+        );
+
+        info = unsafe { std::mem::zeroed() };
+        let sys_info = unsafe { &mut *(&mut info as *mut _ as *mut sys_siginfo) };
+        sys_info.si_signo = libc::SIGSYS;
+        sys_info.si_code = SYS_SECCOMP;
+        sys_info.si_call_addr = 0x2000 as *mut libc::c_void;
+        sys_info.si_syscall = libc::SYS_getpid as Int;
+        sys_info.si_arch = 0xc000_003e;
+        let decoded = SigInfo::from_raw(&info);
+        assert_eq!(
+            decoded,
+            SigInfo::Sys {
+                sig: libc::SIGSYS,
+                call_addr: 0x2000,
+                syscall: libc::SYS_getpid as Int,
+                arch: 0xc000_003e,
+            }
+        );
+        assert_eq!(decoded.sig(), libc::SIGSYS);
+        assert_eq!(decoded.code(), SYS_SECCOMP);
+
+        // Sent from userspace, so it's not actually a fault
+        let fault_info = unsafe { &mut *(&mut info as *mut _ as *mut fault_siginfo) };
+        fault_info.si_signo = libc::SIGSEGV;
+        fault_info.si_code = libc::SI_KERNEL;
+        assert_eq!(
+            SigInfo::from_raw(&info),
+            SigInfo::Other {
+                sig: libc::SIGSEGV,
+                code: libc::SI_KERNEL,
+            }
+        );
+    }
+
+    #[test]
+    fn test_decode_child() {
+        let pid = crate::process::fork().unwrap();
+        if pid == 0 {
+            unsafe {
+                libc::_exit(3);
+            }
+        }
+
+        let mut info: libc::siginfo_t = unsafe { std::mem::zeroed() };
+        crate::error::convert_nzero_ret(unsafe {
+            libc::waitid(libc::P_PID, pid as libc::id_t, &mut info, libc::WEXITED)
+        })
+        .unwrap();
+
+        assert_eq!(
+            SigInfo::from_raw(&info),
+            SigInfo::Child {
+                sig: libc::SIGCHLD,
+                code: libc::CLD_EXITED,
+                pid,
+                uid: getuid(),
+                status: 3,
+            }
+        );
+    }
+
+    static HANDLER_SIG: AtomicI32 = AtomicI32::new(0);
+    static HANDLER_PID: AtomicI32 = AtomicI32::new(0);
+    static HANDLER_VALUE: AtomicI32 = AtomicI32::new(0);
+
+    struct TestHandler;
+
+    impl SigInfoHandler for TestHandler {
+        fn handle(info: &SigInfo) {
+            if let SigInfo::User {
+                sig,
+                pid,
+                value: Some(value),
+                ..
+            } = *info
+            {
+                HANDLER_PID.store(pid, Ordering::SeqCst);
+                HANDLER_VALUE.store(value.as_int(), Ordering::SeqCst);
+                HANDLER_SIG.store(sig, Ordering::SeqCst);
+            }
+        }
+    }
+
+    #[test]
+    fn test_typed_handler() {
+        let (sigrtmin, _) = crate::signal::get_rtsig_minmax().unwrap();
+        let sig = sigrtmin + 3;
+
+        let old_act = crate::sigaction::sig_setaction(
+            sig,
+            crate::sigaction::Sigaction {
+                handler: typed_handler::<TestHandler>(),
+                mask: Sigset::empty(),
+                flags: crate::sigaction::Flags::empty(),
+            },
+        )
+        .unwrap();
+
+        // The signal is delivered to this thread before tgsigqueue() returns
+        tgsigqueue(getpid(), gettid(), sig, SigVal::from_int(17)).unwrap();
+
+        assert_eq!(HANDLER_SIG.load(Ordering::SeqCst), sig);
+        assert_eq!(HANDLER_PID.load(Ordering::SeqCst), getpid());
+        assert_eq!(HANDLER_VALUE.load(Ordering::SeqCst), 17);
+
+        crate::sigaction::sig_setaction(sig, old_act).unwrap();
+    }
+}
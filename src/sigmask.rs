@@ -4,32 +4,172 @@ use crate::signal::Sigset;
 use crate::Int;
 
 fn sigmask(how: Int, set: Option<&Sigset>) -> io::Result<Sigset> {
-    let oldset = Sigset::empty();
+    let mut oldset = Sigset::empty().raw_set();
 
     let raw_set = crate::internal::ptr_from_opt_ref(set.map(Sigset::as_ref));
 
-    match unsafe { libc::pthread_sigmask(how, raw_set, &mut oldset.raw_set()) } {
-        0 => Ok(oldset),
+    match unsafe { libc::pthread_sigmask(how, raw_set, &mut oldset) } {
+        0 => Ok(Sigset::from(oldset)),
         errno => Err(io::Error::from_raw_os_error(errno)),
     }
 }
 
+/// Get the signal mask of the current thread.
+///
+/// All of the functions in this module use `pthread_sigmask()`, so they only affect the calling
+/// thread (`sigprocmask()`'s behavior is unspecified in multithreaded programs).
 #[inline]
 pub fn getmask() -> io::Result<Sigset> {
     sigmask(0, None)
 }
 
+/// Set the signal mask of the current thread to `set`, returning the old mask.
 #[inline]
 pub fn setmask(set: &Sigset) -> io::Result<Sigset> {
     sigmask(libc::SIG_SETMASK, Some(set))
 }
 
+/// Add the signals in `set` to the signal mask of the current thread, returning the old mask.
 #[inline]
 pub fn block(set: &Sigset) -> io::Result<Sigset> {
     sigmask(libc::SIG_BLOCK, Some(set))
 }
 
+/// Remove the signals in `set` from the signal mask of the current thread, returning the old
+/// mask.
 #[inline]
 pub fn unblock(set: &Sigset) -> io::Result<Sigset> {
     sigmask(libc::SIG_UNBLOCK, Some(set))
 }
+
+/// A guard that restores the signal mask of the current thread when it is dropped.
+///
+/// Since signal masks are per-thread, the guard should be dropped on the same thread that
+/// created it (so it is neither `Send` nor `Sync`).
+#[derive(Debug)]
+pub struct SigmaskGuard {
+    old_mask: Sigset,
+    _phantom: std::marker::PhantomData<*const ()>,
+}
+
+impl SigmaskGuard {
+    fn new(old_mask: Sigset) -> Self {
+        Self {
+            old_mask,
+            _phantom: std::marker::PhantomData,
+        }
+    }
+
+    /// Set the signal mask to `set` (see `setmask()`) until the guard is dropped.
+    #[inline]
+    pub fn setmask(set: &Sigset) -> io::Result<Self> {
+        setmask(set).map(Self::new)
+    }
+
+    /// Block the signals in `set` (see `block()`) until the guard is dropped.
+    #[inline]
+    pub fn block(set: &Sigset) -> io::Result<Self> {
+        block(set).map(Self::new)
+    }
+
+    /// Unblock the signals in `set` (see `unblock()`) until the guard is dropped.
+    #[inline]
+    pub fn unblock(set: &Sigset) -> io::Result<Self> {
+        unblock(set).map(Self::new)
+    }
+
+    /// Get the signal mask that will be restored when the guard is dropped.
+    #[inline]
+    pub fn old_mask(&self) -> &Sigset {
+        &self.old_mask
+    }
+}
+
+impl Drop for SigmaskGuard {
+    #[inline]
+    fn drop(&mut self) {
+        let _ = setmask(&self.old_mask);
+    }
+}
+
+/// Run `f` with the signals in `set` blocked, then restore the old signal mask.
+///
+/// This only fails if the signals could not be blocked (in which case `f` is not called). The
+/// old mask is restored even if `f` panics. Note that any of the signals in `set` that are
+/// received while `f` is running will be delivered as soon as the old mask is restored.
+///
+/// Example (blocking signals around `fork()`, so that the child can reset its signal handlers
+/// before any of the signals are delivered):
+/// ```
+/// use simple_libc::signal::{Sigset, SIGCHLD, SIGTERM};
+/// use simple_libc::sigmask::with_signals_blocked;
+///
+/// let mut set = Sigset::empty();
+/// set.add(SIGCHLD).unwrap();
+/// set.add(SIGTERM).unwrap();
+///
+/// let pid = with_signals_blocked(&set, || {
+///     let pid = simple_libc::process::fork().unwrap();
+///     if pid == 0 {
+///         // Reset signal handlers, etc.
+///         unsafe { libc::_exit(0) };
+///     }
+///     pid
+/// })
+/// .unwrap();
+/// # use simple_libc::wait::{waitpid, WaitpidOptions, WaitpidSpec};
+/// # waitpid(WaitpidSpec::Pid(pid), WaitpidOptions::empty()).unwrap();
+/// ```
+pub fn with_signals_blocked<T, F: FnOnce() -> T>(set: &Sigset, f: F) -> io::Result<T> {
+    let _guard = SigmaskGuard::block(set)?;
+    Ok(f())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::iter::FromIterator;
+
+    use crate::signal::{SIGUSR1, SIGUSR2};
+
+    #[test]
+    fn test_sigmask() {
+        let orig_mask = getmask().unwrap();
+
+        let mut set = Sigset::empty();
+        set.add(SIGUSR1).unwrap();
+
+        assert_eq!(block(&set).unwrap(), orig_mask);
+        assert!(getmask().unwrap().ismember(SIGUSR1).unwrap());
+
+        {
+            let guard = SigmaskGuard::block(&Sigset::from_iter(vec![SIGUSR2])).unwrap();
+            assert!(guard.old_mask().ismember(SIGUSR1).unwrap());
+            assert!(!guard.old_mask().ismember(SIGUSR2).unwrap());
+            assert!(getmask().unwrap().ismember(SIGUSR2).unwrap());
+
+            {
+                let _guard = SigmaskGuard::unblock(&set).unwrap();
+                assert!(!getmask().unwrap().ismember(SIGUSR1).unwrap());
+                assert!(getmask().unwrap().ismember(SIGUSR2).unwrap());
+            }
+
+            assert!(getmask().unwrap().ismember(SIGUSR1).unwrap());
+        }
+
+        assert!(!getmask().unwrap().ismember(SIGUSR2).unwrap());
+
+        let res = with_signals_blocked(&Sigset::from_iter(vec![SIGUSR2]), || {
+            assert!(getmask().unwrap().ismember(SIGUSR2).unwrap());
+            1
+        });
+        assert_eq!(res.unwrap(), 1);
+        assert!(!getmask().unwrap().ismember(SIGUSR2).unwrap());
+
+        let mut set = orig_mask;
+        set.add(SIGUSR1).unwrap();
+        assert_eq!(setmask(&orig_mask).unwrap(), set);
+        assert_eq!(getmask().unwrap(), orig_mask);
+    }
+}
@@ -1,14 +1,31 @@
-use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::ffi::CStr;
+use std::fmt;
 use std::io;
+use std::iter::FromIterator;
+use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, Not, Sub, SubAssign};
+use std::str::FromStr;
+
+#[cfg(any(feature = "serde", test))]
+use serde::ser::SerializeSeq;
 
 pub use libc::{
-    SIGABRT, SIGALRM, SIGBUS, SIGCHLD, SIGCONT, SIGFPE, SIGHUP, SIGILL, SIGINT, SIGKILL, SIGPIPE,
-    SIGPROF, SIGQUIT, SIGSEGV, SIGSTOP, SIGSYS, SIGTERM, SIGTRAP, SIGTSTP, SIGTTIN, SIGTTOU,
-    SIGURG, SIGUSR1, SIGUSR2, SIGVTALRM, SIGXCPU, SIGXFSZ,
+    SIGABRT, SIGALRM, SIGBUS, SIGCHLD, SIGCONT, SIGFPE, SIGHUP, SIGILL, SIGINT, SIGIO, SIGIOT,
+    SIGKILL, SIGPIPE, SIGPROF, SIGQUIT, SIGSEGV, SIGSTOP, SIGSYS, SIGTERM, SIGTRAP, SIGTSTP,
+    SIGTTIN, SIGTTOU, SIGURG, SIGUSR1, SIGUSR2, SIGVTALRM, SIGWINCH, SIGXCPU, SIGXFSZ,
 };
 
 #[cfg(target_os = "linux")]
-pub use libc::SIGPOLL;
+pub use libc::{SIGPOLL, SIGPWR, SIGSTKFLT};
+
+#[cfg(any(
+    target_os = "freebsd",
+    target_os = "openbsd",
+    target_os = "netbsd",
+    target_os = "dragonfly",
+    target_os = "macos",
+))]
+pub use libc::{SIGEMT, SIGINFO};
 
 use crate::Int;
 
@@ -19,85 +36,142 @@ pub fn can_catch(sig: Int) -> bool {
     }
 }
 
-fn get_signal_name_map() -> &'static HashMap<&'static str, Int> {
-    static mut SIG_NAME_MAP: Option<HashMap<&'static str, Int>> = None;
-    static INIT: std::sync::Once = std::sync::Once::new();
-
-    INIT.call_once(|| {
-        let mut m = HashMap::new();
-
-        m.insert("SIGABRT", SIGABRT);
-        m.insert("SIGALRM", SIGALRM);
-        m.insert("SIGBUS", SIGBUS);
-        m.insert("SIGCHLD", SIGCHLD);
-        m.insert("SIGCONT", SIGCONT);
-        m.insert("SIGFPE", SIGFPE);
-        m.insert("SIGHUP", SIGHUP);
-        m.insert("SIGILL", SIGILL);
-        m.insert("SIGINT", SIGINT);
-        m.insert("SIGKILL", SIGKILL);
-        m.insert("SIGPIPE", SIGPIPE);
-        m.insert("SIGPROF", SIGPROF);
-        m.insert("SIGQUIT", SIGQUIT);
-        m.insert("SIGSEGV", SIGSEGV);
-        m.insert("SIGSTOP", SIGSTOP);
-        m.insert("SIGSYS", SIGSYS);
-        m.insert("SIGTERM", SIGTERM);
-        m.insert("SIGTRAP", SIGTRAP);
-        m.insert("SIGTSTP", SIGTSTP);
-        m.insert("SIGTTIN", SIGTTIN);
-        m.insert("SIGTTOU", SIGTTOU);
-        m.insert("SIGURG", SIGURG);
-        m.insert("SIGUSR1", SIGUSR1);
-        m.insert("SIGUSR2", SIGUSR2);
-        m.insert("SIGVTALRM", SIGVTALRM);
-        m.insert("SIGXCPU", SIGXCPU);
-        m.insert("SIGXFSZ", SIGXFSZ);
-
-        #[cfg(target_os = "linux")]
-        m.insert("SIGPOLL", SIGPOLL);
-
-        unsafe {
-            SIG_NAME_MAP = Some(m);
-        }
-    });
-
-    unsafe { SIG_NAME_MAP.as_ref().unwrap() }
-}
+// The names of all the (non-realtime) signals. If a signal has multiple names, the first one
+// listed is used by sig_to_name(); the rest are aliases.
+static SIGNAL_NAMES: &[(&str, Int)] = &[
+    ("SIGHUP", SIGHUP),
+    ("SIGINT", SIGINT),
+    ("SIGQUIT", SIGQUIT),
+    ("SIGILL", SIGILL),
+    ("SIGTRAP", SIGTRAP),
+    ("SIGABRT", SIGABRT),
+    ("SIGBUS", SIGBUS),
+    ("SIGFPE", SIGFPE),
+    ("SIGKILL", SIGKILL),
+    ("SIGUSR1", SIGUSR1),
+    ("SIGSEGV", SIGSEGV),
+    ("SIGUSR2", SIGUSR2),
+    ("SIGPIPE", SIGPIPE),
+    ("SIGALRM", SIGALRM),
+    ("SIGTERM", SIGTERM),
+    #[cfg(target_os = "linux")]
+    ("SIGSTKFLT", SIGSTKFLT),
+    ("SIGCHLD", SIGCHLD),
+    ("SIGCONT", SIGCONT),
+    ("SIGSTOP", SIGSTOP),
+    ("SIGTSTP", SIGTSTP),
+    ("SIGTTIN", SIGTTIN),
+    ("SIGTTOU", SIGTTOU),
+    ("SIGURG", SIGURG),
+    ("SIGXCPU", SIGXCPU),
+    ("SIGXFSZ", SIGXFSZ),
+    ("SIGVTALRM", SIGVTALRM),
+    ("SIGPROF", SIGPROF),
+    ("SIGWINCH", SIGWINCH),
+    ("SIGIO", SIGIO),
+    #[cfg(target_os = "linux")]
+    ("SIGPWR", SIGPWR),
+    ("SIGSYS", SIGSYS),
+    #[cfg(any(
+        target_os = "freebsd",
+        target_os = "openbsd",
+        target_os = "netbsd",
+        target_os = "dragonfly",
+        target_os = "macos",
+    ))]
+    ("SIGEMT", SIGEMT),
+    #[cfg(any(
+        target_os = "freebsd",
+        target_os = "openbsd",
+        target_os = "netbsd",
+        target_os = "dragonfly",
+        target_os = "macos",
+    ))]
+    ("SIGINFO", SIGINFO),
+    // Aliases
+    ("SIGIOT", SIGIOT),
+    #[cfg(target_os = "linux")]
+    ("SIGPOLL", SIGPOLL),
+    #[cfg(target_os = "linux")]
+    ("SIGCLD", SIGCHLD),
+];
 
+/// Look up a signal by name.
+///
+/// The name is matched case-insensitively, and the "SIG" prefix is optional (so "SIGTERM",
+/// "sigterm", and "TERM" are all accepted). Aliases such as "SIGIOT" are also recognized.
+///
+/// On platforms with realtime signals, names of the form "SIGRTMIN+n" and "SIGRTMAX-n" (as well
+/// as plain "SIGRTMIN" and "SIGRTMAX") are also accepted.
 pub fn sig_from_name(name: &str) -> Option<Int> {
+    let name = name.to_ascii_uppercase();
+    let name = name.strip_prefix("SIG").unwrap_or(&name);
+
     #[cfg(any(target_os = "linux", target_os = "freebsd", target_os = "netbsd"))]
     {
-        if name.starts_with("SIGRTMIN+") {
-            if let Ok(incr) = name[9..].parse::<Int>() {
-                if let Ok(range) = get_rtsig_range() {
-                    let sig = range.start() + incr;
+        if name.starts_with("RTMIN") || name.starts_with("RTMAX") {
+            let range = get_rtsig_range().ok()?;
 
-                    if range.contains(&sig) {
-                        return Some(sig);
-                    }
+            let sig = match (&name[..5], &name[5..]) {
+                ("RTMIN", "") => *range.start(),
+                ("RTMAX", "") => *range.end(),
+                ("RTMIN", offset) if offset.starts_with('+') => range
+                    .start()
+                    .checked_add(offset[1..].parse::<Int>().ok()?)?,
+                ("RTMAX", offset) if offset.starts_with('-') => {
+                    range.end().checked_sub(offset[1..].parse::<Int>().ok()?)?
                 }
-            }
+                _ => return None,
+            };
 
-            return None;
+            return if range.contains(&sig) {
+                Some(sig)
+            } else {
+                None
+            };
         }
+    }
 
-        if name.starts_with("SIGRTMAX-") {
-            if let Ok(decr) = name[9..].parse::<Int>() {
-                if let Ok(range) = get_rtsig_range() {
-                    let sig = range.end() - decr;
+    SIGNAL_NAMES
+        .iter()
+        .find(|(sig_name, _)| &sig_name[3..] == name)
+        .map(|&(_, sig)| sig)
+}
 
-                    if range.contains(&sig) {
-                        return Some(sig);
-                    }
-                }
-            }
+/// Get the name of the given signal (for example, `SIGTERM`).
+///
+/// Realtime signals are named relative to `SIGRTMIN` or `SIGRTMAX` (whichever is closer), so
+/// that the result can be passed to `sig_from_name()`. For signals with aliases, the most
+/// common name is used (for example, `SIGABRT` rather than `SIGIOT`).
+pub fn sig_to_name(sig: Int) -> Option<String> {
+    #[cfg(any(target_os = "linux", target_os = "freebsd", target_os = "netbsd"))]
+    {
+        if let Ok(range) = get_rtsig_range() {
+            if range.contains(&sig) {
+                let incr = sig - range.start();
+                let decr = range.end() - sig;
 
-            return None;
+                return Some(if incr <= decr {
+                    format!("SIGRTMIN+{}", incr)
+                } else {
+                    format!("SIGRTMAX-{}", decr)
+                });
+            }
         }
     }
 
-    get_signal_name_map().get(name).copied()
+    SIGNAL_NAMES
+        .iter()
+        .find(|&&(_, s)| s == sig)
+        .map(|(name, _)| name.to_string())
+}
+
+/// Get a human-readable description of the given signal (for example, "Terminated" for
+/// `SIGTERM`), as returned by `strsignal()`.
+pub fn strsignal(sig: Int) -> String {
+    unsafe { CStr::from_ptr(libc::strsignal(sig)) }
+        .to_string_lossy()
+        .into_owned()
 }
 
 #[cfg(any(target_os = "linux", target_os = "freebsd", target_os = "netbsd"))]
@@ -121,6 +195,278 @@ pub fn get_rtsig_range() -> io::Result<std::ops::RangeInclusive<Int>> {
     Ok(sigrtmin..=sigrtmax)
 }
 
+/// A typed signal.
+///
+/// This converts to and from signal numbers with `Int::from()` and `Signal::try_from()`, and to
+/// and from names with `Display` and `FromStr` (which use `sig_to_name()` and
+/// `sig_from_name()`). With the `serde` feature, it is serialized as its name, and it can be
+/// deserialized from either a name or a number.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum Signal {
+    Hup,
+    Int,
+    Quit,
+    Ill,
+    Trap,
+    Abrt,
+    Bus,
+    Fpe,
+    Kill,
+    Usr1,
+    Segv,
+    Usr2,
+    Pipe,
+    Alrm,
+    Term,
+    #[cfg(target_os = "linux")]
+    Stkflt,
+    Chld,
+    Cont,
+    Stop,
+    Tstp,
+    Ttin,
+    Ttou,
+    Urg,
+    Xcpu,
+    Xfsz,
+    Vtalrm,
+    Prof,
+    Winch,
+    Io,
+    #[cfg(target_os = "linux")]
+    Pwr,
+    Sys,
+    #[cfg(any(
+        target_os = "freebsd",
+        target_os = "openbsd",
+        target_os = "netbsd",
+        target_os = "dragonfly",
+        target_os = "macos",
+    ))]
+    Emt,
+    #[cfg(any(
+        target_os = "freebsd",
+        target_os = "openbsd",
+        target_os = "netbsd",
+        target_os = "dragonfly",
+        target_os = "macos",
+    ))]
+    Info,
+    /// A realtime signal. The value is the signal number (not the offset from `SIGRTMIN`).
+    #[cfg(any(target_os = "linux", target_os = "freebsd", target_os = "netbsd"))]
+    Realtime(Int),
+}
+
+impl Signal {
+    /// Get the signal number.
+    pub fn as_raw(self) -> Int {
+        match self {
+            Self::Hup => SIGHUP,
+            Self::Int => SIGINT,
+            Self::Quit => SIGQUIT,
+            Self::Ill => SIGILL,
+            Self::Trap => SIGTRAP,
+            Self::Abrt => SIGABRT,
+            Self::Bus => SIGBUS,
+            Self::Fpe => SIGFPE,
+            Self::Kill => SIGKILL,
+            Self::Usr1 => SIGUSR1,
+            Self::Segv => SIGSEGV,
+            Self::Usr2 => SIGUSR2,
+            Self::Pipe => SIGPIPE,
+            Self::Alrm => SIGALRM,
+            Self::Term => SIGTERM,
+            #[cfg(target_os = "linux")]
+            Self::Stkflt => SIGSTKFLT,
+            Self::Chld => SIGCHLD,
+            Self::Cont => SIGCONT,
+            Self::Stop => SIGSTOP,
+            Self::Tstp => SIGTSTP,
+            Self::Ttin => SIGTTIN,
+            Self::Ttou => SIGTTOU,
+            Self::Urg => SIGURG,
+            Self::Xcpu => SIGXCPU,
+            Self::Xfsz => SIGXFSZ,
+            Self::Vtalrm => SIGVTALRM,
+            Self::Prof => SIGPROF,
+            Self::Winch => SIGWINCH,
+            Self::Io => SIGIO,
+            #[cfg(target_os = "linux")]
+            Self::Pwr => SIGPWR,
+            Self::Sys => SIGSYS,
+            #[cfg(any(
+                target_os = "freebsd",
+                target_os = "openbsd",
+                target_os = "netbsd",
+                target_os = "dragonfly",
+                target_os = "macos",
+            ))]
+            Self::Emt => SIGEMT,
+            #[cfg(any(
+                target_os = "freebsd",
+                target_os = "openbsd",
+                target_os = "netbsd",
+                target_os = "dragonfly",
+                target_os = "macos",
+            ))]
+            Self::Info => SIGINFO,
+            #[cfg(any(target_os = "linux", target_os = "freebsd", target_os = "netbsd"))]
+            Self::Realtime(sig) => sig,
+        }
+    }
+
+    /// Get the `Signal` for the given signal number, failing with `EINVAL` if it is not a valid
+    /// signal.
+    pub fn from_raw(sig: Int) -> io::Result<Self> {
+        Ok(match sig {
+            SIGHUP => Self::Hup,
+            SIGINT => Self::Int,
+            SIGQUIT => Self::Quit,
+            SIGILL => Self::Ill,
+            SIGTRAP => Self::Trap,
+            SIGABRT => Self::Abrt,
+            SIGBUS => Self::Bus,
+            SIGFPE => Self::Fpe,
+            SIGKILL => Self::Kill,
+            SIGUSR1 => Self::Usr1,
+            SIGSEGV => Self::Segv,
+            SIGUSR2 => Self::Usr2,
+            SIGPIPE => Self::Pipe,
+            SIGALRM => Self::Alrm,
+            SIGTERM => Self::Term,
+            #[cfg(target_os = "linux")]
+            SIGSTKFLT => Self::Stkflt,
+            SIGCHLD => Self::Chld,
+            SIGCONT => Self::Cont,
+            SIGSTOP => Self::Stop,
+            SIGTSTP => Self::Tstp,
+            SIGTTIN => Self::Ttin,
+            SIGTTOU => Self::Ttou,
+            SIGURG => Self::Urg,
+            SIGXCPU => Self::Xcpu,
+            SIGXFSZ => Self::Xfsz,
+            SIGVTALRM => Self::Vtalrm,
+            SIGPROF => Self::Prof,
+            SIGWINCH => Self::Winch,
+            SIGIO => Self::Io,
+            #[cfg(target_os = "linux")]
+            SIGPWR => Self::Pwr,
+            SIGSYS => Self::Sys,
+            #[cfg(any(
+                target_os = "freebsd",
+                target_os = "openbsd",
+                target_os = "netbsd",
+                target_os = "dragonfly",
+                target_os = "macos",
+            ))]
+            SIGEMT => Self::Emt,
+            #[cfg(any(
+                target_os = "freebsd",
+                target_os = "openbsd",
+                target_os = "netbsd",
+                target_os = "dragonfly",
+                target_os = "macos",
+            ))]
+            SIGINFO => Self::Info,
+            _ => {
+                #[cfg(any(target_os = "linux", target_os = "freebsd", target_os = "netbsd"))]
+                {
+                    if get_rtsig_range()?.contains(&sig) {
+                        return Ok(Self::Realtime(sig));
+                    }
+                }
+
+                return Err(io::Error::from_raw_os_error(libc::EINVAL));
+            }
+        })
+    }
+
+    /// Get a human-readable description of this signal; see `strsignal()`.
+    #[inline]
+    pub fn description(self) -> String {
+        strsignal(self.as_raw())
+    }
+}
+
+impl From<Signal> for Int {
+    #[inline]
+    fn from(sig: Signal) -> Int {
+        sig.as_raw()
+    }
+}
+
+impl TryFrom<Int> for Signal {
+    type Error = io::Error;
+
+    #[inline]
+    fn try_from(sig: Int) -> io::Result<Self> {
+        Self::from_raw(sig)
+    }
+}
+
+impl fmt::Display for Signal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match sig_to_name(self.as_raw()) {
+            Some(name) => f.write_str(&name),
+            None => write!(f, "{}", self.as_raw()),
+        }
+    }
+}
+
+impl FromStr for Signal {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> io::Result<Self> {
+        match sig_from_name(s) {
+            Some(sig) => Self::from_raw(sig),
+            None => Err(io::Error::from_raw_os_error(libc::EINVAL)),
+        }
+    }
+}
+
+#[cfg(any(feature = "serde", test))]
+impl serde::Serialize for Signal {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(any(feature = "serde", test))]
+impl<'d> serde::Deserialize<'d> for Signal {
+    fn deserialize<D: serde::Deserializer<'d>>(deserializer: D) -> Result<Self, D::Error> {
+        struct SignalVisitor;
+
+        impl<'d> serde::de::Visitor<'d> for SignalVisitor {
+            type Value = Signal;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a signal name or number")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, s: &str) -> Result<Signal, E> {
+                s.parse()
+                    .map_err(|_| E::invalid_value(serde::de::Unexpected::Str(s), &self))
+            }
+
+            fn visit_i64<E: serde::de::Error>(self, sig: i64) -> Result<Signal, E> {
+                Int::try_from(sig)
+                    .ok()
+                    .and_then(|sig| Signal::from_raw(sig).ok())
+                    .ok_or_else(|| E::invalid_value(serde::de::Unexpected::Signed(sig), &self))
+            }
+
+            fn visit_u64<E: serde::de::Error>(self, sig: u64) -> Result<Signal, E> {
+                Int::try_from(sig)
+                    .ok()
+                    .and_then(|sig| Signal::from_raw(sig).ok())
+                    .ok_or_else(|| E::invalid_value(serde::de::Unexpected::Unsigned(sig), &self))
+            }
+        }
+
+        deserializer.deserialize_any(SignalVisitor)
+    }
+}
+
 #[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
 pub struct Sigset {
     set: libc::sigset_t,
@@ -178,6 +524,329 @@ impl Sigset {
     pub fn into_raw_set(self) -> libc::sigset_t {
         self.set
     }
+
+    /// Check whether this set is empty.
+    pub fn is_empty(&self) -> bool {
+        #[cfg(target_os = "linux")]
+        return unsafe { crate::externs::sigisemptyset(&self.set) } != 0;
+
+        #[cfg(not(target_os = "linux"))]
+        return self.iter().next().is_none();
+    }
+
+    /// Iterate over the signals in this set, in increasing order.
+    ///
+    /// This includes realtime signals.
+    #[inline]
+    pub fn iter(&self) -> SigsetIter {
+        SigsetIter { set: *self, sig: 1 }
+    }
+
+    /// Parse a signal mask in the hexadecimal format used by the kernel (for example, in the
+    /// `SigBlk` field of `/proc/<pid>/status`).
+    ///
+    /// Bit `n - 1` of the mask corresponds to signal `n`.
+    #[cfg(target_os = "linux")]
+    pub fn from_kernel_mask(s: &str) -> io::Result<Self> {
+        let bits = u64::from_str_radix(s.trim(), 16)
+            .map_err(|_| io::Error::from_raw_os_error(libc::EINVAL))?;
+
+        let mut set = Self::empty();
+        for i in 0..64 {
+            if bits & (1 << i) != 0 {
+                set.set_raw_bit(i);
+            }
+        }
+
+        Ok(set)
+    }
+
+    /// Format this set as a signal mask in the hexadecimal format used by the kernel; see
+    /// `from_kernel_mask()`.
+    #[cfg(target_os = "linux")]
+    pub fn to_kernel_mask(&self) -> String {
+        let mut bits = 0u64;
+        for sig in self.iter() {
+            if sig <= 64 {
+                bits |= 1 << (sig - 1);
+            }
+        }
+
+        format!("{:016x}", bits)
+    }
+
+    // sigaddset() refuses to add the signals that glibc reserves for internal use, but those can
+    // still show up in masks reported by the kernel. So we set the bits directly (the layout of
+    // sigset_t matches the kernel's on Linux).
+    #[cfg(target_os = "linux")]
+    fn set_raw_bit(&mut self, bit: usize) {
+        let word_bits = 8 * std::mem::size_of::<crate::Ulong>();
+        self.raw_words_mut()[bit / word_bits] |= 1 << (bit % word_bits);
+    }
+
+    // Likewise, sigdelset() refuses to remove those signals
+    #[cfg(target_os = "linux")]
+    fn clear_raw_bit(&mut self, bit: usize) {
+        let word_bits = 8 * std::mem::size_of::<crate::Ulong>();
+        self.raw_words_mut()[bit / word_bits] &= !(1 << (bit % word_bits));
+    }
+
+    #[cfg(target_os = "linux")]
+    fn raw_words_mut(&mut self) -> &mut [crate::Ulong] {
+        unsafe {
+            std::slice::from_raw_parts_mut(
+                &mut self.set as *mut libc::sigset_t as *mut crate::Ulong,
+                std::mem::size_of::<libc::sigset_t>() / std::mem::size_of::<crate::Ulong>(),
+            )
+        }
+    }
+
+    // glibc only copies the kernel's portion of the mask (the first 64 signals) into some
+    // sigset_ts it returns (such as the old action from sigaction()), leaving the rest of the
+    // structure uninitialized. This clears the rest so that comparisons work properly.
+    #[cfg(target_os = "linux")]
+    pub(crate) fn clear_unused_bits(&mut self) {
+        let bytes = unsafe {
+            std::slice::from_raw_parts_mut(
+                &mut self.set as *mut libc::sigset_t as *mut u8,
+                std::mem::size_of::<libc::sigset_t>(),
+            )
+        };
+
+        for b in bytes[64 / 8..].iter_mut() {
+            *b = 0;
+        }
+    }
+}
+
+/// An iterator over the signals in a `Sigset`.
+#[derive(Clone, Debug)]
+pub struct SigsetIter {
+    set: Sigset,
+    sig: Int,
+}
+
+impl Iterator for SigsetIter {
+    type Item = Int;
+
+    fn next(&mut self) -> Option<Int> {
+        loop {
+            // sigismember() fails with EINVAL once we go past the last valid signal
+            let is_member = self.set.ismember(self.sig).ok()?;
+            self.sig += 1;
+
+            if is_member {
+                return Some(self.sig - 1);
+            }
+        }
+    }
+}
+
+impl IntoIterator for Sigset {
+    type Item = Int;
+    type IntoIter = SigsetIter;
+
+    #[inline]
+    fn into_iter(self) -> SigsetIter {
+        SigsetIter { set: self, sig: 1 }
+    }
+}
+
+impl IntoIterator for &Sigset {
+    type Item = Int;
+    type IntoIter = SigsetIter;
+
+    #[inline]
+    fn into_iter(self) -> SigsetIter {
+        self.iter()
+    }
+}
+
+impl Extend<Int> for Sigset {
+    /// Add all of the given signals to this set.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any of the signals are invalid.
+    fn extend<I: IntoIterator<Item = Int>>(&mut self, iter: I) {
+        for sig in iter {
+            self.add(sig).expect("Invalid signal");
+        }
+    }
+}
+
+impl FromIterator<Int> for Sigset {
+    /// Create a set containing all of the given signals.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any of the signals are invalid.
+    fn from_iter<I: IntoIterator<Item = Int>>(iter: I) -> Self {
+        let mut set = Self::empty();
+        set.extend(iter);
+        set
+    }
+}
+
+impl FromIterator<Signal> for Sigset {
+    #[inline]
+    fn from_iter<I: IntoIterator<Item = Signal>>(iter: I) -> Self {
+        iter.into_iter().map(Signal::as_raw).collect()
+    }
+}
+
+impl BitOr for Sigset {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        #[cfg(target_os = "linux")]
+        {
+            let mut res = Self::empty();
+            unsafe {
+                crate::externs::sigorset(&mut res.set, &self.set, &rhs.set);
+            }
+            res
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        {
+            let mut res = self;
+            res.extend(rhs.iter());
+            res
+        }
+    }
+}
+
+impl BitAnd for Sigset {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self {
+        #[cfg(target_os = "linux")]
+        {
+            let mut res = Self::empty();
+            unsafe {
+                crate::externs::sigandset(&mut res.set, &self.set, &rhs.set);
+            }
+            res
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        {
+            self.iter()
+                .filter(|&sig| rhs.ismember(sig).unwrap_or(false))
+                .collect()
+        }
+    }
+}
+
+impl Sub for Sigset {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        let mut res = self;
+        for sig in rhs.iter() {
+            #[cfg(target_os = "linux")]
+            res.clear_raw_bit(sig as usize - 1);
+
+            #[cfg(not(target_os = "linux"))]
+            let _ = res.del(sig);
+        }
+        res
+    }
+}
+
+impl Not for Sigset {
+    type Output = Self;
+
+    /// Get the complement of this set.
+    ///
+    /// Note that this is computed relative to `Sigset::full()`, so the result will not include
+    /// any signals that the C library reserves for internal use.
+    #[inline]
+    fn not(self) -> Self {
+        Self::full() - self
+    }
+}
+
+impl BitOrAssign for Sigset {
+    #[inline]
+    fn bitor_assign(&mut self, rhs: Self) {
+        *self = *self | rhs;
+    }
+}
+
+impl BitAndAssign for Sigset {
+    #[inline]
+    fn bitand_assign(&mut self, rhs: Self) {
+        *self = *self & rhs;
+    }
+}
+
+impl SubAssign for Sigset {
+    #[inline]
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl fmt::Display for Sigset {
+    /// Format this set as a comma-separated list of signal names (for example,
+    /// "SIGINT, SIGTERM").
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, sig) in self.iter().enumerate() {
+            if i != 0 {
+                f.write_str(", ")?;
+            }
+
+            match sig_to_name(sig) {
+                Some(name) => f.write_str(&name)?,
+                None => write!(f, "{}", sig)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(any(feature = "serde", test))]
+pub fn serialize_sigset_seq<S: serde::Serializer>(
+    set: &Sigset,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let names: Vec<String> = set
+        .iter()
+        .map(|sig| sig_to_name(sig).unwrap_or_else(|| sig.to_string()))
+        .collect();
+
+    let mut seq = serializer.serialize_seq(Some(names.len()))?;
+    for name in names {
+        seq.serialize_element(&name)?;
+    }
+    seq.end()
+}
+
+#[cfg(any(feature = "serde", test))]
+pub fn deserialize_sigset_seq<'d, D: serde::Deserializer<'d>>(
+    deserializer: D,
+) -> Result<Sigset, D::Error> {
+    let signals: Vec<Signal> = serde::Deserialize::deserialize(deserializer)?;
+    Ok(signals.into_iter().collect())
+}
+
+#[cfg(all(target_os = "linux", any(feature = "serde", test)))]
+pub fn serialize_sigset_kernel_mask<S: serde::Serializer>(
+    set: &Sigset,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&set.to_kernel_mask())
+}
+
+#[cfg(all(target_os = "linux", any(feature = "serde", test)))]
+pub fn deserialize_sigset_kernel_mask<'d, D: serde::Deserializer<'d>>(
+    deserializer: D,
+) -> Result<Sigset, D::Error> {
+    let s: String = serde::Deserialize::deserialize(deserializer)?;
+    Sigset::from_kernel_mask(&s).map_err(|_| serde::de::Error::custom("Invalid signal mask"))
 }
 
 impl AsRef<libc::sigset_t> for Sigset {
@@ -220,6 +889,22 @@ mod tests {
         #[cfg(target_os = "linux")]
         assert_eq!(sig_from_name("SIGPOLL"), Some(SIGPOLL));
 
+        // Case-insensitive, with or without the prefix
+        assert_eq!(sig_from_name("sigterm"), Some(SIGTERM));
+        assert_eq!(sig_from_name("TERM"), Some(SIGTERM));
+        assert_eq!(sig_from_name("Term"), Some(SIGTERM));
+        assert_eq!(sig_from_name("SIG"), None);
+        assert_eq!(sig_from_name(""), None);
+
+        // Aliases
+        assert_eq!(sig_from_name("SIGIOT"), Some(SIGABRT));
+        assert_eq!(sig_from_name("IO"), Some(SIGIO));
+        #[cfg(target_os = "linux")]
+        {
+            assert_eq!(sig_from_name("POLL"), Some(SIGIO));
+            assert_eq!(sig_from_name("SIGCLD"), Some(SIGCHLD));
+        }
+
         #[cfg(any(target_os = "linux", target_os = "freebsd", target_os = "netbsd"))]
         {
             let (sigrtmin, sigrtmax) = get_rtsig_minmax().unwrap();
@@ -232,6 +917,11 @@ mod tests {
             );
 
             assert_eq!(sig_from_name("SIGRTMAX-0"), Some(sigrtmax));
+            assert_eq!(sig_from_name("rtmin+1"), Some(sigrtmin + 1));
+            assert_eq!(sig_from_name("SIGRTMIN"), Some(sigrtmin));
+            assert_eq!(sig_from_name("RTMAX"), Some(sigrtmax));
+            assert_eq!(sig_from_name("SIGRTMIN-1"), None);
+            assert_eq!(sig_from_name("SIGRTMAX+1"), None);
             assert_eq!(sig_from_name("SIGRTMAX-1"), Some(sigrtmax - 1));
             assert_eq!(
                 sig_from_name(&("SIGRTMAX-".to_string() + &(sigrtmax - sigrtmin).to_string())),
@@ -256,6 +946,10 @@ mod tests {
                 sig_from_name(&("SIGRTMAX-".to_string() + &(sigrtmax - sigrtmin + 1).to_string())),
                 None,
             );
+
+            // Overflow
+            assert_eq!(sig_from_name("SIGRTMIN+2147483647"), None);
+            assert_eq!(sig_from_name("SIGRTMAX--2147483648"), None);
         }
 
         #[cfg(not(any(target_os = "linux", target_os = "freebsd", target_os = "netbsd")))]
@@ -286,10 +980,279 @@ mod tests {
         assert!(set.ismember(SIGTERM).unwrap());
     }
 
+    #[test]
+    fn test_sigset_iter() {
+        let mut set = Sigset::empty();
+        assert!(set.is_empty());
+        assert_eq!(set.iter().next(), None);
+
+        set.add(SIGTERM).unwrap();
+        set.add(SIGINT).unwrap();
+        assert!(!set.is_empty());
+        assert_eq!(set.iter().collect::<Vec<_>>(), vec![SIGINT, SIGTERM]);
+        assert_eq!(set.into_iter().collect::<Vec<_>>(), vec![SIGINT, SIGTERM]);
+
+        assert_eq!(Sigset::from_iter(vec![SIGTERM, SIGINT]), set);
+        assert_eq!(
+            vec![Signal::Int, Signal::Term]
+                .into_iter()
+                .collect::<Sigset>(),
+            set
+        );
+
+        let full = Sigset::full();
+        assert!(!full.is_empty());
+        for &(_, sig) in SIGNAL_NAMES {
+            assert!(full.iter().any(|s| s == sig));
+        }
+
+        #[cfg(any(target_os = "linux", target_os = "freebsd", target_os = "netbsd"))]
+        {
+            let (sigrtmin, sigrtmax) = get_rtsig_minmax().unwrap();
+
+            let set = Sigset::from_iter(vec![sigrtmin, sigrtmax]);
+            assert_eq!(set.iter().collect::<Vec<_>>(), vec![sigrtmin, sigrtmax]);
+
+            assert_eq!(full.iter().last(), Some(sigrtmax));
+        }
+    }
+
+    #[test]
+    fn test_sigset_ops() {
+        let a = Sigset::from_iter(vec![SIGINT, SIGTERM]);
+        let b = Sigset::from_iter(vec![SIGTERM, SIGHUP]);
+
+        assert_eq!(a | b, Sigset::from_iter(vec![SIGINT, SIGTERM, SIGHUP]));
+        assert_eq!(a & b, Sigset::from_iter(vec![SIGTERM]));
+        assert_eq!(a - b, Sigset::from_iter(vec![SIGINT]));
+
+        let not_a = !a;
+        assert!(!not_a.ismember(SIGINT).unwrap());
+        assert!(!not_a.ismember(SIGTERM).unwrap());
+        assert!(not_a.ismember(SIGHUP).unwrap());
+        assert_eq!(!not_a, a);
+
+        assert_eq!(!Sigset::full(), Sigset::empty());
+        assert_eq!(!Sigset::empty(), Sigset::full());
+
+        // Signals reserved by the C library can be removed too
+        #[cfg(target_os = "linux")]
+        {
+            let reserved = Sigset::from_kernel_mask("180000000").unwrap();
+            assert_eq!(reserved - reserved, Sigset::empty());
+            assert_eq!((reserved | a) - reserved, a);
+            assert_eq!(!reserved & reserved, Sigset::empty());
+        }
+
+        let mut c = a;
+        c |= b;
+        assert_eq!(c, a | b);
+        c &= b;
+        assert_eq!(c, b);
+        c -= a;
+        assert_eq!(c, Sigset::from_iter(vec![SIGHUP]));
+    }
+
+    #[test]
+    fn test_sigset_display() {
+        assert_eq!(Sigset::empty().to_string(), "");
+        assert_eq!(Sigset::from_iter(vec![SIGTERM]).to_string(), "SIGTERM");
+        assert_eq!(
+            Sigset::from_iter(vec![SIGTERM, SIGINT]).to_string(),
+            "SIGINT, SIGTERM"
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_sigset_kernel_mask() {
+        assert_eq!(Sigset::empty().to_kernel_mask(), "0000000000000000");
+        assert_eq!(
+            Sigset::from_iter(vec![SIGHUP, SIGTERM]).to_kernel_mask(),
+            format!("{:016x}", (1u64 << (SIGHUP - 1)) | (1 << (SIGTERM - 1)))
+        );
+
+        assert_eq!(
+            Sigset::from_kernel_mask("0000000000004001").unwrap(),
+            Sigset::from_iter(vec![SIGHUP, SIGTERM])
+        );
+        assert_eq!(
+            Sigset::from_kernel_mask("fffffffe7ffbfeff")
+                .unwrap()
+                .to_kernel_mask(),
+            "fffffffe7ffbfeff"
+        );
+        // Signals reserved by the C library are preserved
+        assert_eq!(
+            Sigset::from_kernel_mask("0000000180000000")
+                .unwrap()
+                .iter()
+                .collect::<Vec<_>>(),
+            vec![32, 33]
+        );
+
+        assert!(Sigset::from_kernel_mask("").is_err());
+        assert!(Sigset::from_kernel_mask("xyz").is_err());
+        assert!(Sigset::from_kernel_mask("10000000000000000").is_err());
+
+        // Check against our own blocked mask
+        let status = std::fs::read_to_string("/proc/thread-self/status").unwrap();
+        let sigblk = status
+            .lines()
+            .find(|line| line.starts_with("SigBlk:"))
+            .unwrap()[7..]
+            .trim();
+        assert_eq!(
+            Sigset::from_kernel_mask(sigblk).unwrap(),
+            crate::sigmask::getmask().unwrap()
+        );
+        assert_eq!(crate::sigmask::getmask().unwrap().to_kernel_mask(), sigblk);
+    }
+
+    #[test]
+    fn test_sigset_serde() {
+        use serde_test::{assert_de_tokens_error, assert_tokens, Token};
+
+        #[derive(Debug, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+        struct SerSet {
+            #[serde(
+                serialize_with = "serialize_sigset_seq",
+                deserialize_with = "deserialize_sigset_seq"
+            )]
+            set: Sigset,
+        }
+
+        assert_tokens(
+            &SerSet {
+                set: Sigset::from_iter(vec![SIGINT, SIGTERM]),
+            },
+            &[
+                Token::Struct {
+                    name: "SerSet",
+                    len: 1,
+                },
+                Token::Str("set"),
+                Token::Seq { len: Some(2) },
+                Token::Str("SIGINT"),
+                Token::Str("SIGTERM"),
+                Token::SeqEnd,
+                Token::StructEnd,
+            ],
+        );
+
+        assert_de_tokens_error::<SerSet>(
+            &[
+                Token::Struct {
+                    name: "SerSet",
+                    len: 1,
+                },
+                Token::Str("set"),
+                Token::Seq { len: Some(1) },
+                Token::Str("BAD"),
+            ],
+            "invalid value: string \"BAD\", expected a signal name or number",
+        );
+    }
+
     #[cfg(any(target_os = "linux", target_os = "freebsd", target_os = "netbsd"))]
     #[test]
     fn test_get_rtsig_minmax_range() {
         get_rtsig_minmax().unwrap();
         get_rtsig_range().unwrap();
     }
+
+    #[test]
+    fn test_sig_to_name() {
+        assert_eq!(sig_to_name(SIGTERM).unwrap(), "SIGTERM");
+        assert_eq!(sig_to_name(SIGABRT).unwrap(), "SIGABRT");
+        assert_eq!(sig_to_name(SIGIO).unwrap(), "SIGIO");
+        assert_eq!(sig_to_name(SIGCHLD).unwrap(), "SIGCHLD");
+        assert_eq!(sig_to_name(0), None);
+        assert_eq!(sig_to_name(-1), None);
+
+        for &(name, sig) in SIGNAL_NAMES {
+            assert_eq!(sig_from_name(name), Some(sig));
+            assert_eq!(sig_from_name(&sig_to_name(sig).unwrap()), Some(sig));
+        }
+
+        #[cfg(any(target_os = "linux", target_os = "freebsd", target_os = "netbsd"))]
+        {
+            let (sigrtmin, sigrtmax) = get_rtsig_minmax().unwrap();
+
+            assert_eq!(sig_to_name(sigrtmin).unwrap(), "SIGRTMIN+0");
+            assert_eq!(sig_to_name(sigrtmin + 1).unwrap(), "SIGRTMIN+1");
+            assert_eq!(sig_to_name(sigrtmax).unwrap(), "SIGRTMAX-0");
+            assert_eq!(sig_to_name(sigrtmax - 1).unwrap(), "SIGRTMAX-1");
+
+            for sig in sigrtmin..=sigrtmax {
+                assert_eq!(sig_from_name(&sig_to_name(sig).unwrap()), Some(sig));
+            }
+        }
+    }
+
+    #[test]
+    fn test_strsignal() {
+        assert_eq!(strsignal(SIGKILL), "Killed");
+        assert!(!strsignal(SIGTERM).is_empty());
+    }
+
+    #[test]
+    fn test_signal() {
+        assert_eq!(Signal::from_raw(SIGTERM).unwrap(), Signal::Term);
+        assert_eq!(Signal::try_from(SIGIOT).unwrap(), Signal::Abrt);
+        assert_eq!(Int::from(Signal::Term), SIGTERM);
+        assert_eq!(
+            Signal::from_raw(0).unwrap_err().raw_os_error(),
+            Some(libc::EINVAL)
+        );
+
+        for &(_, sig) in SIGNAL_NAMES {
+            assert_eq!(Signal::from_raw(sig).unwrap().as_raw(), sig);
+        }
+
+        assert_eq!(Signal::Term.to_string(), "SIGTERM");
+        assert_eq!("term".parse::<Signal>().unwrap(), Signal::Term);
+        assert_eq!("SIGCHLD".parse::<Signal>().unwrap(), Signal::Chld);
+        assert_eq!(
+            "BAD".parse::<Signal>().unwrap_err().raw_os_error(),
+            Some(libc::EINVAL)
+        );
+
+        assert_eq!(Signal::Kill.description(), "Killed");
+
+        #[cfg(any(target_os = "linux", target_os = "freebsd", target_os = "netbsd"))]
+        {
+            let (sigrtmin, _) = get_rtsig_minmax().unwrap();
+
+            assert_eq!(
+                Signal::from_raw(sigrtmin + 1).unwrap(),
+                Signal::Realtime(sigrtmin + 1)
+            );
+            assert_eq!(Signal::Realtime(sigrtmin + 1).as_raw(), sigrtmin + 1);
+            assert_eq!(Signal::Realtime(sigrtmin + 1).to_string(), "SIGRTMIN+1");
+            assert_eq!(
+                "RTMIN+1".parse::<Signal>().unwrap(),
+                Signal::Realtime(sigrtmin + 1)
+            );
+        }
+    }
+
+    #[test]
+    fn test_signal_serde() {
+        use serde_test::{assert_de_tokens, assert_de_tokens_error, assert_tokens, Token};
+
+        assert_tokens(&Signal::Term, &[Token::Str("SIGTERM")]);
+        assert_de_tokens(&Signal::Term, &[Token::Str("TERM")]);
+        assert_de_tokens(&Signal::Term, &[Token::I32(SIGTERM)]);
+        assert_de_tokens(&Signal::Term, &[Token::U64(SIGTERM as u64)]);
+
+        assert_de_tokens_error::<Signal>(
+            &[Token::Str("BAD")],
+            "invalid value: string \"BAD\", expected a signal name or number",
+        );
+        assert_de_tokens_error::<Signal>(
+            &[Token::I32(0)],
+            "invalid value: integer `0`, expected a signal name or number",
+        );
+    }
 }
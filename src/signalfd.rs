@@ -2,12 +2,18 @@ use std::io;
 use std::os::unix::prelude::*;
 
 use crate::error;
+use crate::siginfo::SigInfo;
 use crate::signal::Sigset;
 use crate::Int;
 
 #[derive(Debug)]
 pub struct SignalFd {
     fd: Int,
+    // The signals that we blocked (and that need to be unblocked when this is dropped), if this
+    // was created with new_blocking()
+    blocked: Option<Sigset>,
+    // Whether the file descriptor was created in nonblocking mode (required by drain())
+    nonblock: bool,
 }
 
 impl SignalFd {
@@ -17,27 +23,131 @@ impl SignalFd {
             flags |= libc::SFD_NONBLOCK;
         }
 
-        let fd = error::convert_ret(unsafe { libc::signalfd(-1, &mask.raw_set(), flags) })?;
+        let fd = error::convert_ret(unsafe { libc::signalfd(-1, mask.as_ref(), flags) })?;
 
-        Ok(SignalFd { fd })
+        Ok(SignalFd {
+            fd,
+            blocked: None,
+            nonblock,
+        })
+    }
+
+    /// Create a new `SignalFd`, and also block the signals in `mask` for the current thread
+    /// (with `sigmask::block()`).
+    ///
+    /// When the `SignalFd` is dropped, any signals that were blocked by this function (or by
+    /// `set_mask()`) will be unblocked again. Signals that were already blocked are left
+    /// alone.
+    ///
+    /// Note: Since signal masks are per-thread, the `SignalFd` should be dropped on the same
+    /// thread that created it.
+    pub fn new_blocking(mask: &Sigset, nonblock: bool) -> io::Result<SignalFd> {
+        let old_mask = crate::sigmask::block(mask)?;
+        let blocked = *mask - old_mask;
+
+        match Self::new(mask, nonblock) {
+            Ok(mut sigfd) => {
+                sigfd.blocked = Some(blocked);
+                Ok(sigfd)
+            }
+            Err(e) => {
+                crate::sigmask::unblock(&blocked)?;
+                Err(e)
+            }
+        }
+    }
+
+    /// Change the set of signals that this `SignalFd` will accept.
+    ///
+    /// This reuses the existing file descriptor. If this `SignalFd` was created with
+    /// `new_blocking()`, the signals in `mask` are also blocked (before the mask is changed, and
+    /// if changing it fails, they are unblocked again), and any signals that were blocked for the
+    /// old mask (and are not in the new mask) are unblocked.
+    pub fn set_mask(&mut self, mask: &Sigset) -> io::Result<()> {
+        let blocked = match self.blocked {
+            Some(blocked) => blocked,
+            None => {
+                error::convert_ret(unsafe { libc::signalfd(self.fd, mask.as_ref(), 0) })?;
+                return Ok(());
+            }
+        };
+
+        let old_mask = crate::sigmask::block(mask)?;
+        let newly_blocked = *mask - old_mask;
+
+        if let Err(e) = error::convert_ret(unsafe { libc::signalfd(self.fd, mask.as_ref(), 0) }) {
+            crate::sigmask::unblock(&newly_blocked)?;
+            return Err(e);
+        }
+
+        // Keep track of the stale signals until they've been unblocked
+        self.blocked = Some(blocked | newly_blocked);
+
+        let stale = blocked - *mask;
+        if !stale.is_empty() {
+            crate::sigmask::unblock(&stale)?;
+        }
+
+        self.blocked = Some((blocked & *mask) | newly_blocked);
+
+        Ok(())
+    }
+
+    /// Begin monitoring this `SignalFd` with the given `Poller` (for readability).
+    #[inline]
+    pub fn register_with<P: crate::pollers::Poller>(&self, poller: &mut P) -> io::Result<()> {
+        poller.register(self.fd, crate::pollers::Events::READ)
+    }
+
+    /// Stop monitoring this `SignalFd` with the given `Poller`.
+    #[inline]
+    pub fn unregister_from<P: crate::pollers::Poller>(&self, poller: &mut P) -> io::Result<()> {
+        poller.unregister(self.fd)
+    }
+
+    /// Get an iterator that reads and decodes all of the currently pending signals.
+    ///
+    /// This never blocks; the iterator ends once there are no more signals pending. Signals are
+    /// read from the kernel in batches.
+    ///
+    /// This `SignalFd` must have been created in nonblocking mode (with `nonblock` set to
+    /// `true`); otherwise, this fails with `EINVAL`. (Checking for readability before each read
+    /// isn't enough, since another thread could read the signals first.)
+    #[inline]
+    pub fn drain(&self) -> io::Result<Drain<'_>> {
+        if !self.nonblock {
+            return Err(io::Error::from_raw_os_error(libc::EINVAL));
+        }
+
+        Ok(Drain {
+            sigfd: self,
+            buf: [Siginfo::default(); DRAIN_BATCH_SIZE],
+            pos: 0,
+            len: 0,
+            done: false,
+        })
     }
 
     pub fn read_one(&self) -> io::Result<Siginfo> {
         let mut siginfo = unsafe { std::mem::zeroed() };
 
-        error::convert_neg_ret(
-            unsafe {
-                libc::read(
-                    self.fd,
-                    (&mut siginfo as *mut Siginfo) as *mut libc::c_void,
-                    std::mem::size_of::<Siginfo>(),
-                )
-            }
-        )?;
+        error::convert_neg_ret(unsafe {
+            libc::read(
+                self.fd,
+                (&mut siginfo as *mut Siginfo) as *mut libc::c_void,
+                std::mem::size_of::<Siginfo>(),
+            )
+        })?;
 
         Ok(siginfo)
     }
 
+    /// Read a single signal and decode it into a `SigInfo`.
+    #[inline]
+    pub fn read_info(&self) -> io::Result<SigInfo> {
+        Ok(self.read_one()?.decode())
+    }
+
     pub fn read(&self, siginfos: &mut [Siginfo]) -> io::Result<usize> {
         let n = error::convert_neg_ret(unsafe {
             libc::read(
@@ -51,6 +161,63 @@ impl SignalFd {
     }
 }
 
+const DRAIN_BATCH_SIZE: usize = 8;
+
+/// An iterator over the pending signals on a `SignalFd`.
+///
+/// See `SignalFd::drain()`.
+#[derive(Debug)]
+pub struct Drain<'a> {
+    sigfd: &'a SignalFd,
+    buf: [Siginfo; DRAIN_BATCH_SIZE],
+    pos: usize,
+    len: usize,
+    done: bool,
+}
+
+impl<'a> Drain<'a> {
+    fn fill(&mut self) -> io::Result<bool> {
+        match self.sigfd.read(&mut self.buf) {
+            Ok(n) => {
+                self.pos = 0;
+                self.len = n;
+                Ok(n > 0)
+            }
+            // No more signals (or someone else read them first)
+            Err(e) if error::is_eagain(&e) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl<'a> Iterator for Drain<'a> {
+    type Item = io::Result<SigInfo>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pos == self.len {
+            if self.done {
+                return None;
+            }
+
+            match self.fill() {
+                Ok(true) => (),
+                Ok(false) => {
+                    self.done = true;
+                    return None;
+                }
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+
+        let info = self.buf[self.pos].decode();
+        self.pos += 1;
+        Some(Ok(info))
+    }
+}
+
 impl AsRawFd for SignalFd {
     #[inline]
     fn as_raw_fd(&self) -> libc::c_int {
@@ -64,6 +231,12 @@ impl Drop for SignalFd {
         unsafe {
             libc::close(self.fd);
         }
+
+        if let Some(blocked) = self.blocked {
+            if !blocked.is_empty() {
+                let _ = crate::sigmask::unblock(&blocked);
+            }
+        }
     }
 }
 
@@ -87,7 +260,18 @@ pub struct Siginfo {
     pub stime: u64,
     pub addr: u64,
     pub addr_lsb: u16,
+    _pad: u16,
+    pub syscall: i32,
+    pub call_addr: u64,
+    pub arch: u32,
     // WARNING: This is dependent on the size of the fields above!
-    _padding: [u8; 32],
-    _padding2: [u8; 14],
+    _padding: [u8; 28],
+}
+
+impl Siginfo {
+    /// Decode this structure into a `SigInfo`.
+    #[inline]
+    pub fn decode(&self) -> SigInfo {
+        SigInfo::from(self)
+    }
 }
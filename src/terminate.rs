@@ -0,0 +1,435 @@
+use std::fs;
+use std::io;
+use std::os::unix::prelude::*;
+use std::time::{Duration, Instant};
+
+use crate::poll::{Events, PollFd};
+use crate::signal::{Signal, SIGCONT, SIGKILL, SIGSTOP};
+use crate::wait::{ProcStatus, WaitpidOptions, WaitpidSpec};
+use crate::{Int, PidT};
+
+/// The set of processes to terminate with `terminate()`.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum TerminateTarget {
+    /// A single process (must be > 0).
+    Pid(PidT),
+    /// Every process in the given process group (must be > 1).
+    Pgid(PidT),
+    /// The given process and all of its descendants.
+    Tree(PidT),
+    /// All of the descendants of the given process, but not the process itself. (For example,
+    /// `Descendants(getpid())` terminates all of the current process's descendants.)
+    Descendants(PidT),
+}
+
+/// Controls how `terminate()` terminates processes.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct TerminatePolicy {
+    /// The signal to send first.
+    pub signal: Signal,
+    /// How long to wait for the processes to exit after sending `signal`, before sending
+    /// `SIGKILL`.
+    pub grace_period: Duration,
+    /// How long to wait for the processes to exit after sending `SIGKILL`. (A process that is
+    /// in uninterruptible sleep will not die until it wakes up.)
+    pub kill_timeout: Duration,
+    /// How often to check whether the processes have exited, if this cannot be done with
+    /// pidfds.
+    pub poll_interval: Duration,
+}
+
+impl TerminatePolicy {
+    /// Create a policy that sends `signal` first, then sends `SIGKILL` if the processes do not
+    /// exit within `grace_period`.
+    #[inline]
+    pub fn new(signal: Signal, grace_period: Duration) -> Self {
+        Self {
+            signal,
+            grace_period,
+            ..Self::default()
+        }
+    }
+}
+
+impl Default for TerminatePolicy {
+    /// Send `SIGTERM`, wait 5 seconds, then send `SIGKILL`.
+    fn default() -> Self {
+        Self {
+            signal: Signal::Term,
+            grace_period: Duration::from_secs(5),
+            kill_timeout: Duration::from_secs(5),
+            poll_interval: Duration::from_millis(10),
+        }
+    }
+}
+
+/// A process that was terminated by `terminate()`.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct TerminatedProcess {
+    pub pid: PidT,
+    /// The status of the process, if it was a child of the current process. (`terminate()`
+    /// reaps any children of the current process that it terminates.)
+    pub status: Option<ProcStatus>,
+}
+
+/// The results of `terminate()`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct TerminateReport {
+    /// The processes that exited before the grace period expired.
+    pub exited: Vec<TerminatedProcess>,
+    /// The processes that had to be sent `SIGKILL`.
+    pub killed: Vec<TerminatedProcess>,
+    /// The processes that were still alive after `kill_timeout` expired.
+    pub remaining: Vec<PidT>,
+}
+
+#[derive(Debug)]
+struct Target {
+    pid: PidT,
+    pidfd: Option<fs::File>,
+    is_child: bool,
+    status: Option<ProcStatus>,
+}
+
+impl Target {
+    fn new(pid: PidT, ppid: PidT) -> io::Result<Self> {
+        let pidfd = match crate::wait::pidfd_open(pid) {
+            Ok(pidfd) => Some(pidfd),
+            Err(e) if crate::error::is_raw(&e, libc::ENOSYS) => None,
+            // If it's already gone, we'll notice when we check if it's exited
+            Err(e) if crate::error::is_raw(&e, libc::ESRCH) => None,
+            Err(e) => return Err(e),
+        };
+
+        Ok(Self {
+            pid,
+            pidfd,
+            is_child: ppid == crate::process::getpid(),
+            status: None,
+        })
+    }
+
+    fn send_signal(&self, sig: Int) -> io::Result<()> {
+        // If we have a pidfd, use it to avoid races with PID reuse
+        let res = match self.pidfd {
+            Some(ref pidfd) => crate::error::convert_nzero_ret(unsafe {
+                libc::syscall(
+                    libc::SYS_pidfd_send_signal,
+                    pidfd.as_raw_fd(),
+                    sig,
+                    std::ptr::null::<libc::siginfo_t>(),
+                    0,
+                )
+            }),
+            None => crate::kill(crate::KillSpec::Pid(self.pid), sig),
+        };
+
+        match res {
+            Err(e) if crate::error::is_raw(&e, libc::ESRCH) => Ok(()),
+            res => res,
+        }
+    }
+
+    fn has_exited(&mut self) -> io::Result<bool> {
+        if self.is_child {
+            match crate::wait::waitpid(WaitpidSpec::Pid(self.pid), WaitpidOptions::NOHANG) {
+                Ok(Some((_, status))) => {
+                    self.status = Some(status);
+                    Ok(true)
+                }
+                Ok(None) => Ok(false),
+                // Someone else reaped it
+                Err(e) if crate::error::is_raw(&e, libc::ECHILD) => Ok(true),
+                Err(e) => Err(e),
+            }
+        } else if let Some(ref pidfd) = self.pidfd {
+            let mut pollfds = [PollFd::new(pidfd.as_raw_fd(), Events::IN)];
+            Ok(crate::poll::poll(&mut pollfds, Some(Duration::from_secs(0)))? > 0)
+        } else {
+            match read_stat(self.pid) {
+                Ok(stat) => Ok(stat.state == b'Z'),
+                Err(e) if crate::error::is_raw(&e, libc::ESRCH) => Ok(true),
+                Err(e) => Err(e),
+            }
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+struct ProcStat {
+    pid: PidT,
+    state: u8,
+    ppid: PidT,
+    pgrp: PidT,
+}
+
+fn read_stat(pid: PidT) -> io::Result<ProcStat> {
+    let data = match fs::read(format!("/proc/{}/stat", pid)) {
+        Ok(data) => data,
+        Err(e) if crate::error::is_raw(&e, libc::ENOENT) => {
+            return Err(io::Error::from_raw_os_error(libc::ESRCH))
+        }
+        Err(e) => return Err(e),
+    };
+
+    // The command name is in parentheses and may contain spaces or parentheses, so skip past
+    // the last ')'.
+    let rest = data
+        .iter()
+        .rposition(|&c| c == b')')
+        .and_then(|i| std::str::from_utf8(&data[i + 1..]).ok())
+        .ok_or_else(|| io::Error::from_raw_os_error(libc::EINVAL))?;
+
+    let mut fields = rest.split_whitespace();
+
+    let state = fields.next().and_then(|s| s.bytes().next());
+    let ppid = fields.next().and_then(|s| s.parse().ok());
+    let pgrp = fields.next().and_then(|s| s.parse().ok());
+
+    match (state, ppid, pgrp) {
+        (Some(state), Some(ppid), Some(pgrp)) => Ok(ProcStat {
+            pid,
+            state,
+            ppid,
+            pgrp,
+        }),
+        _ => Err(io::Error::from_raw_os_error(libc::EINVAL)),
+    }
+}
+
+fn list_processes() -> io::Result<Vec<ProcStat>> {
+    let mut stats = Vec::new();
+
+    for entry in fs::read_dir("/proc")? {
+        if let Some(pid) = entry?.file_name().to_str().and_then(|s| s.parse().ok()) {
+            match read_stat(pid) {
+                Ok(stat) => stats.push(stat),
+                // It exited while we were scanning
+                Err(e) if crate::error::is_raw(&e, libc::ESRCH) => (),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    Ok(stats)
+}
+
+// Resume processes that were stopped by find_and_stop_descendants() (used if an error occurs
+// before they are sent SIGCONT, so they aren't left stopped forever)
+fn resume(stats: &[ProcStat]) {
+    for stat in stats {
+        let _ = crate::kill(crate::KillSpec::Pid(stat.pid), SIGCONT);
+    }
+}
+
+// Find all the descendants of the given process, and stop them (with SIGSTOP) so that they
+// can't fork any more children while we're looking. If include_root is true, the root process
+// is stopped and included too. The current process is skipped (if the root is one of its
+// ancestors, stopping it would hang forever), though its descendants are still included. If an
+// error occurs, the processes that were stopped are resumed.
+fn find_and_stop_descendants(root: PidT, include_root: bool) -> io::Result<Vec<ProcStat>> {
+    let mut found = Vec::new();
+
+    match stop_descendants(root, include_root, &mut found) {
+        Ok(()) => Ok(found),
+        Err(e) => {
+            resume(&found);
+            Err(e)
+        }
+    }
+}
+
+fn stop_descendants(root: PidT, include_root: bool, found: &mut Vec<ProcStat>) -> io::Result<()> {
+    if include_root {
+        let stat = read_stat(root)?;
+        crate::kill(crate::KillSpec::Pid(root), SIGSTOP)?;
+        found.push(stat);
+    }
+
+    loop {
+        let stats = list_processes()?;
+        let self_pid = crate::process::getpid();
+
+        let mut new_found = Vec::new();
+        let mut parents = vec![root];
+        while let Some(parent) = parents.pop() {
+            for stat in stats.iter().filter(|stat| stat.ppid == parent) {
+                parents.push(stat.pid);
+
+                if stat.pid != self_pid && !found.iter().any(|s| s.pid == stat.pid) {
+                    new_found.push(*stat);
+                }
+            }
+        }
+
+        // Once a scan turns up no new processes, everything has been stopped and we know
+        // we've found them all.
+        if new_found.is_empty() {
+            return Ok(());
+        }
+
+        for stat in new_found {
+            match crate::kill(crate::KillSpec::Pid(stat.pid), SIGSTOP) {
+                Ok(()) => found.push(stat),
+                Err(e) if crate::error::is_raw(&e, libc::ESRCH) => (),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+// Wait until either all of the targets have exited or the deadline passes. The targets that
+// exited are removed from `targets` and added to `done`.
+fn wait_for_exit(
+    targets: &mut Vec<Target>,
+    done: &mut Vec<TerminatedProcess>,
+    deadline: Instant,
+    poll_interval: Duration,
+) -> io::Result<()> {
+    loop {
+        let mut i = 0;
+        while i < targets.len() {
+            if targets[i].has_exited()? {
+                let target = targets.remove(i);
+                done.push(TerminatedProcess {
+                    pid: target.pid,
+                    status: target.status,
+                });
+            } else {
+                i += 1;
+            }
+        }
+
+        let now = Instant::now();
+        if targets.is_empty() || now >= deadline {
+            return Ok(());
+        }
+        let remaining = deadline - now;
+
+        if targets.iter().all(|target| target.pidfd.is_some()) {
+            // The pidfds become readable when the processes exit
+            let mut pollfds: Vec<PollFd> = targets
+                .iter()
+                .map(|target| PollFd::new(target.pidfd.as_ref().unwrap().as_raw_fd(), Events::IN))
+                .collect();
+
+            match crate::poll::poll(&mut pollfds, Some(remaining)) {
+                Ok(_) => (),
+                Err(e) if crate::error::is_eintr(&e) => (),
+                Err(e) => return Err(e),
+            }
+        } else {
+            std::thread::sleep(std::cmp::min(remaining, poll_interval));
+        }
+    }
+}
+
+// Send the first signal to the processes (and SIGCONT, for process trees), and return the
+// targets to wait for
+fn signal_targets(
+    target: TerminateTarget,
+    policy: &TerminatePolicy,
+    stats: &[ProcStat],
+) -> io::Result<Vec<Target>> {
+    let targets = stats
+        .iter()
+        .map(|stat| Target::new(stat.pid, stat.ppid))
+        .collect::<io::Result<Vec<Target>>>()?;
+
+    match target {
+        TerminateTarget::Pgid(pgid) => match crate::killpg(pgid, policy.signal.as_raw()) {
+            Err(e) if crate::error::is_raw(&e, libc::ESRCH) => (),
+            res => res?,
+        },
+        TerminateTarget::Pid(_) => targets[0].send_signal(policy.signal.as_raw())?,
+        TerminateTarget::Tree(_) | TerminateTarget::Descendants(_) => {
+            for target in targets.iter() {
+                target.send_signal(policy.signal.as_raw())?;
+            }
+            for target in targets.iter() {
+                target.send_signal(SIGCONT)?;
+            }
+        }
+    }
+
+    Ok(targets)
+}
+
+/// Terminate a process (or a group of processes), escalating to `SIGKILL` if necessary.
+///
+/// This sends `policy.signal` to the processes specified by `target`, waits up to
+/// `policy.grace_period` for them to exit, and then sends `SIGKILL` to any that are left (and
+/// waits up to `policy.kill_timeout` for those to exit).
+///
+/// For `TerminateTarget::Tree` and `TerminateTarget::Descendants`, the process tree is found
+/// by scanning `/proc`. Each process is stopped with `SIGSTOP` as it is found (so that it
+/// can't create more processes), and once the whole tree has been found, every process is sent
+/// `policy.signal` followed by `SIGCONT` (so that processes which handle `policy.signal` can
+/// do so). If an error occurs before the processes are sent `SIGCONT`, they are resumed
+/// before the error is returned.
+///
+/// The current process is never stopped, signaled, or waited for as part of a tree, so (for
+/// example) `Descendants(getppid())` terminates the current process's siblings and their
+/// descendants (and the current process's own descendants) without hanging.
+///
+/// For `TerminateTarget::Pgid`, the processes in the group are found by scanning `/proc`, and
+/// the first signal is sent with `killpg()`. Processes that join the group after the scan are
+/// sent the first signal, but they are not tracked or sent `SIGKILL`. (If the current process
+/// is in the group, `killpg()` sends it the first signal too, but it is not sent `SIGKILL`.)
+///
+/// Any of the processes that are children of the current process are reaped, and their exit
+/// statuses are included in the report. Whether other processes have exited is checked with
+/// pidfds if the kernel supports them, or by polling `/proc` (every `policy.poll_interval`)
+/// otherwise.
+pub fn terminate(target: TerminateTarget, policy: &TerminatePolicy) -> io::Result<TerminateReport> {
+    let stats = match target {
+        TerminateTarget::Pid(pid) if pid > 0 => vec![read_stat(pid)?],
+        TerminateTarget::Pgid(pgid) if pgid > 1 => list_processes()?
+            .into_iter()
+            .filter(|stat| stat.pgrp == pgid && stat.pid != crate::process::getpid())
+            .collect(),
+        TerminateTarget::Tree(pid) if pid > 0 && pid != crate::process::getpid() => {
+            find_and_stop_descendants(pid, true)?
+        }
+        TerminateTarget::Descendants(pid) if pid > 0 => find_and_stop_descendants(pid, false)?,
+        _ => return Err(io::Error::from_raw_os_error(libc::EINVAL)),
+    };
+
+    let mut targets = match signal_targets(target, policy, &stats) {
+        Ok(targets) => targets,
+        Err(e) => {
+            if let TerminateTarget::Tree(_) | TerminateTarget::Descendants(_) = target {
+                resume(&stats);
+            }
+            return Err(e);
+        }
+    };
+
+    let mut report = TerminateReport::default();
+
+    wait_for_exit(
+        &mut targets,
+        &mut report.exited,
+        Instant::now() + policy.grace_period,
+        policy.poll_interval,
+    )?;
+
+    if targets.is_empty() {
+        return Ok(report);
+    }
+
+    for target in targets.iter() {
+        target.send_signal(SIGKILL)?;
+    }
+
+    wait_for_exit(
+        &mut targets,
+        &mut report.killed,
+        Instant::now() + policy.kill_timeout,
+        policy.poll_interval,
+    )?;
+
+    report.remaining = targets.iter().map(|target| target.pid).collect();
+
+    Ok(report)
+}
@@ -0,0 +1,353 @@
+use std::convert::TryInto;
+use std::io;
+use std::os::unix::prelude::*;
+use std::time::{Duration, SystemTime};
+
+use crate::error;
+use crate::Int;
+
+/// The clock that a `TimerFd` is based on.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum Clock {
+    /// The settable system-wide ("wall-clock") clock.
+    Realtime = libc::CLOCK_REALTIME as isize,
+    /// A nonsettable clock that does not count time that the system is suspended.
+    Monotonic = libc::CLOCK_MONOTONIC as isize,
+    /// Like `Monotonic`, but it includes time that the system is suspended.
+    Boottime = libc::CLOCK_BOOTTIME as isize,
+    /// Like `Realtime`, but it will wake the system if it is suspended. (This requires the
+    /// `CAP_WAKE_ALARM` capability.)
+    RealtimeAlarm = libc::CLOCK_REALTIME_ALARM as isize,
+    /// Like `Boottime`, but it will wake the system if it is suspended. (This requires the
+    /// `CAP_WAKE_ALARM` capability.)
+    BoottimeAlarm = libc::CLOCK_BOOTTIME_ALARM as isize,
+}
+
+impl Clock {
+    #[inline]
+    fn is_realtime(self) -> bool {
+        matches!(self, Self::Realtime | Self::RealtimeAlarm)
+    }
+}
+
+/// The current setting of a `TimerFd`.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct TimerSpec {
+    /// The time until the next expiration.
+    pub value: Duration,
+    /// The interval between expirations, or `None` if this is a one-shot timer.
+    pub interval: Option<Duration>,
+}
+
+// Durations that are too large (including times after 2038 on platforms with a 32-bit time_t)
+// are clamped, so they don't wrap around into the past
+fn duration_to_timespec(d: Duration) -> libc::timespec {
+    libc::timespec {
+        tv_sec: d.as_secs().try_into().unwrap_or(libc::time_t::MAX),
+        tv_nsec: d.subsec_nanos() as crate::Long,
+    }
+}
+
+fn timespec_to_duration(ts: libc::timespec) -> Duration {
+    Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32)
+}
+
+#[derive(Debug)]
+pub struct TimerFd {
+    fd: Int,
+    clock: Clock,
+}
+
+impl TimerFd {
+    /// Create a new (disarmed) timer based on the given clock.
+    pub fn new(clock: Clock, nonblock: bool) -> io::Result<Self> {
+        let mut flags = libc::TFD_CLOEXEC;
+        if nonblock {
+            flags |= libc::TFD_NONBLOCK;
+        }
+
+        let fd = error::convert_neg_ret(unsafe {
+            libc::timerfd_create(clock as libc::clockid_t, flags)
+        })?;
+
+        Ok(Self { fd, clock })
+    }
+
+    #[inline]
+    pub fn clock(&self) -> Clock {
+        self.clock
+    }
+
+    fn settime(&self, flags: Int, value: Duration, interval: Option<Duration>) -> io::Result<()> {
+        let new_value = libc::itimerspec {
+            it_value: duration_to_timespec(value),
+            it_interval: duration_to_timespec(interval.unwrap_or_default()),
+        };
+
+        error::convert_nzero_ret(unsafe {
+            libc::timerfd_settime(self.fd, flags, &new_value, std::ptr::null_mut())
+        })
+    }
+
+    /// Arm the timer so that it first expires after `value`, and then (if `interval` is not
+    /// `None`) every `interval` after that.
+    ///
+    /// This replaces any previous setting. If `value` is zero, the timer is disarmed.
+    #[inline]
+    pub fn set(&self, value: Duration, interval: Option<Duration>) -> io::Result<()> {
+        self.settime(0, value, interval)
+    }
+
+    /// Arm the timer so that it expires once, after `value`.
+    #[inline]
+    pub fn set_oneshot(&self, value: Duration) -> io::Result<()> {
+        self.set(value, None)
+    }
+
+    /// Arm the timer so that it expires every `interval`, starting `interval` from now.
+    #[inline]
+    pub fn set_interval(&self, interval: Duration) -> io::Result<()> {
+        self.set(interval, Some(interval))
+    }
+
+    /// Arm the timer so that it first expires at the given time, and then (if `interval` is not
+    /// `None`) every `interval` after that. If `time` is in the past, the timer expires
+    /// immediately.
+    ///
+    /// For the `Realtime` and `RealtimeAlarm` clocks, the expiration time is absolute, so the
+    /// expiration will be adjusted if the system clock is changed. If `cancel_on_set` is true,
+    /// then if the system clock is changed (discontinuously) before the timer expires, reads from
+    /// the timer will fail with `ECANCELED` (and it can then be re-armed).
+    ///
+    /// For the other clocks, the time is converted to a relative expiration time, and
+    /// `cancel_on_set` must be false (otherwise this fails with `EINVAL`).
+    pub fn set_at(
+        &self,
+        time: SystemTime,
+        interval: Option<Duration>,
+        cancel_on_set: bool,
+    ) -> io::Result<()> {
+        if self.clock.is_realtime() {
+            let mut flags = libc::TFD_TIMER_ABSTIME;
+            if cancel_on_set {
+                flags |= libc::TFD_TIMER_CANCEL_ON_SET;
+            }
+
+            // A zero value would disarm the timer, so times before the epoch are clamped to just
+            // after it (which still expires immediately)
+            let value = time
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default()
+                .max(Duration::from_nanos(1));
+
+            self.settime(flags, value, interval)
+        } else if cancel_on_set {
+            Err(io::Error::from_raw_os_error(libc::EINVAL))
+        } else {
+            let value = time
+                .duration_since(SystemTime::now())
+                .unwrap_or_default()
+                .max(Duration::from_nanos(1));
+
+            self.settime(0, value, interval)
+        }
+    }
+
+    /// Disarm the timer.
+    #[inline]
+    pub fn disarm(&self) -> io::Result<()> {
+        self.set(Duration::from_secs(0), None)
+    }
+
+    /// Get the current setting of the timer, or `None` if it is disarmed.
+    pub fn get(&self) -> io::Result<Option<TimerSpec>> {
+        let mut curr_value: libc::itimerspec = unsafe { std::mem::zeroed() };
+
+        error::convert_nzero_ret(unsafe { libc::timerfd_gettime(self.fd, &mut curr_value) })?;
+
+        let value = timespec_to_duration(curr_value.it_value);
+        let interval = timespec_to_duration(curr_value.it_interval);
+
+        if value == Duration::from_secs(0) {
+            Ok(None)
+        } else if interval == Duration::from_secs(0) {
+            Ok(Some(TimerSpec {
+                value,
+                interval: None,
+            }))
+        } else {
+            Ok(Some(TimerSpec {
+                value,
+                interval: Some(interval),
+            }))
+        }
+    }
+
+    /// Read the number of times the timer has expired since it was last armed or read.
+    ///
+    /// If the timer has not expired, this blocks until it does (or fails with `EAGAIN` if the
+    /// timer is nonblocking). If the timer was armed with `cancel_on_set` and the system clock
+    /// was changed, this fails with `ECANCELED`.
+    pub fn read(&self) -> io::Result<u64> {
+        let mut count: u64 = 0;
+
+        error::convert_neg_ret(unsafe {
+            libc::read(
+                self.fd,
+                (&mut count as *mut u64) as *mut libc::c_void,
+                std::mem::size_of::<u64>(),
+            )
+        })?;
+
+        Ok(count)
+    }
+
+    /// Begin monitoring this `TimerFd` with the given `Poller` (for readability, which indicates
+    /// that the timer has expired).
+    #[inline]
+    pub fn register_with<P: crate::pollers::Poller>(&self, poller: &mut P) -> io::Result<()> {
+        poller.register(self.fd, crate::pollers::Events::READ)
+    }
+
+    /// Stop monitoring this `TimerFd` with the given `Poller`.
+    #[inline]
+    pub fn unregister_from<P: crate::pollers::Poller>(&self, poller: &mut P) -> io::Result<()> {
+        poller.unregister(self.fd)
+    }
+}
+
+impl AsRawFd for TimerFd {
+    #[inline]
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd
+    }
+}
+
+impl Drop for TimerFd {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe {
+            libc::close(self.fd);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::pollers::{DefaultPoller, Events, Poller};
+
+    #[test]
+    fn test_timerfd_oneshot() {
+        let timer = TimerFd::new(Clock::Monotonic, true).unwrap();
+        assert_eq!(timer.clock(), Clock::Monotonic);
+        assert_eq!(timer.get().unwrap(), None);
+        assert!(error::is_eagain(&timer.read().unwrap_err()));
+
+        let mut poller = DefaultPoller::new().unwrap();
+        timer.register_with(&mut poller).unwrap();
+
+        timer.set_oneshot(Duration::from_millis(20)).unwrap();
+        let spec = timer.get().unwrap().unwrap();
+        assert!(spec.value <= Duration::from_millis(20));
+        assert_eq!(spec.interval, None);
+
+        assert_eq!(
+            poller.poll(Some(Duration::from_secs(5))).unwrap(),
+            vec![(timer.as_raw_fd(), Events::READ)]
+        );
+        assert_eq!(timer.read().unwrap(), 1);
+        assert_eq!(timer.get().unwrap(), None);
+        assert_eq!(poller.poll(Some(Duration::from_secs(0))).unwrap(), vec![]);
+
+        timer.unregister_from(&mut poller).unwrap();
+
+        // Huge values are clamped (instead of wrapping around and failing or expiring)
+        timer.set_oneshot(Duration::MAX).unwrap();
+        assert!(timer.get().unwrap().unwrap().value > Duration::from_secs(1 << 31));
+
+        // Disarming
+        timer.set_oneshot(Duration::from_secs(10)).unwrap();
+        assert!(timer.get().unwrap().is_some());
+        timer.disarm().unwrap();
+        assert_eq!(timer.get().unwrap(), None);
+    }
+
+    #[test]
+    fn test_timerfd_interval() {
+        let timer = TimerFd::new(Clock::Boottime, false).unwrap();
+
+        timer.set_interval(Duration::from_millis(5)).unwrap();
+        assert_eq!(
+            timer.get().unwrap().unwrap().interval,
+            Some(Duration::from_millis(5))
+        );
+
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(timer.read().unwrap() >= 2);
+
+        // Blocking read
+        assert!(timer.read().unwrap() >= 1);
+    }
+
+    #[test]
+    fn test_timerfd_set_at() {
+        let timer = TimerFd::new(Clock::Realtime, true).unwrap();
+
+        // In the past; expires immediately
+        timer.set_at(SystemTime::UNIX_EPOCH, None, false).unwrap();
+        assert_eq!(timer.read().unwrap(), 1);
+
+        timer
+            .set_at(SystemTime::now() + Duration::from_secs(10), None, true)
+            .unwrap();
+        assert!(timer.get().unwrap().unwrap().value <= Duration::from_secs(10));
+        assert!(error::is_eagain(&timer.read().unwrap_err()));
+
+        // Far in the future (past the range of a 32-bit time_t)
+        timer
+            .set_at(
+                SystemTime::UNIX_EPOCH + Duration::from_secs(1 << 33),
+                None,
+                false,
+            )
+            .unwrap();
+        assert!(timer.get().unwrap().is_some());
+        assert!(error::is_eagain(&timer.read().unwrap_err()));
+
+        // Converted to a relative time for other clocks
+        let timer = TimerFd::new(Clock::Monotonic, true).unwrap();
+        timer
+            .set_at(SystemTime::now() + Duration::from_secs(10), None, false)
+            .unwrap();
+        let spec = timer.get().unwrap().unwrap();
+        assert!(spec.value <= Duration::from_secs(10));
+        assert!(spec.value > Duration::from_secs(5));
+
+        timer.set_at(SystemTime::UNIX_EPOCH, None, false).unwrap();
+        std::thread::sleep(Duration::from_millis(1));
+        assert_eq!(timer.read().unwrap(), 1);
+
+        assert!(error::is_raw(
+            &timer.set_at(SystemTime::now(), None, true).unwrap_err(),
+            libc::EINVAL
+        ));
+    }
+
+    #[test]
+    fn test_timerfd_alarm() {
+        // These require CAP_WAKE_ALARM
+        for &clock in [Clock::RealtimeAlarm, Clock::BoottimeAlarm].iter() {
+            match TimerFd::new(clock, true) {
+                Ok(timer) => {
+                    timer.set_oneshot(Duration::from_secs(10)).unwrap();
+                    timer.disarm().unwrap();
+                }
+                Err(e) => {
+                    assert!(error::is_raw(&e, libc::EPERM) || error::is_raw(&e, libc::EINVAL))
+                }
+            }
+        }
+    }
+}
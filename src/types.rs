@@ -26,6 +26,155 @@ crate::attr_group! {
         pub si_uid: libc::uid_t,
         pub si_status: libc::c_int,
     }
+
+    // The layout of siginfo_t for signals sent by kill(), sigqueue(), etc.
+    #[repr(C)]
+    pub struct rt_siginfo {
+        pub si_signo: libc::c_int,
+        pub si_errno: libc::c_int,
+        pub si_code: libc::c_int,
+        #[cfg(target_pointer_width = "64")]
+        _pad: libc::c_int,
+        pub si_pid: libc::pid_t,
+        pub si_uid: libc::uid_t,
+        pub si_value: *mut libc::c_void,
+    }
+
+    // The layout of siginfo_t for SIGSEGV, SIGBUS, SIGILL, SIGFPE, and SIGTRAP
+    #[repr(C)]
+    pub struct fault_siginfo {
+        pub si_signo: libc::c_int,
+        pub si_errno: libc::c_int,
+        pub si_code: libc::c_int,
+        #[cfg(target_pointer_width = "64")]
+        _pad: libc::c_int,
+        pub si_addr: *mut libc::c_void,
+    }
+
+    // The layout of siginfo_t for SIGIO/SIGPOLL
+    #[repr(C)]
+    pub struct poll_siginfo {
+        pub si_signo: libc::c_int,
+        pub si_errno: libc::c_int,
+        pub si_code: libc::c_int,
+        #[cfg(target_pointer_width = "64")]
+        _pad: libc::c_int,
+        pub si_band: libc::c_long,
+        pub si_fd: libc::c_int,
+    }
+
+    // The layout of siginfo_t for POSIX timers
+    #[repr(C)]
+    pub struct timer_siginfo {
+        pub si_signo: libc::c_int,
+        pub si_errno: libc::c_int,
+        pub si_code: libc::c_int,
+        #[cfg(target_pointer_width = "64")]
+        _pad: libc::c_int,
+        pub si_tid: libc::c_int,
+        pub si_overrun: libc::c_int,
+        pub si_value: *mut libc::c_void,
+    }
+
+    // The layout of siginfo_t for SIGSYS sent by seccomp
+    #[repr(C)]
+    pub struct sys_siginfo {
+        pub si_signo: libc::c_int,
+        pub si_errno: libc::c_int,
+        pub si_code: libc::c_int,
+        #[cfg(target_pointer_width = "64")]
+        _pad: libc::c_int,
+        pub si_call_addr: *mut libc::c_void,
+        pub si_syscall: libc::c_int,
+        pub si_arch: libc::c_uint,
+    }
+
+    #[derive(Copy, Clone, Debug, Default)]
+    #[repr(C)]
+    pub struct io_sqring_offsets {
+        pub head: u32,
+        pub tail: u32,
+        pub ring_mask: u32,
+        pub ring_entries: u32,
+        pub flags: u32,
+        pub dropped: u32,
+        pub array: u32,
+        pub resv1: u32,
+        pub user_addr: u64,
+    }
+
+    #[derive(Copy, Clone, Debug, Default)]
+    #[repr(C)]
+    pub struct io_cqring_offsets {
+        pub head: u32,
+        pub tail: u32,
+        pub ring_mask: u32,
+        pub ring_entries: u32,
+        pub overflow: u32,
+        pub cqes: u32,
+        pub flags: u32,
+        pub resv1: u32,
+        pub user_addr: u64,
+    }
+
+    #[derive(Copy, Clone, Debug, Default)]
+    #[repr(C)]
+    pub struct io_uring_params {
+        pub sq_entries: u32,
+        pub cq_entries: u32,
+        pub flags: u32,
+        pub sq_thread_cpu: u32,
+        pub sq_thread_idle: u32,
+        pub features: u32,
+        pub wq_fd: u32,
+        pub resv: [u32; 3],
+        pub sq_off: io_sqring_offsets,
+        pub cq_off: io_cqring_offsets,
+    }
+
+    // The layout of a submission queue entry (with the unions flattened to the members that we
+    // use)
+    #[derive(Copy, Clone, Debug, Default)]
+    #[repr(C)]
+    pub struct io_uring_sqe {
+        pub opcode: u8,
+        pub flags: u8,
+        pub ioprio: u16,
+        pub fd: i32,
+        pub off: u64,
+        pub addr: u64,
+        pub len: u32,
+        // rw_flags/poll32_events/accept_flags/etc.
+        pub op_flags: u32,
+        pub user_data: u64,
+        pub buf_index: u16,
+        pub personality: u16,
+        pub splice_fd_in: i32,
+        pub addr3: u64,
+        pub _pad2: u64,
+    }
+
+    #[derive(Copy, Clone, Debug, Default)]
+    #[repr(C)]
+    pub struct io_uring_cqe {
+        pub user_data: u64,
+        pub res: i32,
+        pub flags: u32,
+    }
+
+    #[repr(C)]
+    pub struct io_uring_getevents_arg {
+        pub sigmask: u64,
+        pub sigmask_sz: u32,
+        pub pad: u32,
+        pub ts: u64,
+    }
+
+    #[repr(C)]
+    pub struct kernel_timespec {
+        pub tv_sec: i64,
+        pub tv_nsec: i64,
+    }
 }
 
 crate::attr_group! {
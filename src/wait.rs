@@ -1,4 +1,6 @@
+use std::fmt;
 use std::io;
+use std::os::unix::process::ExitStatusExt;
 
 use bitflags::bitflags;
 
@@ -8,7 +10,9 @@ use crate::{Int, PidT};
 #[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
 pub enum ProcStatus {
     Exited(Int),
-    Signaled(Int),
+    /// The process was killed by the given signal. The second field indicates whether a core dump
+    /// was produced.
+    Signaled(Int, bool),
     Stopped(Int),
     Continued,
 }
@@ -17,7 +21,7 @@ impl ProcStatus {
     fn from_raw_status(status: Int) -> Self {
         unsafe {
             if libc::WIFSIGNALED(status) {
-                Self::Signaled(libc::WTERMSIG(status))
+                Self::Signaled(libc::WTERMSIG(status), libc::WCOREDUMP(status))
             } else if libc::WIFSTOPPED(status) {
                 Self::Stopped(libc::WSTOPSIG(status))
             } else if libc::WIFCONTINUED(status) {
@@ -28,6 +32,73 @@ impl ProcStatus {
             }
         }
     }
+
+    /// Convert this status back into a raw wait status, as returned by `waitpid()`.
+    ///
+    /// This uses the traditional encoding, which is shared by all supported platforms.
+    fn to_raw_status(self) -> Int {
+        match self {
+            Self::Exited(code) => (code & 0xff) << 8,
+            Self::Signaled(sig, core_dumped) => (sig & 0x7f) | if core_dumped { 0x80 } else { 0 },
+            Self::Stopped(sig) => ((sig & 0xff) << 8) | 0x7f,
+            Self::Continued => 0xffff,
+        }
+    }
+
+    /// Get the value that a shell would store in `$?` for a process with this status.
+    ///
+    /// For processes that exited normally, this is the exit code. For processes that were killed
+    /// (or stopped) by a signal, this is 128 plus the signal number. For processes that were
+    /// continued, this is 0.
+    pub fn to_shell_code(&self) -> Int {
+        match *self {
+            Self::Exited(code) => code,
+            Self::Signaled(sig, _) | Self::Stopped(sig) => 128 + sig,
+            Self::Continued => 0,
+        }
+    }
+}
+
+impl From<std::process::ExitStatus> for ProcStatus {
+    #[inline]
+    fn from(status: std::process::ExitStatus) -> Self {
+        Self::from_raw_status(status.into_raw())
+    }
+}
+
+impl From<ProcStatus> for std::process::ExitStatus {
+    #[inline]
+    fn from(status: ProcStatus) -> Self {
+        Self::from_raw(status.to_raw_status())
+    }
+}
+
+impl fmt::Display for ProcStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fn fmt_sig(f: &mut fmt::Formatter, sig: Int) -> fmt::Result {
+            match crate::signal::sig_to_name(sig) {
+                Some(name) => f.write_str(&name),
+                None => write!(f, "signal {}", sig),
+            }
+        }
+
+        match *self {
+            Self::Exited(code) => write!(f, "exited with status {}", code),
+            Self::Signaled(sig, core_dumped) => {
+                f.write_str("killed by ")?;
+                fmt_sig(f, sig)?;
+                if core_dumped {
+                    f.write_str(" (core dumped)")?;
+                }
+                Ok(())
+            }
+            Self::Stopped(sig) => {
+                f.write_str("stopped by ")?;
+                fmt_sig(f, sig)
+            }
+            Self::Continued => f.write_str("continued"),
+        }
+    }
 }
 
 pub fn wait() -> io::Result<(PidT, ProcStatus)> {
@@ -205,8 +276,10 @@ crate::attr_group! {
         Exited(Int),
         Killed(Int),
         Dumped(Int),
-        Stopped,
-        Trapped,
+        /// The process was stopped by the given signal.
+        Stopped(Int),
+        /// The (traced) process was stopped by the given signal.
+        Trapped(Int),
         Continued,
     }
 
@@ -216,14 +289,26 @@ crate::attr_group! {
                 constants::CLD_EXITED => Ok(WaitidStatus::Exited(status)),
                 constants::CLD_KILLED => Ok(WaitidStatus::Killed(status)),
                 constants::CLD_DUMPED => Ok(WaitidStatus::Dumped(status)),
-                constants::CLD_STOPPED => Ok(WaitidStatus::Stopped),
-                constants::CLD_TRAPPED => Ok(WaitidStatus::Trapped),
+                constants::CLD_STOPPED => Ok(WaitidStatus::Stopped(status)),
+                constants::CLD_TRAPPED => Ok(WaitidStatus::Trapped(status)),
                 constants::CLD_CONTINUED => Ok(WaitidStatus::Continued),
                 _ => Err(io::Error::from_raw_os_error(libc::EINVAL)),
             }
         }
     }
 
+    impl From<WaitidStatus> for ProcStatus {
+        fn from(status: WaitidStatus) -> Self {
+            match status {
+                WaitidStatus::Exited(code) => Self::Exited(code),
+                WaitidStatus::Killed(sig) => Self::Signaled(sig, false),
+                WaitidStatus::Dumped(sig) => Self::Signaled(sig, true),
+                WaitidStatus::Stopped(sig) | WaitidStatus::Trapped(sig) => Self::Stopped(sig),
+                WaitidStatus::Continued => Self::Continued,
+            }
+        }
+    }
+
     #[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
     pub struct WaitidInfo {
         pub pid: PidT,
@@ -321,3 +406,256 @@ crate::attr_group! {
         }
     }
 }
+
+crate::attr_group! {
+    #![cfg(target_os = "linux")]
+
+    use std::fs;
+    use std::os::unix::prelude::*;
+    use std::time::{Duration, Instant};
+
+    use crate::poll::{Events, PollFd};
+    use crate::signalfd::SignalFd;
+
+    #[derive(Debug)]
+    struct WatchedChild {
+        pid: PidT,
+        pidfd: Option<fs::File>,
+    }
+
+    /// Waits for a set of child processes to exit, with optional timeouts.
+    ///
+    /// If the kernel supports it, a pidfd is opened for each child, and the pidfds are polled.
+    /// Otherwise, a `SignalFd` is used to listen for `SIGCHLD`; in this case, `SIGCHLD` must be
+    /// blocked in every thread in the process (`ChildWatcher::new()` blocks it in the current
+    /// thread).
+    ///
+    /// Only the children that have been `add()`ed are reaped.
+    #[derive(Debug)]
+    pub struct ChildWatcher {
+        children: Vec<WatchedChild>,
+        sigfd: Option<SignalFd>,
+    }
+
+    impl ChildWatcher {
+        /// Create a new `ChildWatcher`, using pidfds if they are supported and falling back on
+        /// a `SIGCHLD` `SignalFd` otherwise.
+        pub fn new() -> io::Result<Self> {
+            match pidfd_open(crate::process::getpid()) {
+                Ok(_) => Ok(Self {
+                    children: Vec::new(),
+                    sigfd: None,
+                }),
+                Err(e) if crate::error::is_raw(&e, libc::ENOSYS) => Self::with_signalfd(),
+                Err(e) => Err(e),
+            }
+        }
+
+        /// Create a new `ChildWatcher` that always uses a `SIGCHLD` `SignalFd`, even if pidfds
+        /// are supported.
+        ///
+        /// This blocks `SIGCHLD` in the current thread.
+        pub fn with_signalfd() -> io::Result<Self> {
+            let mut mask = crate::signal::Sigset::empty();
+            mask.add(libc::SIGCHLD)?;
+
+            crate::sigmask::block(&mask)?;
+
+            Ok(Self {
+                children: Vec::new(),
+                sigfd: Some(SignalFd::new(&mask, true)?),
+            })
+        }
+
+        /// Returns whether this watcher is using pidfds (as opposed to a `SignalFd`).
+        #[inline]
+        pub fn uses_pidfds(&self) -> bool {
+            self.sigfd.is_none()
+        }
+
+        /// Start watching the given child process.
+        pub fn add(&mut self, pid: PidT) -> io::Result<()> {
+            if pid <= 0 {
+                return Err(io::Error::from_raw_os_error(libc::EINVAL));
+            } else if self.contains(pid) {
+                return Err(io::Error::from_raw_os_error(libc::EEXIST));
+            }
+
+            let pidfd = if self.uses_pidfds() {
+                Some(pidfd_open(pid)?)
+            } else {
+                None
+            };
+
+            self.children.push(WatchedChild { pid, pidfd });
+
+            Ok(())
+        }
+
+        /// Stop watching the given child process (without reaping it).
+        ///
+        /// Returns whether the process was being watched.
+        pub fn remove(&mut self, pid: PidT) -> bool {
+            let old_len = self.children.len();
+            self.children.retain(|child| child.pid != pid);
+            self.children.len() != old_len
+        }
+
+        /// Returns whether the given process is being watched.
+        #[inline]
+        pub fn contains(&self, pid: PidT) -> bool {
+            self.children.iter().any(|child| child.pid == pid)
+        }
+
+        /// Get the PIDs of all the processes that are being watched (in the order they were
+        /// added).
+        pub fn pids(&self) -> Vec<PidT> {
+            self.children.iter().map(|child| child.pid).collect()
+        }
+
+        #[inline]
+        pub fn len(&self) -> usize {
+            self.children.len()
+        }
+
+        #[inline]
+        pub fn is_empty(&self) -> bool {
+            self.children.is_empty()
+        }
+
+        /// Check whether any of the watched processes have exited, without reaping them.
+        ///
+        /// This uses `waitid()` with `WNOWAIT`, so the process will still be reported by
+        /// `wait_any()` and similar methods.
+        pub fn peek(&self) -> io::Result<Option<(PidT, ProcStatus)>> {
+            for child in self.children.iter() {
+                if let Some(info) = waitid(
+                    WaitidSpec::Pid(child.pid),
+                    WaitidOptions::EXITED | WaitidOptions::NOHANG | WaitidOptions::NOWAIT,
+                )? {
+                    return Ok(Some((info.pid, info.status.into())));
+                }
+            }
+
+            Ok(None)
+        }
+
+        /// Reap one of the watched processes if it has exited, without blocking.
+        ///
+        /// Returns `Ok(None)` if none of the processes have exited. If no processes are being
+        /// watched, fails with `ECHILD`.
+        pub fn try_wait_any(&mut self) -> io::Result<Option<(PidT, ProcStatus, Rusage)>> {
+            if self.children.is_empty() {
+                return Err(io::Error::from_raw_os_error(libc::ECHILD));
+            }
+
+            for i in 0..self.children.len() {
+                if let Some(res) =
+                    wait4(WaitpidSpec::Pid(self.children[i].pid), WaitpidOptions::NOHANG)?
+                {
+                    self.children.remove(i);
+                    return Ok(Some(res));
+                }
+            }
+
+            Ok(None)
+        }
+
+        /// Wait for one of the watched processes to exit and reap it.
+        ///
+        /// If `timeout` is `None`, this will wait indefinitely. If it is `Some`, this will wait
+        /// at most that long, and return `Ok(None)` if none of the processes exited. If no
+        /// processes are being watched, fails with `ECHILD`.
+        pub fn wait_any(
+            &mut self,
+            timeout: Option<Duration>,
+        ) -> io::Result<Option<(PidT, ProcStatus, Rusage)>> {
+            self.wait_any_until(timeout.map(|t| Instant::now() + t))
+        }
+
+        fn wait_any_until(
+            &mut self,
+            deadline: Option<Instant>,
+        ) -> io::Result<Option<(PidT, ProcStatus, Rusage)>> {
+            let mut pollfds = Vec::with_capacity(self.children.len());
+
+            loop {
+                if let Some(res) = self.try_wait_any()? {
+                    return Ok(Some(res));
+                }
+
+                let timeout = match deadline {
+                    Some(deadline) => {
+                        let now = Instant::now();
+                        if now >= deadline {
+                            return Ok(None);
+                        }
+
+                        // Round up so we don't wake up just before the deadline
+                        Some(deadline - now + Duration::from_nanos(999_999))
+                    }
+                    None => None,
+                };
+
+                pollfds.clear();
+                match self.sigfd {
+                    Some(ref sigfd) => pollfds.push(PollFd::new(sigfd.as_raw_fd(), Events::IN)),
+                    None => pollfds.extend(self.children.iter().map(|child| {
+                        PollFd::new(child.pidfd.as_ref().unwrap().as_raw_fd(), Events::IN)
+                    })),
+                }
+
+                match crate::poll::poll(&mut pollfds, timeout) {
+                    Ok(_) => (),
+                    Err(e) if crate::error::is_eintr(&e) => (),
+                    Err(e) => return Err(e),
+                }
+
+                if let Some(ref sigfd) = self.sigfd {
+                    // Drain the pending signals
+                    let mut siginfos = [crate::signalfd::Siginfo::default(); 8];
+                    loop {
+                        match sigfd.read(&mut siginfos) {
+                            Ok(n) if n == siginfos.len() => (),
+                            Ok(_) => break,
+                            Err(e) if crate::error::is_eagain(&e) => break,
+                            Err(e) => return Err(e),
+                        }
+                    }
+                }
+            }
+        }
+
+        /// Wait for all of the watched processes to exit and reap them.
+        ///
+        /// If `deadline` is `Some`, this stops waiting once the deadline has passed. Any
+        /// processes that have not exited by then are still being watched (so, for example,
+        /// they can be retrieved with `pids()` and killed).
+        ///
+        /// Returns the information on every process that was reaped, in the order they were
+        /// reaped.
+        pub fn wait_all(
+            &mut self,
+            deadline: Option<Instant>,
+        ) -> io::Result<Vec<(PidT, ProcStatus, Rusage)>> {
+            let mut results = Vec::with_capacity(self.children.len());
+
+            while !self.children.is_empty() {
+                match self.wait_any_until(deadline)? {
+                    Some(res) => results.push(res),
+                    None => break,
+                }
+            }
+
+            Ok(results)
+        }
+    }
+
+    pub(crate) fn pidfd_open(pid: PidT) -> io::Result<fs::File> {
+        let fd = crate::error::convert_neg_ret(unsafe {
+            libc::syscall(libc::SYS_pidfd_open, pid, 0)
+        })?;
+
+        Ok(unsafe { fs::File::from_raw_fd(fd as Int) })
+    }
+}
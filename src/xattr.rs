@@ -2,9 +2,20 @@ use std::ffi::{CStr, CString, OsStr, OsString};
 use std::io;
 use std::os::unix::prelude::*;
 
+use bitflags::bitflags;
+
 use crate::error;
 use crate::Int;
 
+bitflags! {
+    pub struct SetFlags: Int {
+        /// Fail with `EEXIST` if the attribute already exists.
+        const CREATE = libc::XATTR_CREATE;
+        /// Fail with `ENODATA` (`ENOATTR` on macOS) if the attribute does not already exist.
+        const REPLACE = libc::XATTR_REPLACE;
+    }
+}
+
 enum Target {
     File(CString),
     Link(CString),
@@ -83,6 +94,87 @@ impl Target {
         }
     }
 
+    fn setxattr(&self, name: &CStr, value: &[u8], flags: SetFlags) -> io::Result<()> {
+        unsafe {
+            #[cfg(target_os = "linux")]
+            let res = match self {
+                Self::File(path) => libc::setxattr(
+                    path.as_ptr(),
+                    name.as_ptr(),
+                    value.as_ptr() as *const libc::c_void,
+                    value.len(),
+                    flags.bits(),
+                ),
+                Self::Link(path) => libc::lsetxattr(
+                    path.as_ptr(),
+                    name.as_ptr(),
+                    value.as_ptr() as *const libc::c_void,
+                    value.len(),
+                    flags.bits(),
+                ),
+                Self::Fd(fd) => libc::fsetxattr(
+                    *fd,
+                    name.as_ptr(),
+                    value.as_ptr() as *const libc::c_void,
+                    value.len(),
+                    flags.bits(),
+                ),
+            };
+
+            #[cfg(target_os = "macos")]
+            let res = match self {
+                Self::File(path) => libc::setxattr(
+                    path.as_ptr(),
+                    name.as_ptr(),
+                    value.as_ptr() as *const libc::c_void,
+                    value.len(),
+                    0,
+                    flags.bits(),
+                ),
+                Self::Link(path) => libc::setxattr(
+                    path.as_ptr(),
+                    name.as_ptr(),
+                    value.as_ptr() as *const libc::c_void,
+                    value.len(),
+                    0,
+                    flags.bits() | libc::XATTR_NOFOLLOW,
+                ),
+                Self::Fd(fd) => libc::fsetxattr(
+                    *fd,
+                    name.as_ptr(),
+                    value.as_ptr() as *const libc::c_void,
+                    value.len(),
+                    0,
+                    flags.bits(),
+                ),
+            };
+
+            error::convert_nzero_ret(res)
+        }
+    }
+
+    fn removexattr(&self, name: &CStr) -> io::Result<()> {
+        unsafe {
+            #[cfg(target_os = "linux")]
+            let res = match self {
+                Self::File(path) => libc::removexattr(path.as_ptr(), name.as_ptr()),
+                Self::Link(path) => libc::lremovexattr(path.as_ptr(), name.as_ptr()),
+                Self::Fd(fd) => libc::fremovexattr(*fd, name.as_ptr()),
+            };
+
+            #[cfg(target_os = "macos")]
+            let res = match self {
+                Self::File(path) => libc::removexattr(path.as_ptr(), name.as_ptr(), 0),
+                Self::Link(path) => {
+                    libc::removexattr(path.as_ptr(), name.as_ptr(), libc::XATTR_NOFOLLOW)
+                }
+                Self::Fd(fd) => libc::fremovexattr(*fd, name.as_ptr(), 0),
+            };
+
+            error::convert_nzero_ret(res)
+        }
+    }
+
     fn listxattr(&self, list: &mut [u8]) -> io::Result<usize> {
         unsafe {
             #[cfg(target_os = "linux")]
@@ -185,6 +277,45 @@ pub fn fgetxattr<N: AsRef<OsStr>>(fd: Int, name: N) -> io::Result<Vec<u8>> {
     getxattr_impl(Target::Fd(fd), &c_name)
 }
 
+pub fn setxattr<P: AsRef<OsStr>, N: AsRef<OsStr>>(
+    path: P,
+    name: N,
+    value: &[u8],
+    flags: SetFlags,
+    follow_links: bool,
+) -> io::Result<()> {
+    let c_name = CString::new(name.as_ref().as_bytes())?;
+
+    Target::build_from_path(path, follow_links)?.setxattr(&c_name, value, flags)
+}
+
+pub fn fsetxattr<N: AsRef<OsStr>>(
+    fd: Int,
+    name: N,
+    value: &[u8],
+    flags: SetFlags,
+) -> io::Result<()> {
+    let c_name = CString::new(name.as_ref().as_bytes())?;
+
+    Target::Fd(fd).setxattr(&c_name, value, flags)
+}
+
+pub fn removexattr<P: AsRef<OsStr>, N: AsRef<OsStr>>(
+    path: P,
+    name: N,
+    follow_links: bool,
+) -> io::Result<()> {
+    let c_name = CString::new(name.as_ref().as_bytes())?;
+
+    Target::build_from_path(path, follow_links)?.removexattr(&c_name)
+}
+
+pub fn fremovexattr<N: AsRef<OsStr>>(fd: Int, name: N) -> io::Result<()> {
+    let c_name = CString::new(name.as_ref().as_bytes())?;
+
+    Target::Fd(fd).removexattr(&c_name)
+}
+
 fn listxattr_impl(target: Target) -> io::Result<Vec<OsString>> {
     let mut c_list = Vec::new();
     let init_size = target.listxattr(&mut c_list)?;
@@ -273,4 +404,35 @@ mod tests {
         assert_eq!(flistxattr(f.as_raw_fd()).unwrap(), Vec::<OsString>::new());
         assert_eq!(flistxattr_raw(f.as_raw_fd(), &mut buf).unwrap(), 0);
     }
+
+    #[test]
+    fn test_setxattr_removexattr() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("file");
+        let f = fs::File::create(&path).unwrap();
+
+        // Not all filesystems support user xattrs (tmpfs on older kernels, for example)
+        match setxattr(&path, "user.test", b"abc", SetFlags::CREATE, true) {
+            Err(e) if e.raw_os_error() == Some(libc::ENOTSUP) => return,
+            res => res.unwrap(),
+        }
+
+        assert_eq!(getxattr(&path, "user.test", true).unwrap(), b"abc");
+        assert_eq!(
+            setxattr(&path, "user.test", b"def", SetFlags::CREATE, true)
+                .unwrap_err()
+                .raw_os_error(),
+            Some(libc::EEXIST)
+        );
+
+        fsetxattr(f.as_raw_fd(), "user.test", b"def", SetFlags::REPLACE).unwrap();
+        assert_eq!(fgetxattr(f.as_raw_fd(), "user.test").unwrap(), b"def");
+
+        setxattr(&path, "user.test2", b"", SetFlags::empty(), false).unwrap();
+        assert_eq!(listxattr(&path, false).unwrap().len(), 2);
+
+        removexattr(&path, "user.test2", false).unwrap();
+        fremovexattr(f.as_raw_fd(), "user.test").unwrap();
+        assert_eq!(flistxattr(f.as_raw_fd()).unwrap(), Vec::<OsString>::new());
+    }
 }
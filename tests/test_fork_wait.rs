@@ -77,3 +77,416 @@ fn test_fork_wait6() {
         }
     }
 }
+
+#[test]
+fn test_fork_waitpid_signaled() {
+    match fork().unwrap() {
+        0 => loop {
+            std::thread::sleep(std::time::Duration::from_secs(1));
+        },
+        pid => {
+            simple_libc::kill(simple_libc::KillSpec::Pid(pid), libc::SIGKILL).unwrap();
+
+            let (wpid, status) =
+                wait::waitpid(wait::WaitpidSpec::Pid(pid), wait::WaitpidOptions::empty())
+                    .unwrap()
+                    .unwrap();
+
+            assert_eq!(pid, wpid);
+
+            assert_eq!(status, wait::ProcStatus::Signaled(libc::SIGKILL, false));
+            assert_eq!(status.to_shell_code(), 128 + libc::SIGKILL);
+            assert_eq!(status.to_string(), "killed by SIGKILL");
+        }
+    }
+}
+
+#[test]
+fn test_procstatus_convert() {
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::ExitStatus;
+
+    for &status in &[
+        wait::ProcStatus::Exited(0),
+        wait::ProcStatus::Exited(3),
+        wait::ProcStatus::Signaled(libc::SIGTERM, false),
+        wait::ProcStatus::Signaled(libc::SIGSEGV, true),
+        wait::ProcStatus::Stopped(libc::SIGTSTP),
+        wait::ProcStatus::Continued,
+    ] {
+        let exit_status = ExitStatus::from(status);
+        assert_eq!(wait::ProcStatus::from(exit_status), status);
+
+        match status {
+            wait::ProcStatus::Exited(code) => assert_eq!(exit_status.code(), Some(code)),
+            wait::ProcStatus::Signaled(sig, core_dumped) => {
+                assert_eq!(exit_status.signal(), Some(sig));
+                assert_eq!(exit_status.core_dumped(), core_dumped);
+            }
+            wait::ProcStatus::Stopped(sig) => assert_eq!(exit_status.stopped_signal(), Some(sig)),
+            wait::ProcStatus::Continued => assert!(exit_status.continued()),
+        }
+    }
+
+    assert_eq!(wait::ProcStatus::Exited(3).to_shell_code(), 3);
+    assert_eq!(
+        wait::ProcStatus::Signaled(libc::SIGSEGV, true).to_shell_code(),
+        128 + libc::SIGSEGV
+    );
+    assert_eq!(wait::ProcStatus::Continued.to_shell_code(), 0);
+
+    assert_eq!(
+        wait::ProcStatus::Exited(3).to_string(),
+        "exited with status 3"
+    );
+    assert_eq!(
+        wait::ProcStatus::Signaled(libc::SIGSEGV, true).to_string(),
+        "killed by SIGSEGV (core dumped)"
+    );
+    assert_eq!(
+        wait::ProcStatus::Stopped(libc::SIGTSTP).to_string(),
+        "stopped by SIGTSTP"
+    );
+    assert_eq!(wait::ProcStatus::Continued.to_string(), "continued");
+
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "netbsd",
+        target_os = "freebsd",
+        target_os = "dragonfly",
+    ))]
+    {
+        assert_eq!(
+            wait::ProcStatus::from(wait::WaitidStatus::Dumped(libc::SIGSEGV)),
+            wait::ProcStatus::Signaled(libc::SIGSEGV, true)
+        );
+        assert_eq!(
+            wait::ProcStatus::from(wait::WaitidStatus::Trapped(libc::SIGTRAP)),
+            wait::ProcStatus::Stopped(libc::SIGTRAP)
+        );
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn check_child_watcher(mut watcher: wait::ChildWatcher) {
+    use std::time::{Duration, Instant};
+
+    fn spawn_sleeper() -> simple_libc::PidT {
+        match fork().unwrap() {
+            0 => loop {
+                std::thread::sleep(Duration::from_secs(1));
+            },
+            pid => pid,
+        }
+    }
+
+    assert!(watcher.is_empty());
+    assert_eq!(
+        watcher.wait_any(None).unwrap_err().raw_os_error(),
+        Some(libc::ECHILD)
+    );
+
+    let exiter = match fork().unwrap() {
+        0 => unsafe { libc::_exit(3) },
+        pid => pid,
+    };
+    let sleeper1 = spawn_sleeper();
+    let sleeper2 = spawn_sleeper();
+
+    watcher.add(exiter).unwrap();
+    watcher.add(sleeper1).unwrap();
+    watcher.add(sleeper2).unwrap();
+    assert_eq!(watcher.len(), 3);
+    assert_eq!(
+        watcher.add(exiter).unwrap_err().raw_os_error(),
+        Some(libc::EEXIST)
+    );
+
+    // Peeking doesn't reap the process
+    let peek_deadline = Instant::now() + Duration::from_secs(5);
+    while watcher.peek().unwrap().is_none() {
+        assert!(Instant::now() < peek_deadline);
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    assert_eq!(
+        watcher.peek().unwrap(),
+        Some((exiter, wait::ProcStatus::Exited(3)))
+    );
+
+    let (pid, status, _) = watcher
+        .wait_any(Some(Duration::from_secs(5)))
+        .unwrap()
+        .unwrap();
+    assert_eq!(pid, exiter);
+    assert_eq!(status, wait::ProcStatus::Exited(3));
+    assert!(!watcher.contains(exiter));
+
+    // The sleepers won't exit on their own
+    let start = Instant::now();
+    assert_eq!(
+        watcher.wait_any(Some(Duration::from_millis(50))).unwrap(),
+        None
+    );
+    assert!(start.elapsed() >= Duration::from_millis(50));
+
+    assert_eq!(watcher.peek().unwrap(), None);
+
+    let results = watcher
+        .wait_all(Some(Instant::now() + Duration::from_millis(50)))
+        .unwrap();
+    assert!(results.is_empty());
+    assert_eq!(watcher.pids(), vec![sleeper1, sleeper2]);
+
+    for &pid in &[sleeper1, sleeper2] {
+        simple_libc::kill(simple_libc::KillSpec::Pid(pid), libc::SIGKILL).unwrap();
+    }
+
+    let mut results = watcher
+        .wait_all(Some(Instant::now() + Duration::from_secs(5)))
+        .unwrap();
+    results.sort_by_key(|res| res.0);
+
+    let mut expected_pids = vec![sleeper1, sleeper2];
+    expected_pids.sort_unstable();
+
+    assert_eq!(
+        results.iter().map(|res| res.0).collect::<Vec<_>>(),
+        expected_pids
+    );
+    for (_, status, _) in results {
+        assert_eq!(status, wait::ProcStatus::Signaled(libc::SIGKILL, false));
+    }
+    assert!(watcher.is_empty());
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_child_watcher() {
+    check_child_watcher(wait::ChildWatcher::new().unwrap());
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_child_watcher_signalfd() {
+    // SIGCHLD has to be blocked in every thread for the SignalFd fallback to work reliably, so
+    // run the test in a (single-threaded) child process.
+    match fork().unwrap() {
+        0 => {
+            let res = std::panic::catch_unwind(|| {
+                let watcher = wait::ChildWatcher::with_signalfd().unwrap();
+                assert!(!watcher.uses_pidfds());
+                check_child_watcher(watcher);
+            });
+
+            unsafe { libc::_exit(if res.is_ok() { 0 } else { 1 }) }
+        }
+        pid => {
+            let (_, status) =
+                wait::waitpid(wait::WaitpidSpec::Pid(pid), wait::WaitpidOptions::empty())
+                    .unwrap()
+                    .unwrap();
+
+            assert_eq!(status, wait::ProcStatus::Exited(0));
+        }
+    }
+}
+
+// Fork a child that signals that it's ready and then waits to be killed. If `ignore_term` is
+// true, it ignores SIGTERM. If `new_pgrp` is true, it moves itself into a new process group.
+#[cfg(target_os = "linux")]
+fn spawn_waiting_child(ignore_term: bool, new_pgrp: bool) -> libc::pid_t {
+    use std::io::Read;
+    use std::os::unix::io::AsRawFd;
+
+    let (mut r, w) = simple_libc::pipe().unwrap();
+
+    match fork().unwrap() {
+        0 => unsafe {
+            if ignore_term {
+                libc::signal(libc::SIGTERM, libc::SIG_IGN);
+            }
+            if new_pgrp {
+                libc::setpgid(0, 0);
+            }
+
+            libc::write(w.as_raw_fd(), b"x".as_ptr() as *const libc::c_void, 1);
+            loop {
+                libc::pause();
+            }
+        },
+        pid => {
+            drop(w);
+            let mut buf = [0; 1];
+            r.read_exact(&mut buf).unwrap();
+            pid
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_terminate() {
+    use std::io::Read;
+    use std::os::unix::io::AsRawFd;
+    use std::time::Duration;
+
+    use simple_libc::process::getpid;
+    use simple_libc::signal::{Signal, SIGKILL, SIGTERM};
+    use simple_libc::terminate::{terminate, TerminatePolicy, TerminateTarget, TerminatedProcess};
+
+    let policy = TerminatePolicy::new(Signal::Term, Duration::from_millis(200));
+
+    for &target in [
+        TerminateTarget::Pid(0),
+        TerminateTarget::Pgid(1),
+        TerminateTarget::Tree(getpid()),
+        TerminateTarget::Descendants(-1),
+    ]
+    .iter()
+    {
+        assert_eq!(
+            terminate(target, &policy).unwrap_err().raw_os_error(),
+            Some(libc::EINVAL)
+        );
+    }
+
+    // Exits after SIGTERM
+    let pid = spawn_waiting_child(false, false);
+    let report = terminate(TerminateTarget::Pid(pid), &policy).unwrap();
+    assert_eq!(
+        report.exited,
+        vec![TerminatedProcess {
+            pid,
+            status: Some(wait::ProcStatus::Signaled(SIGTERM, false)),
+        }]
+    );
+    assert_eq!(report.killed, vec![]);
+    assert_eq!(report.remaining, vec![]);
+
+    // Already reaped
+    assert_eq!(
+        terminate(TerminateTarget::Pid(pid), &policy)
+            .unwrap_err()
+            .raw_os_error(),
+        Some(libc::ESRCH)
+    );
+
+    // Ignores SIGTERM
+    let pid = spawn_waiting_child(true, false);
+    let report = terminate(TerminateTarget::Pid(pid), &policy).unwrap();
+    assert_eq!(report.exited, vec![]);
+    assert_eq!(
+        report.killed,
+        vec![TerminatedProcess {
+            pid,
+            status: Some(wait::ProcStatus::Signaled(SIGKILL, false)),
+        }]
+    );
+    assert_eq!(report.remaining, vec![]);
+
+    // Process group
+    let pid = spawn_waiting_child(false, true);
+    let report = terminate(TerminateTarget::Pgid(pid), &policy).unwrap();
+    assert_eq!(
+        report.exited,
+        vec![TerminatedProcess {
+            pid,
+            status: Some(wait::ProcStatus::Signaled(SIGTERM, false)),
+        }]
+    );
+    assert_eq!(report.killed, vec![]);
+
+    // A child that exits on SIGTERM, with a grandchild that ignores it
+    let (mut r, w) = simple_libc::pipe().unwrap();
+    let pid = match fork().unwrap() {
+        0 => {
+            let grandchild = spawn_waiting_child(true, false);
+            unsafe {
+                libc::write(
+                    w.as_raw_fd(),
+                    &grandchild as *const libc::pid_t as *const libc::c_void,
+                    std::mem::size_of::<libc::pid_t>(),
+                );
+                loop {
+                    libc::pause();
+                }
+            }
+        }
+        pid => pid,
+    };
+    drop(w);
+    let mut buf = [0; std::mem::size_of::<libc::pid_t>()];
+    r.read_exact(&mut buf).unwrap();
+    let grandchild = libc::pid_t::from_ne_bytes(buf);
+
+    let report = terminate(TerminateTarget::Tree(pid), &policy).unwrap();
+    assert_eq!(
+        report.exited,
+        vec![TerminatedProcess {
+            pid,
+            status: Some(wait::ProcStatus::Signaled(SIGTERM, false)),
+        }]
+    );
+    assert_eq!(
+        report.killed,
+        vec![TerminatedProcess {
+            pid: grandchild,
+            status: None,
+        }]
+    );
+    assert_eq!(report.remaining, vec![]);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_terminate_ancestor() {
+    use std::time::Duration;
+
+    use simple_libc::process::getppid;
+    use simple_libc::signal::Signal;
+    use simple_libc::terminate::{terminate, TerminatePolicy, TerminateTarget, TerminatedProcess};
+
+    // The child acts as the "parent" of a sibling (which waits forever) and of a process that
+    // terminates all of its parent's descendants (which includes itself).
+    let pid = match fork().unwrap() {
+        0 => unsafe {
+            // If terminate() stops the calling process, fail instead of hanging (and put
+            // everything in a new process group so it can be cleaned up)
+            libc::alarm(10);
+            libc::setpgid(0, 0);
+
+            let sibling = spawn_waiting_child(false, false);
+
+            match fork().unwrap() {
+                0 => {
+                    let policy = TerminatePolicy::new(Signal::Term, Duration::from_millis(200));
+                    let ok = match terminate(TerminateTarget::Descendants(getppid()), &policy) {
+                        Ok(report) => {
+                            report.exited
+                                == vec![TerminatedProcess {
+                                    pid: sibling,
+                                    status: None,
+                                }]
+                                && report.killed.is_empty()
+                                && report.remaining.is_empty()
+                        }
+                        Err(_) => false,
+                    };
+                    libc::_exit(if ok { 0 } else { 1 });
+                }
+                pid => {
+                    match wait::waitpid(wait::WaitpidSpec::Pid(pid), wait::WaitpidOptions::empty())
+                    {
+                        Ok(Some((_, wait::ProcStatus::Exited(0)))) => libc::_exit(0),
+                        _ => libc::_exit(1),
+                    }
+                }
+            }
+        },
+        pid => pid,
+    };
+
+    let status = wait::waitpid(wait::WaitpidSpec::Pid(pid), wait::WaitpidOptions::empty());
+    let _ = simple_libc::killpg(pid, libc::SIGKILL);
+    assert_eq!(status.unwrap(), Some((pid, wait::ProcStatus::Exited(0))));
+}
@@ -2,11 +2,19 @@ simple_libc::attr_group! {
     #![cfg(target_os = "linux")]
 
     use std::io;
+    use std::os::unix::io::AsRawFd;
+    use std::time::Duration;
+
+    use simple_libc::pollers::{Events, PollPoller, Poller};
+    use simple_libc::process::fork;
+    use simple_libc::sigaction::{self, Sigaction, SignalDispositions};
+    use simple_libc::wait;
 
     use simple_libc::sigmask;
+    use simple_libc::siginfo::{self, SigInfo, SigVal};
     use simple_libc::signalfd;
     use simple_libc::process::{getpid, gettid, getuid};
-    use simple_libc::signal::{Sigset, SIGUSR1, SIGUSR2};
+    use simple_libc::signal::{get_rtsig_minmax, Sigset, SIGKILL, SIGSTOP, SIGUSR1, SIGUSR2};
     use simple_libc::{tgkill, Int, PidT, UidT};
 
     #[test]
@@ -65,10 +73,163 @@ simple_libc::attr_group! {
         assert_eq!(sigs[1].pid as PidT, getpid());
         assert_eq!(sigs[1].uid as UidT, getuid());
 
+        // Decode the signal
+        tgkill(getpid(), gettid(), SIGUSR2).unwrap();
+        match sigfd.read_info().unwrap() {
+            SigInfo::User { sig, pid, uid, value, .. } => {
+                assert_eq!(sig, SIGUSR2);
+                assert_eq!(pid, getpid());
+                assert_eq!(uid, getuid());
+                assert_eq!(value, None);
+            }
+            info => panic!("{:?}", info),
+        }
+
+        // Now with a value
+        siginfo::tgsigqueue(getpid(), gettid(), SIGUSR1, SigVal::from_int(5)).unwrap();
+        let siginfo = sigfd.read_one().unwrap();
+        assert_eq!(
+            siginfo.decode(),
+            SigInfo::User {
+                sig: SIGUSR1,
+                code: libc::SI_QUEUE,
+                pid: getpid(),
+                uid: getuid(),
+                value: Some(SigVal::from_int(5)),
+            },
+        );
+
         // Restore our signal mask
         sigmask::unblock(&new_mask).unwrap();
         assert_eq!(sigmask::getmask().unwrap(), orig_mask);
         sigmask::setmask(&orig_mask).unwrap();
         assert_eq!(sigmask::getmask().unwrap(), orig_mask);
     }
+
+    fn is_blocked(sig: Int) -> bool {
+        let mut raw_mask = unsafe { std::mem::zeroed() };
+        assert_eq!(
+            unsafe { libc::pthread_sigmask(0, std::ptr::null(), &mut raw_mask) },
+            0
+        );
+        unsafe { libc::sigismember(&raw_mask, sig) == 1 }
+    }
+
+    #[test]
+    fn test_signalfd_blocking() {
+        assert!(!is_blocked(SIGUSR1));
+        assert!(!is_blocked(SIGUSR2));
+
+        let mut mask = Sigset::empty();
+        mask.add(SIGUSR1).unwrap();
+
+        // Draining requires nonblocking mode
+        let sigfd = signalfd::SignalFd::new_blocking(&mask, false).unwrap();
+        assert!(is_blocked(SIGUSR1));
+        assert_eq!(
+            sigfd.drain().unwrap_err().raw_os_error(),
+            Some(libc::EINVAL)
+        );
+        drop(sigfd);
+        assert!(!is_blocked(SIGUSR1));
+
+        let mut sigfd = signalfd::SignalFd::new_blocking(&mask, true).unwrap();
+        assert!(is_blocked(SIGUSR1));
+        assert_eq!(sigfd.drain().unwrap().count(), 0);
+
+        tgkill(getpid(), gettid(), SIGUSR1).unwrap();
+        let infos = sigfd
+            .drain()
+            .unwrap()
+            .collect::<io::Result<Vec<SigInfo>>>()
+            .unwrap();
+        assert_eq!(infos.len(), 1);
+        assert_eq!(infos[0].sig(), SIGUSR1);
+        assert_eq!(sigfd.drain().unwrap().count(), 0);
+
+        // Switch to SIGUSR2
+        mask.del(SIGUSR1).unwrap();
+        mask.add(SIGUSR2).unwrap();
+        sigfd.set_mask(&mask).unwrap();
+        assert!(!is_blocked(SIGUSR1));
+        assert!(is_blocked(SIGUSR2));
+
+        let mut poller = PollPoller::new().unwrap();
+        sigfd.register_with(&mut poller).unwrap();
+        assert_eq!(poller.poll(Some(Duration::from_secs(0))).unwrap(), vec![]);
+
+        tgkill(getpid(), gettid(), SIGUSR2).unwrap();
+        assert_eq!(
+            poller.poll(Some(Duration::from_secs(0))).unwrap(),
+            vec![(sigfd.as_raw_fd(), Events::READ)],
+        );
+
+        let infos = sigfd
+            .drain()
+            .unwrap()
+            .collect::<io::Result<Vec<SigInfo>>>()
+            .unwrap();
+        assert_eq!(infos.len(), 1);
+        assert_eq!(infos[0].sig(), SIGUSR2);
+        sigfd.unregister_from(&mut poller).unwrap();
+
+        drop(sigfd);
+        assert!(!is_blocked(SIGUSR1));
+        assert!(!is_blocked(SIGUSR2));
+    }
+
+    #[test]
+    fn test_signal_dispositions() {
+        // Changing signal dispositions affects every thread, so do this in a (single-threaded)
+        // child process.
+        match fork().unwrap() {
+            0 => {
+                let res = std::panic::catch_unwind(check_signal_dispositions);
+                unsafe { libc::_exit(if res.is_ok() { 0 } else { 1 }) }
+            }
+            pid => {
+                let (_, status) =
+                    wait::waitpid(wait::WaitpidSpec::Pid(pid), wait::WaitpidOptions::empty())
+                        .unwrap()
+                        .unwrap();
+
+                assert_eq!(status, wait::ProcStatus::Exited(0));
+            }
+        }
+    }
+
+    fn check_signal_dispositions() {
+        let (sigrtmin, sigrtmax) = get_rtsig_minmax().unwrap();
+
+        let orig = SignalDispositions::capture().unwrap();
+        assert!(orig.get(SIGKILL).is_none());
+        assert!(orig.get(SIGSTOP).is_none());
+        assert!(orig.get(SIGUSR1).is_some());
+        assert!(orig.get(sigrtmin).is_some());
+        assert!(orig.get(sigrtmax).is_some());
+        assert!(orig.diff(&orig).is_empty());
+
+        sigaction::sig_setaction(SIGUSR1, Sigaction::empty_handler()).unwrap();
+        sigaction::sig_setaction(SIGUSR2, Sigaction::ignore()).unwrap();
+
+        let changed = SignalDispositions::capture().unwrap();
+        assert_eq!(
+            orig.diff(&changed),
+            vec![
+                (SIGUSR1, Sigaction::default(), Sigaction::empty_handler()),
+                (SIGUSR2, Sigaction::default(), Sigaction::ignore()),
+            ],
+        );
+
+        // Handlers are reset, but ignored signals stay ignored
+        changed.reset_handlers().unwrap();
+        assert_eq!(sigaction::sig_getaction(SIGUSR1).unwrap(), Sigaction::default());
+        assert_eq!(sigaction::sig_getaction(SIGUSR2).unwrap(), Sigaction::ignore());
+
+        changed.restore().unwrap();
+        assert_eq!(SignalDispositions::capture().unwrap(), changed);
+
+        orig.restore().unwrap();
+        assert_eq!(SignalDispositions::capture().unwrap(), orig);
+    }
 }
@@ -0,0 +1,76 @@
+simple_libc::attr_group! {
+    #![cfg(target_os = "linux")]
+
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    use simple_libc::prctl::{find_inconsistent_threads, CapState};
+    use simple_libc::process::gettid;
+    use simple_libc::signal::{get_rtsig_minmax, Sigset};
+    use simple_libc::sigmask;
+
+    // set_all_threads() changes the disposition of a signal and signals every thread in the
+    // process, so it's tested in its own test binary (where no other tests are running).
+    #[test]
+    fn test_set_all_threads() {
+        let (_, sigrtmax) = get_rtsig_minmax().unwrap();
+        let sig = sigrtmax - 1;
+        let timeout = Duration::from_secs(10);
+
+        // A thread with the signal blocked is detected (instead of hanging)
+        let (tid_tx, tid_rx) = mpsc::channel();
+        let (done_tx, done_rx) = mpsc::channel::<()>();
+        let handle = std::thread::spawn(move || {
+            let mut set = Sigset::empty();
+            set.add(sig).unwrap();
+            sigmask::block(&set).unwrap();
+
+            tid_tx.send(gettid()).unwrap();
+            done_rx.recv().unwrap();
+        });
+        tid_rx.recv().unwrap();
+        assert_eq!(
+            CapState::get_current()
+                .unwrap()
+                .set_all_threads(sig, timeout)
+                .unwrap_err()
+                .raw_os_error(),
+            Some(libc::EINVAL)
+        );
+        done_tx.send(()).unwrap();
+        handle.join().unwrap();
+
+        let orig_state = CapState::get_current().unwrap();
+        let cap = match orig_state.effective.iter().next() {
+            Some(cap) => cap,
+            None => return,
+        };
+
+        let (tid_tx, tid_rx) = mpsc::channel();
+        let (done_tx, done_rx) = mpsc::channel::<()>();
+
+        let handle = std::thread::spawn(move || {
+            let mut state = CapState::get_current().unwrap();
+            state.effective.drop(cap);
+            state.set_current().unwrap();
+
+            tid_tx.send(gettid()).unwrap();
+            done_rx.recv().unwrap();
+
+            CapState::get_current().unwrap()
+        });
+
+        let tid = tid_rx.recv().unwrap();
+
+        let inconsistent = find_inconsistent_threads(0).unwrap();
+        assert!(inconsistent.iter().any(|state| state.tid == tid));
+
+        orig_state.set_all_threads(sig, timeout).unwrap();
+
+        let inconsistent = find_inconsistent_threads(0).unwrap();
+        assert!(!inconsistent.iter().any(|state| state.tid == tid));
+
+        done_tx.send(()).unwrap();
+        assert_eq!(handle.join().unwrap(), orig_state);
+    }
+}